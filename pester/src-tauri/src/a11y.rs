@@ -0,0 +1,71 @@
+use serde::{Deserialize, Serialize};
+use tauri::State;
+use tokio::sync::broadcast;
+
+/// Summaries are cut to this many characters; helpers read them aloud.
+const SUMMARY_CHARS: usize = 140;
+
+/// Events that can queue up before a slow subscriber drops the oldest.
+const CHANNEL_CAPACITY: usize = 64;
+
+/// Structured events for screen-reader helpers and other assistive tools,
+/// published independently of the webview's DOM. External tools subscribe
+/// through the IPC endpoint.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum A11yEvent {
+    MessageReceived {
+        conversation: String,
+        sender: String,
+        summary: String,
+    },
+    StatusChanged {
+        status: String,
+    },
+    UnreadChanged {
+        total: u32,
+    },
+    ReminderDue {
+        conversation: String,
+        summary: String,
+    },
+}
+
+pub struct A11yBus(broadcast::Sender<A11yEvent>);
+
+impl Default for A11yBus {
+    fn default() -> Self {
+        Self(broadcast::channel(CHANNEL_CAPACITY).0)
+    }
+}
+
+fn summarize(text: &str) -> String {
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if text.chars().count() <= SUMMARY_CHARS {
+        return text;
+    }
+    let cut: String = text.chars().take(SUMMARY_CHARS - 1).collect();
+    format!("{}…", cut.trim_end())
+}
+
+impl A11yBus {
+    pub fn publish(&self, mut event: A11yEvent) {
+        if let A11yEvent::MessageReceived { summary, .. } | A11yEvent::ReminderDue { summary, .. } =
+            &mut event
+        {
+            *summary = summarize(summary);
+        }
+        // No subscribers is the normal case.
+        let _ = self.0.send(event);
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<A11yEvent> {
+        self.0.subscribe()
+    }
+}
+
+/// For events raised by the frontend.
+#[tauri::command]
+pub fn publish_a11y_event(bus: State<'_, A11yBus>, event: A11yEvent) {
+    bus.publish(event);
+}
@@ -0,0 +1,118 @@
+use serde_json::{json, Value};
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::conversations::{self, Flag};
+use crate::unread::UnreadState;
+
+type Handler = fn(&AppHandle, &Value) -> Result<Value, String>;
+
+/// Every conversation action, implemented once. Global shortcuts, the tray,
+/// the IPC endpoint and the frontend all go through `invoke` so they can't
+/// drift apart.
+const ACTIONS: &[(&str, Handler)] = &[
+    ("open_chat", |app, args| {
+        open_chat(app, conversation(args)?);
+        Ok(Value::Null)
+    }),
+    ("mark_read", |app, args| {
+        app.state::<UnreadState>()
+            .mark_read(app, conversation(args)?);
+        Ok(Value::Null)
+    }),
+    ("mark_unread", |app, args| {
+        app.state::<UnreadState>()
+            .mark_unread(app, conversation(args)?.to_string());
+        Ok(Value::Null)
+    }),
+    ("mark_all_read", |app, _| {
+        let unread = app.state::<UnreadState>();
+        for conversation in unread.counts().keys() {
+            unread.mark_read(app, conversation);
+        }
+        Ok(Value::Null)
+    }),
+    ("next_unread", |app, args| jump_unread(app, args, true)),
+    ("prev_unread", |app, args| jump_unread(app, args, false)),
+    ("pin", |app, args| flag(app, args, Flag::Pinned, true)),
+    ("unpin", |app, args| flag(app, args, Flag::Pinned, false)),
+    ("archive", |app, args| flag(app, args, Flag::Archived, true)),
+    ("unarchive", |app, args| {
+        flag(app, args, Flag::Archived, false)
+    }),
+    ("mute", |app, args| {
+        conversations::mute(app, conversation(args)?, None)?;
+        Ok(Value::Null)
+    }),
+    ("unmute", |app, args| {
+        conversations::unmute(app, conversation(args)?)?;
+        Ok(Value::Null)
+    }),
+];
+
+fn conversation(args: &Value) -> Result<&str, String> {
+    args["conversation"]
+        .as_str()
+        .filter(|c| !c.is_empty())
+        .ok_or_else(|| "Missing \"conversation\" argument".to_string())
+}
+
+fn flag(app: &AppHandle, args: &Value, flag: Flag, on: bool) -> Result<Value, String> {
+    conversations::set(app, conversation(args)?, flag, on)?;
+    Ok(Value::Null)
+}
+
+/// Open the unread conversation after (or before) `args.conversation` in
+/// alphabetical order, wrapping around. Returns the opened conversation.
+fn jump_unread(app: &AppHandle, args: &Value, forward: bool) -> Result<Value, String> {
+    let current = args["conversation"].as_str().unwrap_or_default();
+    let mut unread: Vec<String> = app
+        .state::<UnreadState>()
+        .counts()
+        .into_keys()
+        .filter(|c| c != current)
+        .collect();
+    unread.sort();
+    if !forward {
+        unread.reverse();
+    }
+
+    let after = |c: &String| {
+        if forward {
+            c.as_str() > current
+        } else {
+            c.as_str() < current
+        }
+    };
+    let Some(target) = unread.iter().find(|c| after(c)).or(unread.first()).cloned() else {
+        return Ok(Value::Null);
+    };
+
+    open_chat(app, &target);
+    Ok(json!(target))
+}
+
+/// Bring the window up on the chat with `conversation`.
+pub fn open_chat(app: &AppHandle, conversation: &str) {
+    crate::show_main_window(app);
+    let _ = app.emit("tray-action", format!("chat:{}", conversation));
+}
+
+/// Run the action called `name`.
+pub fn invoke(app: &AppHandle, name: &str, args: &Value) -> Result<Value, String> {
+    let (_, handler) = ACTIONS
+        .iter()
+        .find(|(n, _)| *n == name)
+        .ok_or_else(|| format!("Unknown action: {}", name))?;
+    log::debug!("Running action {}", name);
+    handler(app, args)
+}
+
+#[tauri::command]
+pub fn invoke_action(app: AppHandle, name: String, args: Option<Value>) -> Result<Value, String> {
+    invoke(&app, &name, &args.unwrap_or(Value::Null))
+}
+
+#[tauri::command]
+pub fn list_actions() -> Vec<&'static str> {
+    ACTIONS.iter().map(|(name, _)| *name).collect()
+}
@@ -0,0 +1,61 @@
+use std::collections::HashMap;
+
+use tauri::{AppHandle, Emitter, Manager, WebviewWindow};
+
+use crate::prefs;
+
+const PINNED_KEY: &str = "always_on_top";
+/// Tray menu item toggling it for the main window.
+pub const MENU_ID: &str = "always_on_top";
+
+/// Window label → whether it floats over other apps. Windows not listed
+/// keep what they were created with.
+type Pinned = HashMap<String, bool>;
+
+/// Whether the main window floats, as shown in the tray menu.
+pub fn main_pinned(app: &AppHandle) -> bool {
+    app.get_webview_window("main")
+        .and_then(|w| w.is_always_on_top().ok())
+        .unwrap_or(false)
+}
+
+/// Give a newly opened window its saved setting.
+pub fn restore(window: &WebviewWindow) {
+    let pinned: Pinned = prefs::load(window.app_handle(), PINNED_KEY).unwrap_or_default();
+    if let Some(&on) = pinned.get(window.label()) {
+        if let Err(e) = window.set_always_on_top(on) {
+            log::warn!("Failed to restore always-on-top: {}", e);
+        }
+    }
+}
+
+pub fn set(app: &AppHandle, label: &str, pinned: bool) -> Result<(), String> {
+    let window = app
+        .get_webview_window(label)
+        .ok_or_else(|| format!("No window {}", label))?;
+    window
+        .set_always_on_top(pinned)
+        .map_err(|e| e.to_string())?;
+
+    let mut saved: Pinned = prefs::load(app, PINNED_KEY).unwrap_or_default();
+    saved.insert(label.to_string(), pinned);
+    prefs::save(app, PINNED_KEY, &saved)?;
+    log::debug!("Window {} always on top: {}", label, pinned);
+    let _ = app.emit_to(label, "always-on-top-changed", pinned);
+    Ok(())
+}
+
+/// Keep `window_label` above other apps, e.g. a chat popout during a call.
+/// Remembered for the next time the window opens.
+#[tauri::command]
+pub fn set_always_on_top(app: AppHandle, window_label: String, pinned: bool) -> Result<(), String> {
+    set(&app, &window_label, pinned)
+}
+
+#[tauri::command]
+pub fn is_always_on_top(app: AppHandle, window_label: String) -> Result<bool, String> {
+    app.get_webview_window(&window_label)
+        .ok_or_else(|| format!("No window {}", window_label))?
+        .is_always_on_top()
+        .map_err(|e| e.to_string())
+}
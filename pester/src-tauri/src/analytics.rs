@@ -0,0 +1,235 @@
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Datelike, Duration as Days, Local, NaiveDate, TimeZone, Timelike, Weekday};
+use rusqlite::params;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager, State};
+
+use crate::scheduler::{Priority, Scheduler};
+use crate::storage::Database;
+use crate::{focus, notifications, prefs};
+
+/// Opt-in usage stats, computed from local history only. Nothing here is
+/// ever sent anywhere.
+const SETTINGS_KEY: &str = "usage_analytics";
+/// Local date → seconds spent with the OS in a focus mode.
+const FOCUS_KEY: &str = "focus_seconds";
+/// Monday of the last week the Sunday summary went out for.
+const NOTIFIED_KEY: &str = "weekly_summary_notified";
+
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(60);
+/// A gap longer than this between samples (sleep, battery deferral) isn't
+/// counted as focus time.
+const MAX_SAMPLE_GAP: Duration = Duration::from_secs(5 * 60);
+const FOCUS_RETENTION_DAYS: i64 = 8 * 7;
+
+/// Replies later than this aren't counted towards response time.
+const MAX_RESPONSE_MS: i64 = 12 * 60 * 60 * 1000;
+const TOP_CONTACTS: u32 = 5;
+/// The Sunday summary goes out from this local hour on.
+const NOTIFY_HOUR: u32 = 18;
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct AnalyticsSettings {
+    pub enabled: bool,
+    pub sunday_notification: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ContactActivity {
+    pub contact: String,
+    pub messages: u32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WeeklySummary {
+    /// Monday the week started, local time.
+    pub week_start: NaiveDate,
+    pub sent: u32,
+    pub received: u32,
+    pub most_active: Vec<ContactActivity>,
+    /// Mean time to reply to an incoming message, `None` without replies.
+    pub avg_response_secs: Option<u64>,
+    pub focus_hours: f64,
+}
+
+type FocusSeconds = BTreeMap<NaiveDate, u64>;
+
+fn settings(app: &AppHandle) -> AnalyticsSettings {
+    prefs::load(app, SETTINGS_KEY).unwrap_or_default()
+}
+
+fn week_start(now: DateTime<Local>) -> NaiveDate {
+    let today = now.date_naive();
+    today - Days::days(today.weekday().num_days_from_monday() as i64)
+}
+
+fn record_focus(app: &AppHandle, seconds: u64) {
+    let today = Local::now().date_naive();
+    let mut focus: FocusSeconds = prefs::load(app, FOCUS_KEY).unwrap_or_default();
+    *focus.entry(today).or_default() += seconds;
+    focus.retain(|day, _| *day > today - Days::days(FOCUS_RETENTION_DAYS));
+    if let Err(e) = prefs::save(app, FOCUS_KEY, &focus) {
+        log::warn!("Failed to save focus time: {}", e);
+    }
+}
+
+fn summarize(app: &AppHandle, db: &Database) -> Result<WeeklySummary, String> {
+    let week_start = week_start(Local::now());
+    let since = Local
+        .from_local_datetime(&week_start.and_hms_opt(0, 0, 0).unwrap())
+        .earliest()
+        .map(|t| t.timestamp_millis())
+        .unwrap_or_default();
+
+    // Outgoing messages are the ones not from the conversation's contact.
+    let (sent, received, most_active, avg_response_ms) = db.with(|conn| {
+        let (sent, received) = conn.query_row(
+            "SELECT COALESCE(SUM(from_user_id != conversation_id), 0),
+                    COALESCE(SUM(from_user_id = conversation_id), 0)
+             FROM messages WHERE timestamp >= ?1",
+            [since],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+
+        let mut stmt = conn.prepare(
+            "SELECT conversation_id, COUNT(*) AS n FROM messages WHERE timestamp >= ?1
+             GROUP BY conversation_id ORDER BY n DESC LIMIT ?2",
+        )?;
+        let most_active = stmt
+            .query_map(params![since, TOP_CONTACTS], |row| {
+                Ok(ContactActivity {
+                    contact: row.get(0)?,
+                    messages: row.get(1)?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        // A reply is an outgoing message right after an incoming one.
+        let avg_response_ms: Option<f64> = conn.query_row(
+            "SELECT AVG(timestamp - prev_timestamp) FROM (
+                 SELECT timestamp, from_user_id, conversation_id,
+                        LAG(timestamp) OVER w AS prev_timestamp,
+                        LAG(from_user_id) OVER w AS prev_from
+                 FROM messages WHERE timestamp >= ?1 - ?2
+                 WINDOW w AS (PARTITION BY conversation_id ORDER BY timestamp)
+             )
+             WHERE timestamp >= ?1 AND from_user_id != conversation_id
+               AND prev_from = conversation_id AND timestamp - prev_timestamp <= ?2",
+            params![since, MAX_RESPONSE_MS],
+            |row| row.get(0),
+        )?;
+
+        Ok((sent, received, most_active, avg_response_ms))
+    })?;
+
+    let focus: FocusSeconds = prefs::load(app, FOCUS_KEY).unwrap_or_default();
+    let focus_secs: u64 = focus.range(week_start..).map(|(_, secs)| secs).sum();
+
+    Ok(WeeklySummary {
+        week_start,
+        sent,
+        received,
+        most_active,
+        avg_response_secs: avg_response_ms.map(|ms| (ms / 1000.0).round() as u64),
+        focus_hours: (focus_secs as f64 / 360.0).round() / 10.0,
+    })
+}
+
+/// Send the summary once per week, on Sunday evening.
+fn maybe_notify(app: &AppHandle) {
+    let now = Local::now();
+    if now.weekday() != Weekday::Sun || now.hour() < NOTIFY_HOUR {
+        return;
+    }
+    let week = week_start(now);
+    if prefs::load::<_, NaiveDate>(app, NOTIFIED_KEY) == Some(week) {
+        return;
+    }
+    let summary = match summarize(app, &app.state::<Database>()) {
+        Ok(summary) => summary,
+        Err(e) => {
+            log::warn!("Failed to compute weekly summary: {}", e);
+            return;
+        }
+    };
+
+    let mut body = format!("{} sent, {} received", summary.sent, summary.received);
+    if let Some(top) = summary.most_active.first() {
+        body.push_str(&format!(" · most with {}", top.contact));
+    }
+    if summary.focus_hours > 0.0 {
+        body.push_str(&format!(" · {} h in focus", summary.focus_hours));
+    }
+    if let Err(e) = notifications::notify(app, "Your week in Pester".to_string(), body) {
+        log::warn!("Failed to show weekly summary: {}", e);
+    }
+    let _ = prefs::save(app, NOTIFIED_KEY, &week);
+}
+
+/// Register the focus sampler and Sunday check. Both do nothing unless the
+/// user opted in. Called from `setup`.
+pub fn start(app: &AppHandle) {
+    let last_sample = Arc::new(Mutex::new(None::<Instant>));
+    app.state::<Scheduler>().register(
+        "usage_analytics",
+        Priority::Normal,
+        false,
+        SAMPLE_INTERVAL,
+        move |app| {
+            let last_sample = last_sample.clone();
+            async move {
+                let settings = settings(&app);
+                let now = Instant::now();
+                let previous = last_sample.lock().unwrap().replace(now);
+                if !settings.enabled {
+                    return;
+                }
+
+                let elapsed = previous.map(|p| now.duration_since(p));
+                if let Some(elapsed) = elapsed.filter(|e| *e <= MAX_SAMPLE_GAP) {
+                    if focus::query(&app).suppresses_toasts() {
+                        record_focus(&app, elapsed.as_secs());
+                    }
+                }
+                if settings.sunday_notification {
+                    maybe_notify(&app);
+                }
+            }
+        },
+    );
+}
+
+#[tauri::command]
+pub fn get_usage_analytics_settings(app: AppHandle) -> AnalyticsSettings {
+    settings(&app)
+}
+
+/// Turning analytics off also forgets recorded focus time.
+#[tauri::command]
+pub fn set_usage_analytics_settings(
+    app: AppHandle,
+    settings: AnalyticsSettings,
+) -> Result<(), String> {
+    if !settings.enabled {
+        prefs::save(&app, FOCUS_KEY, &FocusSeconds::new())?;
+    }
+    prefs::save(&app, SETTINGS_KEY, &settings)
+}
+
+/// Stats for the current week (Monday to now). Errors unless opted in.
+#[tauri::command]
+pub fn get_weekly_summary(
+    app: AppHandle,
+    db: State<'_, Database>,
+) -> Result<WeeklySummary, String> {
+    if !settings(&app).enabled {
+        return Err("Usage summary is turned off".to_string());
+    }
+    summarize(&app, &db)
+}
@@ -0,0 +1,61 @@
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use rodio::{OutputStream, OutputStreamBuilder, Source};
+
+pub mod sounds;
+
+pub type BoxedSource = Box<dyn Source + Send>;
+
+/// The device is let go after this long without a sound, so it can sleep.
+const IDLE_CLOSE: Duration = Duration::from_secs(30);
+
+/// The default output device, opened on its own thread on first use.
+/// Audio streams can't move between threads on every platform, so sounds
+/// are sent to that thread to be played.
+#[derive(Default)]
+pub struct AudioOutput(Mutex<Option<mpsc::Sender<BoxedSource>>>);
+
+impl AudioOutput {
+    pub fn play(&self, source: BoxedSource) {
+        let mut sender = self.0.lock().unwrap();
+        // The thread exits when idle; start a new one if it has
+        let source = match sender.as_ref() {
+            Some(tx) => match tx.send(source) {
+                Ok(()) => return,
+                Err(mpsc::SendError(source)) => source,
+            },
+            None => source,
+        };
+        let (tx, rx) = mpsc::channel();
+        let _ = tx.send(source);
+        std::thread::spawn(move || run(rx));
+        *sender = Some(tx);
+    }
+}
+
+fn run(rx: Receiver<BoxedSource>) {
+    let mut stream: Option<OutputStream> = None;
+    loop {
+        let source = match rx.recv_timeout(IDLE_CLOSE) {
+            Ok(source) => source,
+            Err(RecvTimeoutError::Timeout | RecvTimeoutError::Disconnected) => return,
+        };
+        if stream.is_none() {
+            match OutputStreamBuilder::open_default_stream() {
+                Ok(mut opened) => {
+                    opened.log_on_drop(false);
+                    stream = Some(opened);
+                }
+                Err(e) => {
+                    log::warn!("Failed to open the audio device: {}", e);
+                    continue;
+                }
+            }
+        }
+        if let Some(stream) = &stream {
+            stream.mixer().add(source);
+        }
+    }
+}
@@ -0,0 +1,145 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::time::Duration;
+
+use rodio::source::SineWave;
+use rodio::{Decoder, Source};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+
+use super::{AudioOutput, BoxedSource};
+use crate::prefs;
+
+const SETTINGS_KEY: &str = "sound_settings";
+/// Sound ID that plays nothing.
+const SILENT: &str = "none";
+/// Sounds that ship with Pester. Anything else is a path to an audio file.
+const BUILTIN: &[&str] = &["chime", "ping", "pop"];
+/// Sound files are cut off after this long.
+const MAX_LENGTH: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SoundEvent {
+    Message,
+    Reminder,
+    SendFailed,
+}
+
+impl SoundEvent {
+    fn default_sound(self) -> &'static str {
+        match self {
+            Self::Message => "chime",
+            Self::Reminder => "ping",
+            Self::SendFailed => "pop",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SoundSettings {
+    /// Off by default, leaving sounds to the OS toast.
+    pub enabled: bool,
+    /// From 0 to 1.
+    pub volume: f32,
+    /// Sound per event, the event's default when missing.
+    pub events: HashMap<SoundEvent, String>,
+    /// Message sounds for particular contacts, over `events`.
+    pub contacts: HashMap<String, String>,
+}
+
+impl Default for SoundSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            volume: 0.6,
+            events: HashMap::new(),
+            contacts: HashMap::new(),
+        }
+    }
+}
+
+fn settings(app: &AppHandle) -> SoundSettings {
+    prefs::load(app, SETTINGS_KEY).unwrap_or_default()
+}
+
+/// A short tone, faded out so it doesn't click.
+fn tone(freq: f32, length_ms: u64) -> impl Source + Send {
+    let length = Duration::from_millis(length_ms);
+    SineWave::new(freq)
+        .take_duration(length)
+        .fade_out(length)
+        .amplify(0.3)
+}
+
+fn source(id: &str) -> Result<BoxedSource, String> {
+    Ok(match id {
+        "chime" => {
+            Box::new(tone(880.0, 140).mix(tone(1320.0, 220).delay(Duration::from_millis(120))))
+        }
+        "ping" => Box::new(tone(1760.0, 250)),
+        "pop" => Box::new(tone(420.0, 80)),
+        path => {
+            let file = File::open(path).map_err(|e| format!("{}: {}", path, e))?;
+            let decoder = Decoder::try_from(file).map_err(|e| format!("{}: {}", path, e))?;
+            Box::new(decoder.take_duration(MAX_LENGTH))
+        }
+    })
+}
+
+/// The sound `event` would play for `contact`, if any.
+pub fn sound_for(app: &AppHandle, event: SoundEvent, contact: Option<&str>) -> Option<String> {
+    let mut settings = settings(app);
+    if !settings.enabled {
+        return None;
+    }
+    let id = contact
+        .filter(|_| event == SoundEvent::Message)
+        .and_then(|c| settings.contacts.remove(c))
+        .or_else(|| settings.events.remove(&event))
+        .unwrap_or_else(|| event.default_sound().to_string());
+    (id != SILENT).then_some(id)
+}
+
+fn play_id(app: &AppHandle, id: &str, volume: f32) -> Result<(), String> {
+    let source = source(id)?;
+    app.state::<AudioOutput>()
+        .play(Box::new(source.amplify(volume.clamp(0.0, 1.0))));
+    Ok(())
+}
+
+/// Play the sound configured for `event`, if sounds are on.
+pub fn play_event(app: &AppHandle, event: SoundEvent, contact: Option<&str>) {
+    let Some(id) = sound_for(app, event, contact) else {
+        return;
+    };
+    if let Err(e) = play_id(app, &id, settings(app).volume) {
+        log::warn!("Failed to play sound for {:?}: {}", event, e);
+    }
+}
+
+/// Play a sound by ID, e.g. to preview it in settings.
+#[tauri::command]
+pub fn play_sound(app: AppHandle, id: String) -> Result<(), String> {
+    if id == SILENT {
+        return Ok(());
+    }
+    play_id(&app, &id, settings(&app).volume)
+}
+
+#[tauri::command]
+pub fn list_builtin_sounds() -> Vec<&'static str> {
+    BUILTIN.to_vec()
+}
+
+#[tauri::command]
+pub fn get_sound_settings(app: AppHandle) -> SoundSettings {
+    settings(&app)
+}
+
+#[tauri::command]
+pub fn set_sound_settings(app: AppHandle, mut settings: SoundSettings) -> Result<(), String> {
+    settings.volume = settings.volume.clamp(0.0, 1.0);
+    prefs::save(&app, SETTINGS_KEY, &settings)
+}
@@ -0,0 +1,576 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use base64::{engine::general_purpose::STANDARD as B64, Engine};
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use rand_core::{OsRng, RngCore};
+use rusqlite::params;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tauri::{AppHandle, Manager, State};
+
+use crate::scheduler::{Priority, Scheduler};
+use crate::storage::messages::{self, StoredMessage};
+use crate::storage::Database;
+use crate::tasks::{TaskContext, TaskManager};
+use crate::{clock, notes, prefs, secrets};
+
+const SETTINGS_KEY: &str = "backup_settings";
+/// Newest message rowid already in the backup folder.
+const HIGH_WATER_KEY: &str = "backup_last_rowid";
+/// Keychain entry holding the backup key.
+const KEY_SECRET: &str = "backup_key";
+
+/// Backups go in this folder inside the one the user picked.
+const BACKUP_DIR: &str = "Pester Backup";
+const MANIFEST_FILE: &str = "manifest.json";
+/// Conversation notes, rewritten whole on every run since they change in
+/// place.
+const NOTES_FILE: &str = "notes.bin";
+const FORMAT_VERSION: u32 = 1;
+
+const CHECK_INTERVAL: Duration = Duration::from_secs(60 * 60);
+const BACKUP_EVERY_MS: i64 = 24 * 60 * 60 * 1000;
+/// Messages per encrypted part file.
+const PART_MESSAGES: u32 = 5000;
+
+/// Crockford's base32, which leaves out letters that look like digits.
+const ALPHABET: &[u8; 32] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+/// Checksum bytes appended to the key in the recovery phrase, to catch
+/// typos before trying to decrypt anything.
+const PHRASE_CHECKSUM: usize = 2;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct BackupSettings {
+    pub enabled: bool,
+    /// Folder chosen by the user, e.g. inside Dropbox or OneDrive.
+    pub folder: Option<PathBuf>,
+    /// When the last backup finished, in milliseconds.
+    pub last_backup_at: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupStatus {
+    #[serde(flatten)]
+    pub settings: BackupSettings,
+    /// Whether this device has a backup key in the keychain.
+    pub has_key: bool,
+    /// The task of a backup or restore in progress.
+    pub running_task: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PartInfo {
+    file: String,
+    created_at: i64,
+    messages: u32,
+}
+
+/// Kept in the clear next to the parts, so a restore can check the key
+/// before decrypting anything.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Manifest {
+    version: u32,
+    key_id: String,
+    parts: Vec<PartInfo>,
+}
+
+/// The backup or restore task in progress, so they never overlap.
+#[derive(Default)]
+pub struct Backups(Mutex<Option<u64>>);
+
+fn settings(app: &AppHandle) -> BackupSettings {
+    prefs::load(app, SETTINGS_KEY).unwrap_or_default()
+}
+
+// ── Keys and recovery phrases ───────────────────────────────────────────────
+
+fn key_id(key: &[u8; 32]) -> String {
+    let digest = Sha256::new()
+        .chain_update(b"pester-backup-key-id")
+        .chain_update(key)
+        .finalize();
+    digest[..8].iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn load_key(app: &AppHandle) -> Result<Option<[u8; 32]>, String> {
+    let Some(encoded) = secrets::get(app, KEY_SECRET)? else {
+        return Ok(None);
+    };
+    let bytes = B64.decode(encoded).map_err(|e| e.to_string())?;
+    let key = bytes
+        .try_into()
+        .map_err(|_| "Backup key in the keychain is malformed".to_string())?;
+    Ok(Some(key))
+}
+
+fn store_key(app: &AppHandle, key: &[u8; 32]) -> Result<(), String> {
+    secrets::store(app, KEY_SECRET, &B64.encode(key))
+}
+
+/// The key plus a checksum in base32, in dash-separated groups of five.
+fn recovery_phrase(key: &[u8; 32]) -> String {
+    let checksum = Sha256::digest(key);
+    let mut bytes = key.to_vec();
+    bytes.extend_from_slice(&checksum[..PHRASE_CHECKSUM]);
+
+    let (mut chars, mut buffer, mut bits) = (String::new(), 0u32, 0);
+    for byte in bytes {
+        buffer = (buffer << 8) | byte as u32;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            chars.push(ALPHABET[((buffer >> bits) & 31) as usize] as char);
+        }
+    }
+    if bits > 0 {
+        chars.push(ALPHABET[((buffer << (5 - bits)) & 31) as usize] as char);
+    }
+
+    chars
+        .as_bytes()
+        .chunks(5)
+        .map(|group| String::from_utf8_lossy(group).into_owned())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// Read a phrase back, forgiving case, spacing and look-alike letters.
+fn parse_recovery_phrase(phrase: &str) -> Result<[u8; 32], String> {
+    let (mut bytes, mut buffer, mut bits) = (Vec::new(), 0u32, 0);
+    for c in phrase.chars().filter(|c| c.is_ascii_alphanumeric()) {
+        let c = match c.to_ascii_uppercase() {
+            'O' => '0',
+            'I' | 'L' => '1',
+            c => c,
+        };
+        let value = ALPHABET
+            .iter()
+            .position(|&a| a as char == c)
+            .ok_or_else(|| format!("'{}' isn't part of a recovery phrase", c))?;
+        buffer = (buffer << 5) | value as u32;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((buffer >> bits) as u8);
+        }
+    }
+
+    if bytes.len() != 32 + PHRASE_CHECKSUM {
+        return Err("The recovery phrase is too short or too long".to_string());
+    }
+    let key: [u8; 32] = bytes[..32].try_into().unwrap();
+    if Sha256::digest(key)[..PHRASE_CHECKSUM] != bytes[32..] {
+        return Err("The recovery phrase has a typo".to_string());
+    }
+    Ok(key)
+}
+
+// ── Backup folder ───────────────────────────────────────────────────────────
+
+fn backup_dir(folder: &Path) -> PathBuf {
+    folder.join(BACKUP_DIR)
+}
+
+fn read_manifest(dir: &Path) -> Result<Option<Manifest>, String> {
+    match fs::read(dir.join(MANIFEST_FILE)) {
+        Ok(bytes) => serde_json::from_slice(&bytes)
+            .map(Some)
+            .map_err(|e| format!("Backup manifest is unreadable: {}", e)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+/// Write through a temporary file, so a sync client never picks up half a
+/// file.
+fn write_atomic(path: &Path, bytes: &[u8]) -> Result<(), String> {
+    let tmp = path.with_extension("tmp");
+    fs::write(&tmp, bytes).map_err(|e| e.to_string())?;
+    fs::rename(&tmp, path).map_err(|e| e.to_string())
+}
+
+fn seal(key: &[u8; 32], file: &str, plaintext: &[u8]) -> Result<Vec<u8>, String> {
+    let cipher = ChaCha20Poly1305::new(Key::from_slice(key));
+    let mut nonce = [0u8; 12];
+    OsRng.fill_bytes(&mut nonce);
+    let sealed = cipher
+        .encrypt(
+            Nonce::from_slice(&nonce),
+            Payload {
+                msg: plaintext,
+                aad: file.as_bytes(),
+            },
+        )
+        .map_err(|_| "Failed to encrypt backup".to_string())?;
+    Ok([nonce.as_slice(), &sealed].concat())
+}
+
+fn open(key: &[u8; 32], file: &str, sealed: &[u8]) -> Result<Vec<u8>, String> {
+    if sealed.len() < 12 {
+        return Err(format!("{} is truncated", file));
+    }
+    let (nonce, ciphertext) = sealed.split_at(12);
+    ChaCha20Poly1305::new(Key::from_slice(key))
+        .decrypt(
+            Nonce::from_slice(nonce),
+            Payload {
+                msg: ciphertext,
+                aad: file.as_bytes(),
+            },
+        )
+        .map_err(|_| format!("{} is damaged or from another key", file))
+}
+
+// ── Backing up ──────────────────────────────────────────────────────────────
+
+fn unbacked_count(app: &AppHandle, after: i64) -> Result<u64, String> {
+    app.state::<Database>().with(|conn| {
+        conn.query_row(
+            "SELECT COUNT(*) FROM messages WHERE rowid > ?1",
+            [after],
+            |row| row.get(0),
+        )
+    })
+}
+
+fn next_batch(app: &AppHandle, after: i64) -> Result<Vec<(i64, StoredMessage)>, String> {
+    app.state::<Database>().with(|conn| {
+        let mut stmt = conn.prepare(
+            "SELECT id, conversation_id, from_user_id, text, timestamp, rowid FROM messages
+             WHERE rowid > ?1 ORDER BY rowid LIMIT ?2",
+        )?;
+        let batch = stmt
+            .query_map(params![after, PART_MESSAGES], |row| {
+                Ok((row.get(5)?, messages::from_row(row)?))
+            })?
+            .collect();
+        batch
+    })
+}
+
+/// Write every message stored since the last run into new encrypted part
+/// files, then list them in the manifest.
+fn back_up(app: &AppHandle, ctx: &TaskContext) -> Result<(), String> {
+    let mut settings = settings(app);
+    let folder = settings.folder.clone().ok_or("No backup folder chosen")?;
+    let key = load_key(app)?.ok_or("No backup key on this device")?;
+    let dir = backup_dir(&folder);
+    fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+
+    let mut manifest = match read_manifest(&dir)? {
+        Some(manifest) if manifest.key_id != key_id(&key) => {
+            return Err("The backup folder belongs to a different backup key".to_string())
+        }
+        Some(manifest) => manifest,
+        None => Manifest {
+            version: FORMAT_VERSION,
+            key_id: key_id(&key),
+            parts: Vec::new(),
+        },
+    };
+
+    let mut high_water: i64 = prefs::load(app, HIGH_WATER_KEY).unwrap_or(0);
+    let total = unbacked_count(app, high_water)?;
+    let mut done = 0;
+    ctx.progress(0, Some(total));
+
+    loop {
+        ctx.check_cancelled()?;
+        let batch = next_batch(app, high_water)?;
+        let Some(&(last_rowid, _)) = batch.last() else {
+            break;
+        };
+
+        let count = batch.len() as u32;
+        let messages: Vec<StoredMessage> = batch.into_iter().map(|(_, m)| m).collect();
+        let created_at = clock::now_millis() as i64;
+        let file = format!("{:06}-{}.bin", manifest.parts.len() + 1, created_at);
+        let plaintext = serde_json::to_vec(&messages).map_err(|e| e.to_string())?;
+        write_atomic(&dir.join(&file), &seal(&key, &file, &plaintext)?)?;
+
+        manifest.parts.push(PartInfo {
+            file,
+            created_at,
+            messages: count,
+        });
+        let json = serde_json::to_vec_pretty(&manifest).map_err(|e| e.to_string())?;
+        write_atomic(&dir.join(MANIFEST_FILE), &json)?;
+        high_water = last_rowid;
+        prefs::save(app, HIGH_WATER_KEY, &high_water)?;
+
+        done += count as u64;
+        ctx.progress(done, Some(total));
+    }
+
+    let notes = serde_json::to_vec(&notes::all(app)).map_err(|e| e.to_string())?;
+    write_atomic(&dir.join(NOTES_FILE), &seal(&key, NOTES_FILE, &notes)?)?;
+
+    log::info!("Backed up {} messages to {}", done, dir.display());
+    settings.last_backup_at = Some(clock::now_millis() as i64);
+    prefs::save(app, SETTINGS_KEY, &settings)
+}
+
+/// Start a backup or restore as a tracked task, unless one is running.
+fn spawn(
+    app: &AppHandle,
+    label: &str,
+    work: impl FnOnce(&AppHandle, &TaskContext) -> Result<(), String> + Send + 'static,
+) -> u64 {
+    let backups = app.state::<Backups>();
+    let mut running = backups.0.lock().unwrap();
+    if let Some(id) = *running {
+        return id;
+    }
+
+    let id = app
+        .state::<TaskManager>()
+        .spawn(app, "backup", label.to_string(), |ctx| async move {
+            let app = ctx.app().clone();
+            let result = tauri::async_runtime::spawn_blocking(move || work(ctx.app(), &ctx))
+                .await
+                .map_err(|e| e.to_string())
+                .and_then(|result| result);
+            *app.state::<Backups>().0.lock().unwrap() = None;
+            result
+        });
+    *running = Some(id);
+    id
+}
+
+/// Check hourly whether a day has passed since the last backup.
+pub fn start(app: &AppHandle) {
+    app.state::<Scheduler>().register(
+        "backup",
+        Priority::Low,
+        false,
+        CHECK_INTERVAL,
+        |app| async move {
+            let settings = settings(&app);
+            let due = settings
+                .last_backup_at
+                .is_none_or(|last| clock::now_millis() as i64 - last >= BACKUP_EVERY_MS);
+            if settings.enabled && due {
+                spawn(&app, "Backing up chats", back_up);
+            }
+        },
+    );
+}
+
+// ── Restoring ───────────────────────────────────────────────────────────────
+
+/// The high-water mark after a restore, from the one for the folder before
+/// (`before`) and the newest rowid before and after. Restored rows come
+/// after every local one, so they can only be skipped when every local
+/// row is already in the folder.
+fn restored_high_water(before: i64, local_max: i64, restored_max: i64) -> i64 {
+    if local_max <= before {
+        restored_max
+    } else {
+        before
+    }
+}
+
+fn max_rowid(app: &AppHandle) -> Result<i64, String> {
+    app.state::<Database>().with(|conn| {
+        conn.query_row("SELECT COALESCE(MAX(rowid), 0) FROM messages", [], |row| {
+            row.get(0)
+        })
+    })
+}
+
+/// Take in the notes backed up in `dir`, if any.
+fn restore_notes(app: &AppHandle, dir: &Path, key: &[u8; 32]) -> Result<(), String> {
+    let sealed = match fs::read(dir.join(NOTES_FILE)) {
+        Ok(sealed) => sealed,
+        // Backups from before notes were included
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(format!("{}: {}", NOTES_FILE, e)),
+    };
+    let plaintext = open(key, NOTES_FILE, &sealed)?;
+    let restored = serde_json::from_slice(&plaintext).map_err(|e| e.to_string())?;
+    notes::merge(app, restored)
+}
+
+/// Import every part in `folder` with the key from `phrase`. Messages
+/// already stored are skipped, so restoring twice is harmless.
+fn restore(app: &AppHandle, ctx: &TaskContext, folder: &Path, key: [u8; 32]) -> Result<(), String> {
+    let dir = backup_dir(folder);
+    let manifest = read_manifest(&dir)?.ok_or("No Pester backup in that folder")?;
+    if manifest.version > FORMAT_VERSION {
+        return Err("This backup was made by a newer version of Pester".to_string());
+    }
+    if manifest.key_id != key_id(&key) {
+        return Err("The recovery phrase doesn't match this backup".to_string());
+    }
+
+    // Local messages past this aren't in the folder yet
+    let before = match settings(app).folder {
+        Some(current) if current == folder => prefs::load(app, HIGH_WATER_KEY).unwrap_or(0),
+        _ => 0,
+    };
+    let local_max = max_rowid(app)?;
+
+    let total = manifest.parts.iter().map(|p| p.messages as u64).sum();
+    let mut done = 0;
+    ctx.progress(0, Some(total));
+    for part in &manifest.parts {
+        ctx.check_cancelled()?;
+        let sealed = fs::read(dir.join(&part.file)).map_err(|e| format!("{}: {}", part.file, e))?;
+        let plaintext = open(&key, &part.file, &sealed)?;
+        let messages: Vec<StoredMessage> =
+            serde_json::from_slice(&plaintext).map_err(|e| e.to_string())?;
+
+        let db = app.state::<Database>();
+        let conn = db.lock();
+        let tx = conn.unchecked_transaction().map_err(|e| e.to_string())?;
+        for message in &messages {
+            messages::save(&tx, message).map_err(|e| e.to_string())?;
+        }
+        tx.commit().map_err(|e| e.to_string())?;
+
+        done += part.messages as u64;
+        ctx.progress(done, Some(total));
+    }
+
+    restore_notes(app, &dir, &key)?;
+
+    // Carry on backing up into the same folder from here
+    store_key(app, &key)?;
+    let high_water = restored_high_water(before, local_max, max_rowid(app)?);
+    prefs::save(app, HIGH_WATER_KEY, &high_water)?;
+    prefs::save(
+        app,
+        SETTINGS_KEY,
+        &BackupSettings {
+            enabled: true,
+            folder: Some(folder.to_path_buf()),
+            last_backup_at: Some(clock::now_millis() as i64),
+        },
+    )?;
+    log::info!("Restored {} messages from {}", done, dir.display());
+    Ok(())
+}
+
+// ── Commands ────────────────────────────────────────────────────────────────
+
+#[tauri::command]
+pub fn get_backup_status(
+    app: AppHandle,
+    backups: State<'_, Backups>,
+) -> Result<BackupStatus, String> {
+    Ok(BackupStatus {
+        settings: settings(&app),
+        has_key: load_key(&app)?.is_some(),
+        running_task: *backups.0.lock().unwrap(),
+    })
+}
+
+/// Back up daily into `folder`. Makes a backup key the first time and
+/// returns its recovery phrase, which the user must write down: it's the
+/// only way to restore on a machine without this keychain.
+#[tauri::command]
+pub fn enable_backup(app: AppHandle, folder: PathBuf) -> Result<Option<String>, String> {
+    if !folder.is_dir() {
+        return Err(format!("{} is not a folder", folder.display()));
+    }
+    let phrase = match load_key(&app)? {
+        Some(_) => None,
+        None => {
+            let mut key = [0u8; 32];
+            OsRng.fill_bytes(&mut key);
+            store_key(&app, &key)?;
+            Some(recovery_phrase(&key))
+        }
+    };
+
+    let mut settings = settings(&app);
+    if settings.folder.as_deref() != Some(folder.as_path()) {
+        // A new folder starts with a full backup
+        prefs::save(&app, HIGH_WATER_KEY, &0i64)?;
+        settings.last_backup_at = None;
+    }
+    settings.enabled = true;
+    settings.folder = Some(folder);
+    prefs::save(&app, SETTINGS_KEY, &settings)?;
+    spawn(&app, "Backing up chats", back_up);
+    Ok(phrase)
+}
+
+#[tauri::command]
+pub fn disable_backup(app: AppHandle) -> Result<(), String> {
+    let mut settings = settings(&app);
+    settings.enabled = false;
+    prefs::save(&app, SETTINGS_KEY, &settings)
+}
+
+/// Show the recovery phrase again, e.g. after losing the copy.
+#[tauri::command]
+pub fn get_backup_recovery_phrase(app: AppHandle) -> Result<String, String> {
+    let key = load_key(&app)?.ok_or("No backup key on this device")?;
+    Ok(recovery_phrase(&key))
+}
+
+/// Back up now instead of waiting for the daily run. Returns the task ID.
+#[tauri::command]
+pub fn run_backup_now(app: AppHandle) -> Result<u64, String> {
+    if settings(&app).folder.is_none() {
+        return Err("Choose a backup folder first".to_string());
+    }
+    Ok(spawn(&app, "Backing up chats", back_up))
+}
+
+/// Restore the backup in `folder` using its recovery phrase, then keep
+/// backing up there. Returns the task ID.
+#[tauri::command]
+pub fn restore_backup(app: AppHandle, folder: PathBuf, phrase: String) -> Result<u64, String> {
+    let key = parse_recovery_phrase(&phrase)?;
+    Ok(spawn(&app, "Restoring chats", move |app, ctx| {
+        restore(app, ctx, &folder, key)
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn restore_skips_restored_rows_when_nothing_local_is_unbacked() {
+        assert_eq!(restored_high_water(0, 0, 120), 120);
+        assert_eq!(restored_high_water(40, 40, 120), 120);
+    }
+
+    #[test]
+    fn restore_keeps_unbacked_local_rows() {
+        // Rows 31–40 were never backed up, so they and the restored rows
+        // after them all go in the next run
+        assert_eq!(restored_high_water(30, 40, 120), 30);
+        // A different folder has none of the local rows
+        assert_eq!(restored_high_water(0, 40, 120), 0);
+    }
+
+    #[test]
+    fn recovery_phrase_round_trip() {
+        let key: [u8; 32] = std::array::from_fn(|i| (i * 7) as u8);
+        let phrase = recovery_phrase(&key);
+        assert_eq!(parse_recovery_phrase(&phrase), Ok(key));
+        let sloppy = phrase.to_lowercase().replace('-', " ").replace('0', "o");
+        assert_eq!(parse_recovery_phrase(&sloppy), Ok(key));
+    }
+
+    #[test]
+    fn recovery_phrase_typo_is_caught() {
+        let phrase = recovery_phrase(&[42; 32]);
+        let mut chars: Vec<char> = phrase.chars().collect();
+        chars[3] = if chars[3] == 'A' { 'B' } else { 'A' };
+        let typo: String = chars.into_iter().collect();
+        assert!(parse_recovery_phrase(&typo).is_err());
+    }
+}
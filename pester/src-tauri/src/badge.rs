@@ -0,0 +1,324 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use tauri::async_runtime::JoinHandle;
+use tauri::image::Image;
+use tauri::{AppHandle, Manager, State};
+use tiny_skia::{Color, FillRule, IntSize, Paint, PathBuilder, Pixmap, Rect, Transform};
+
+use crate::presence::Presence;
+
+/// How long the tray icon stays on, then off, while flashing.
+const FLASH_INTERVAL: Duration = Duration::from_millis(600);
+
+/// 3×5 pixel glyphs for the badge label, one row per byte.
+const GLYPHS: [(char, [u8; 5]); 11] = [
+    ('0', [0b111, 0b101, 0b101, 0b101, 0b111]),
+    ('1', [0b010, 0b110, 0b010, 0b010, 0b111]),
+    ('2', [0b111, 0b001, 0b111, 0b100, 0b111]),
+    ('3', [0b111, 0b001, 0b111, 0b001, 0b111]),
+    ('4', [0b101, 0b101, 0b111, 0b001, 0b001]),
+    ('5', [0b111, 0b100, 0b111, 0b001, 0b111]),
+    ('6', [0b111, 0b100, 0b111, 0b101, 0b111]),
+    ('7', [0b111, 0b001, 0b010, 0b010, 0b010]),
+    ('8', [0b111, 0b101, 0b111, 0b101, 0b111]),
+    ('9', [0b111, 0b101, 0b111, 0b001, 0b111]),
+    ('+', [0b000, 0b010, 0b111, 0b010, 0b000]),
+];
+
+/// What's drawn on the tray icon: the unread count and the status dot.
+#[derive(Default, Clone, Copy, PartialEq)]
+struct Overlay {
+    count: u32,
+    presence: Option<Presence>,
+}
+
+#[derive(Default)]
+pub struct TrayBadge {
+    /// The overlay last drawn on the tray icon.
+    shown: Mutex<Overlay>,
+    /// The timer alternating the icon, while flashing.
+    flash: Mutex<Option<JoinHandle<()>>>,
+}
+
+/// The tray icon from `tauri.conf.json`, without a badge.
+fn tray_icon() -> Image<'static> {
+    tauri::include_image!("./icons/32x32.png")
+}
+
+fn label(count: u32) -> String {
+    if count > 9 {
+        "9+".to_string()
+    } else {
+        count.to_string()
+    }
+}
+
+/// Fill a red circle with `count` in white pixel digits.
+fn draw_badge(
+    pixmap: &mut Pixmap,
+    cx: f32,
+    cy: f32,
+    radius: f32,
+    count: u32,
+) -> Result<(), String> {
+    let mut paint = Paint {
+        anti_alias: true,
+        ..Default::default()
+    };
+    paint.set_color(Color::from_rgba8(0xE5, 0x48, 0x4D, 0xFF));
+    let circle = PathBuilder::from_circle(cx, cy, radius).ok_or("Badge too small")?;
+    pixmap.fill_path(
+        &circle,
+        &paint,
+        FillRule::Winding,
+        Transform::identity(),
+        None,
+    );
+
+    let text = label(count);
+    let scale = (radius / 5.0).floor().max(1.0);
+    let text_width = (text.len() * 4 - 1) as f32 * scale;
+    let (x0, y0) = ((cx - text_width / 2.0).round(), (cy - 2.5 * scale).round());
+    paint.anti_alias = false;
+    paint.set_color(Color::WHITE);
+    for (i, ch) in text.chars().enumerate() {
+        let Some((_, rows)) = GLYPHS.iter().find(|(c, _)| *c == ch) else {
+            continue;
+        };
+        for (row, bits) in rows.iter().enumerate() {
+            for col in 0..3 {
+                if bits & (0b100 >> col) == 0 {
+                    continue;
+                }
+                let x = x0 + (i * 4 + col) as f32 * scale;
+                let y = y0 + row as f32 * scale;
+                if let Some(rect) = Rect::from_xywh(x, y, scale, scale) {
+                    pixmap.fill_rect(rect, &paint, Transform::identity(), None);
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn to_image(pixmap: Pixmap) -> Image<'static> {
+    let rgba = pixmap
+        .pixels()
+        .iter()
+        .flat_map(|px| {
+            let c = px.demultiply();
+            [c.red(), c.green(), c.blue(), c.alpha()]
+        })
+        .collect();
+    Image::new_owned(rgba, pixmap.width(), pixmap.height())
+}
+
+/// Fill a circle in the status color, ringed in white so it stands out on
+/// the icon.
+fn draw_status(
+    pixmap: &mut Pixmap,
+    cx: f32,
+    cy: f32,
+    radius: f32,
+    presence: Presence,
+) -> Result<(), String> {
+    let mut paint = Paint {
+        anti_alias: true,
+        ..Default::default()
+    };
+    let (r, g, b) = presence.color();
+    for (radius, color) in [
+        (radius, Color::WHITE),
+        (radius * 0.7, Color::from_rgba8(r, g, b, 0xFF)),
+    ] {
+        paint.set_color(color);
+        let circle = PathBuilder::from_circle(cx, cy, radius).ok_or("Status dot too small")?;
+        pixmap.fill_path(
+            &circle,
+            &paint,
+            FillRule::Winding,
+            Transform::identity(),
+            None,
+        );
+    }
+    Ok(())
+}
+
+/// Draw the unread badge in the top-right corner of `base` and the status
+/// dot in the bottom-right.
+fn render(base: &Image<'_>, overlay: Overlay) -> Result<Image<'static>, String> {
+    let (width, height) = (base.width(), base.height());
+    // tiny-skia works on premultiplied alpha
+    let data = base
+        .rgba()
+        .chunks_exact(4)
+        .flat_map(|px| {
+            let alpha = px[3] as u16;
+            let premultiply = |c: u8| (c as u16 * alpha / 255) as u8;
+            [
+                premultiply(px[0]),
+                premultiply(px[1]),
+                premultiply(px[2]),
+                px[3],
+            ]
+        })
+        .collect();
+    let size = IntSize::from_wh(width, height).ok_or("Empty tray icon")?;
+    let mut pixmap = Pixmap::from_vec(data, size).ok_or("Malformed tray icon")?;
+
+    if let Some(presence) = overlay.presence {
+        let radius = width as f32 * 0.22;
+        let (cx, cy) = (width as f32 - radius, height as f32 - radius);
+        draw_status(&mut pixmap, cx, cy, radius, presence)?;
+    }
+    if overlay.count > 0 {
+        let radius = width as f32 * 0.32;
+        draw_badge(
+            &mut pixmap,
+            width as f32 - radius,
+            radius,
+            radius,
+            overlay.count,
+        )?;
+    }
+    Ok(to_image(pixmap))
+}
+
+/// A badge on its own, `size` pixels square, for overlay icons.
+#[cfg(target_os = "windows")]
+pub fn badge_icon(count: u32, size: u32) -> Result<Image<'static>, String> {
+    let mut pixmap = Pixmap::new(size, size).ok_or("Empty badge")?;
+    let radius = size as f32 / 2.0;
+    draw_badge(&mut pixmap, radius, radius, radius, count)?;
+    Ok(to_image(pixmap))
+}
+
+fn icon(overlay: Overlay) -> Result<Image<'static>, String> {
+    if overlay == Overlay::default() {
+        Ok(tray_icon())
+    } else {
+        render(&tray_icon(), overlay)
+    }
+}
+
+/// Redraw the tray icon with `update` applied, if that changes anything.
+fn redraw(
+    app: &AppHandle,
+    badge: &TrayBadge,
+    update: impl FnOnce(&mut Overlay),
+) -> Result<(), String> {
+    let mut shown = badge.shown.lock().unwrap();
+    let mut overlay = *shown;
+    update(&mut overlay);
+    if overlay == *shown {
+        return Ok(());
+    }
+    let tray = app.tray_by_id("main-tray").ok_or("Tray not found")?;
+    tray.set_icon(Some(icon(overlay)?))
+        .map_err(|e| e.to_string())?;
+    *shown = overlay;
+    Ok(())
+}
+
+// ── Flashing ────────────────────────────────────────────────────────────────
+
+/// Blink the tray icon until `stop_flash`, the window is focused or nothing
+/// is unread anymore. Does nothing while nothing is unread.
+pub fn start_flash(app: &AppHandle) -> Result<(), String> {
+    let badge = app.state::<TrayBadge>();
+    let mut flash = badge.flash.lock().unwrap();
+    if flash.is_some() || badge.shown.lock().unwrap().count == 0 {
+        return Ok(());
+    }
+    let tray = app.tray_by_id("main-tray").ok_or("Tray not found")?;
+    let base = tray_icon();
+    let blank = Image::new_owned(vec![0; base.rgba().len()], base.width(), base.height());
+
+    log::debug!("Flashing the tray icon");
+    let app = app.clone();
+    *flash = Some(tauri::async_runtime::spawn(async move {
+        let mut on = true;
+        loop {
+            tokio::time::sleep(FLASH_INTERVAL).await;
+            let overlay = *app.state::<TrayBadge>().shown.lock().unwrap();
+            if overlay.count == 0 {
+                stop_flash(&app);
+                return;
+            }
+            on = !on;
+            let image = if on { icon(overlay) } else { Ok(blank.clone()) };
+            if let Err(e) = image.and_then(|i| tray.set_icon(Some(i)).map_err(|e| e.to_string())) {
+                log::warn!("Failed to flash the tray icon: {}", e);
+            }
+        }
+    }));
+    Ok(())
+}
+
+/// Stop blinking and put the regular icon back.
+pub fn stop_flash(app: &AppHandle) {
+    let badge = app.state::<TrayBadge>();
+    let Some(task) = badge.flash.lock().unwrap().take() else {
+        return;
+    };
+    task.abort();
+    let overlay = *badge.shown.lock().unwrap();
+    if let Some(tray) = app.tray_by_id("main-tray") {
+        if let Err(e) =
+            icon(overlay).and_then(|i| tray.set_icon(Some(i)).map_err(|e| e.to_string()))
+        {
+            log::warn!("Failed to restore the tray icon: {}", e);
+        }
+    }
+}
+
+/// Summarize `counts` in the tray tooltip, e.g. "Pester — 5 unread from 2
+/// chats". Windows and macOS only; Linux trays have no tooltips.
+pub fn set_tray_tooltip(app: &AppHandle, counts: &HashMap<String, u32>) {
+    let Some(tray) = app.tray_by_id("main-tray") else {
+        return;
+    };
+    let total: u32 = counts.values().sum();
+    let tooltip = match (total, counts.len()) {
+        (0, _) => "Pester".to_string(),
+        (total, 1) => format!("Pester — {} unread from 1 chat", total),
+        (total, chats) => format!("Pester — {} unread from {} chats", total, chats),
+    };
+    if let Err(e) = tray.set_tooltip(Some(tooltip)) {
+        log::debug!("Failed to set tray tooltip: {}", e);
+    }
+}
+
+/// Show the user's status as a colored dot on the tray icon; `None`
+/// removes it.
+pub fn set_presence(app: &AppHandle, presence: Option<Presence>) -> Result<(), String> {
+    redraw(app, &app.state::<TrayBadge>(), |overlay| {
+        overlay.presence = presence
+    })?;
+    log::debug!("Tray status set to {:?}", presence);
+    Ok(())
+}
+
+/// Show `count` unread messages on the tray icon; zero clears the badge.
+#[tauri::command]
+pub fn set_unread_count(
+    app: AppHandle,
+    badge: State<'_, TrayBadge>,
+    count: u32,
+) -> Result<(), String> {
+    redraw(&app, &badge, |overlay| overlay.count = count)?;
+    log::debug!("Tray badge set to {}", count);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn start_tray_flash(app: AppHandle) -> Result<(), String> {
+    start_flash(&app)
+}
+
+#[tauri::command]
+pub fn stop_tray_flash(app: AppHandle) {
+    stop_flash(&app)
+}
@@ -0,0 +1,184 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+use tauri::{
+    AppHandle, Emitter, Manager, PhysicalPosition, Position, State, WebviewUrl,
+    WebviewWindowBuilder, WindowEvent,
+};
+
+use crate::{actions, conversations, features::FeatureFlags, prefs};
+
+const LABEL: &str = "bubble";
+const POSITIONS_KEY: &str = "bubble_positions";
+const WIDTH: f64 = 260.0;
+const HEIGHT: f64 = 64.0;
+
+/// What the bubble window is currently showing.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BubbleContent {
+    pub conversation: String,
+    pub sender: String,
+    pub snippet: String,
+}
+
+#[derive(Default)]
+pub struct BubbleState(Mutex<Option<BubbleContent>>);
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+struct SavedPosition {
+    x: i32,
+    y: i32,
+}
+
+/// Conversation ID → where the user last dragged its bubble.
+type Positions = HashMap<String, SavedPosition>;
+
+fn main_window_visible(app: &AppHandle) -> bool {
+    app.get_webview_window("main")
+        .and_then(|w| w.is_visible().ok())
+        .unwrap_or(false)
+}
+
+/// Bottom-right corner of the primary monitor, clear of the taskbar.
+fn default_position(app: &AppHandle) -> Option<PhysicalPosition<i32>> {
+    let monitor = app.primary_monitor().ok()??;
+    let scale = monitor.scale_factor();
+    let area = monitor.work_area();
+    Some(PhysicalPosition {
+        x: area.position.x + area.size.width as i32 - ((WIDTH + 16.0) * scale) as i32,
+        y: area.position.y + area.size.height as i32 - ((HEIGHT + 16.0) * scale) as i32,
+    })
+}
+
+fn position_for(app: &AppHandle, conversation: &str) -> Option<PhysicalPosition<i32>> {
+    let positions: Positions = prefs::load(app, POSITIONS_KEY).unwrap_or_default();
+    match positions.get(conversation) {
+        Some(saved) => Some(PhysicalPosition {
+            x: saved.x,
+            y: saved.y,
+        }),
+        None => default_position(app),
+    }
+}
+
+fn save_position(app: &AppHandle, position: PhysicalPosition<i32>) {
+    let Some(conversation) = app
+        .state::<BubbleState>()
+        .0
+        .lock()
+        .unwrap()
+        .as_ref()
+        .map(|c| c.conversation.clone())
+    else {
+        return;
+    };
+    let mut positions: Positions = prefs::load(app, POSITIONS_KEY).unwrap_or_default();
+    positions.insert(
+        conversation,
+        SavedPosition {
+            x: position.x,
+            y: position.y,
+        },
+    );
+    if let Err(e) = prefs::save(app, POSITIONS_KEY, &positions) {
+        log::warn!("Failed to save bubble position: {}", e);
+    }
+}
+
+fn close(app: &AppHandle) {
+    if let Some(window) = app.get_webview_window(LABEL) {
+        let _ = window.close();
+    }
+    app.state::<BubbleState>().0.lock().unwrap().take();
+}
+
+/// Show a heads-up bubble for a new message while the main window is
+/// hidden. Returns whether a bubble is showing. Off unless the
+/// `chat_bubbles` feature flag is enabled.
+#[tauri::command]
+pub async fn show_bubble(
+    app: AppHandle,
+    state: State<'_, BubbleState>,
+    conversation: String,
+    sender: String,
+    snippet: String,
+) -> Result<bool, String> {
+    if !app.state::<FeatureFlags>().is_enabled("chat_bubbles")
+        || main_window_visible(&app)
+        || conversations::is_muted(&app, &conversation)
+    {
+        return Ok(false);
+    }
+
+    let content = BubbleContent {
+        conversation,
+        sender,
+        snippet,
+    };
+    let position = position_for(&app, &content.conversation);
+    *state.0.lock().unwrap() = Some(content.clone());
+
+    if let Some(window) = app.get_webview_window(LABEL) {
+        if let Some(position) = position {
+            let _ = window.set_position(Position::Physical(position));
+        }
+        let _ = window.emit("bubble-updated", &content);
+        return Ok(true);
+    }
+
+    let window =
+        WebviewWindowBuilder::new(&app, LABEL, WebviewUrl::App("index.html#bubble".into()))
+            .title("Pester")
+            .inner_size(WIDTH, HEIGHT)
+            .decorations(false)
+            .resizable(false)
+            .always_on_top(true)
+            .skip_taskbar(true)
+            .focused(false)
+            .visible(false)
+            .build()
+            .map_err(|e| e.to_string())?;
+    if let Some(position) = position {
+        window
+            .set_position(Position::Physical(position))
+            .map_err(|e| e.to_string())?;
+    }
+    window.show().map_err(|e| e.to_string())?;
+
+    let handle = app.clone();
+    window.on_window_event(move |event| {
+        if let WindowEvent::Moved(position) = event {
+            save_position(&handle, *position);
+        }
+    });
+
+    log::debug!("Showing bubble for {}", content.conversation);
+    Ok(true)
+}
+
+#[tauri::command]
+pub fn get_bubble(state: State<'_, BubbleState>) -> Option<BubbleContent> {
+    state.0.lock().unwrap().clone()
+}
+
+/// Bubble was clicked: close it and open the chat in the main window.
+#[tauri::command]
+pub fn open_bubble_chat(app: AppHandle, state: State<'_, BubbleState>) {
+    let conversation = state
+        .0
+        .lock()
+        .unwrap()
+        .as_ref()
+        .map(|c| c.conversation.clone());
+    close(&app);
+    if let Some(conversation) = conversation {
+        actions::open_chat(&app, &conversation);
+    }
+}
+
+#[tauri::command]
+pub fn dismiss_bubble(app: AppHandle) {
+    close(&app);
+}
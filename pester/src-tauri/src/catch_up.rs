@@ -0,0 +1,93 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::notifications;
+use crate::unread::UnreadState;
+
+/// How long after the first held message the summary waits for the rest.
+const SETTLE: Duration = Duration::from_secs(2);
+
+#[derive(Default)]
+struct Inner {
+    /// Server time of the last handshake.
+    registered_at: Option<i64>,
+    /// Held messages per conversation since the last summary.
+    counts: HashMap<String, u32>,
+    flush_pending: bool,
+}
+
+/// The server holds messages while we're offline and sends them right
+/// after registering, all older than the handshake. They're stored and
+/// counted as unread like any other, but summed up in one notification
+/// instead of a toast each.
+#[derive(Default)]
+pub struct CatchUp(Mutex<Inner>);
+
+impl CatchUp {
+    /// A new handshake at `timestamp`, server time.
+    pub fn registered(&self, timestamp: i64) {
+        self.0.lock().unwrap().registered_at = Some(timestamp);
+    }
+
+    /// Whether a message from `sender` sent at `timestamp` was held while
+    /// we were offline. Those are counted as unread here, and the webview
+    /// leaves them alone.
+    pub fn accept(&self, app: &AppHandle, sender: &str, timestamp: i64) -> bool {
+        let mut inner = self.0.lock().unwrap();
+        if inner.registered_at.is_none_or(|at| timestamp >= at) {
+            return false;
+        }
+        *inner.counts.entry(sender.to_string()).or_default() += 1;
+        if !inner.flush_pending {
+            inner.flush_pending = true;
+            let app = app.clone();
+            tauri::async_runtime::spawn(async move {
+                tokio::time::sleep(SETTLE).await;
+                flush(&app);
+            });
+        }
+        drop(inner);
+
+        app.state::<UnreadState>()
+            .increment(app, sender.to_string());
+        true
+    }
+}
+
+fn flush(app: &AppHandle) {
+    let counts = {
+        let catch_up = app.state::<CatchUp>();
+        let mut inner = catch_up.0.lock().unwrap();
+        inner.flush_pending = false;
+        std::mem::take(&mut inner.counts)
+    };
+    let total: u32 = counts.values().sum();
+    log::debug!(
+        "Caught up on {} messages from {} chats",
+        total,
+        counts.len()
+    );
+    let _ = app.emit("catch-up-finished", &counts);
+
+    // Muted chats don't count towards the summary
+    let unmuted: Vec<(&String, &u32)> = counts
+        .iter()
+        .filter(|(c, _)| !crate::conversations::is_muted(app, c))
+        .collect();
+    let body = match unmuted.as_slice() {
+        [] => return,
+        [(sender, 1)] => format!("1 new message from {}", sender),
+        [(sender, count)] => format!("{} new messages from {}", count, sender),
+        chats => format!(
+            "{} new messages from {} chats",
+            chats.iter().map(|(_, n)| **n).sum::<u32>(),
+            chats.len()
+        ),
+    };
+    if let Err(e) = notifications::notify(app, "While you were away".to_string(), body) {
+        log::warn!("Failed to show catch-up summary: {}", e);
+    }
+}
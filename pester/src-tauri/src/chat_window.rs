@@ -0,0 +1,85 @@
+use std::collections::HashMap;
+
+use tauri::{AppHandle, Manager, WebviewUrl, WebviewWindow, WebviewWindowBuilder, WindowEvent};
+
+use crate::prefs;
+use crate::window_state::{self, Geometry};
+
+const LABEL_PREFIX: &str = "chat-";
+const GEOMETRY_KEY: &str = "chat_window_geometry";
+const WIDTH: f64 = 380.0;
+const HEIGHT: f64 = 560.0;
+const MIN_WIDTH: f64 = 280.0;
+const MIN_HEIGHT: f64 = 320.0;
+
+/// Conversation ID → where its popout was last, in physical pixels.
+type Geometries = HashMap<String, Geometry>;
+
+/// Window labels only allow a few characters, so anything else becomes `_`.
+fn label(conversation: &str) -> String {
+    let id: String = conversation
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    format!("{}{}", LABEL_PREFIX, id)
+}
+
+fn save_geometry(window: &WebviewWindow, conversation: &str) {
+    let app = window.app_handle();
+    let mut geometries: Geometries = prefs::load(app, GEOMETRY_KEY).unwrap_or_default();
+    let previous = geometries.get(conversation).copied();
+    let Some(geometry) = window_state::capture(window, previous) else {
+        return;
+    };
+    geometries.insert(conversation.to_string(), geometry);
+    if let Err(e) = prefs::save(app, GEOMETRY_KEY, &geometries) {
+        log::warn!("Failed to save chat window geometry: {}", e);
+    }
+}
+
+/// Open `conversation_id` in a window of its own, or bring its window up
+/// if it already has one. Each popout remembers its size and position.
+#[tauri::command]
+pub async fn open_chat_window(app: AppHandle, conversation_id: String) -> Result<(), String> {
+    let label = label(&conversation_id);
+    if let Some(window) = app.get_webview_window(&label) {
+        let _ = window.unminimize();
+        window.show().map_err(|e| e.to_string())?;
+        return window.set_focus().map_err(|e| e.to_string());
+    }
+
+    let saved = prefs::load::<_, Geometries>(&app, GEOMETRY_KEY)
+        .unwrap_or_default()
+        .remove(&conversation_id);
+    let url = format!("index.html#chat/{}", conversation_id);
+    let window = WebviewWindowBuilder::new(&app, &label, WebviewUrl::App(url.into()))
+        .title(format!("Pester — {}", conversation_id))
+        .inner_size(WIDTH, HEIGHT)
+        .min_inner_size(MIN_WIDTH, MIN_HEIGHT)
+        .decorations(false)
+        .visible(false)
+        .build()
+        .map_err(|e| e.to_string())?;
+    if let Some(saved) = saved {
+        window_state::apply(&window, &saved)?;
+    }
+    crate::always_on_top::restore(&window);
+    window.show().map_err(|e| e.to_string())?;
+
+    // Unlike the main window, closing a popout really closes it
+    let handle = window.clone();
+    window.on_window_event(move |event| match event {
+        WindowEvent::Moved(_) | WindowEvent::Resized(_) => save_geometry(&handle, &conversation_id),
+        WindowEvent::Destroyed => log::debug!("Closed chat window for {}", conversation_id),
+        _ => {}
+    });
+
+    log::debug!("Opened chat window {}", label);
+    Ok(())
+}
@@ -0,0 +1,350 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use rand_core::{OsRng, RngCore};
+
+/// The server rejects frames with more text than this, counted in UTF-16
+/// code units like JavaScript string lengths.
+pub const FRAME_UNITS: usize = 300;
+/// Longest message accepted for sending, in UTF-16 code units, split into
+/// parts as needed.
+pub const MAX_MESSAGE_UNITS: usize = 64 * 1024;
+
+/// Wraps the part header so it can't be mistaken for typed text, and ends
+/// the body so the server's trim can't eat whitespace at a part boundary.
+const MARKER: char = '\u{2063}';
+/// Most parts a message can be reassembled from.
+const MAX_PARTS: usize = MAX_MESSAGE_UNITS / 200;
+/// Parts of a message that never completes are forgotten after this long
+/// without a new one.
+pub const PARTIAL_TTL: Duration = Duration::from_secs(10 * 60);
+/// Unfinished messages kept per sender; opening another drops the oldest.
+const MAX_GROUPS_PER_SENDER: usize = 8;
+/// Bytes of unfinished messages kept from everyone together; past this the
+/// oldest are dropped.
+const MAX_BUFFERED_BYTES: usize = 4 * 1024 * 1024;
+
+/// Length as the server measures it.
+pub fn utf16_len(text: &str) -> usize {
+    text.encode_utf16().count()
+}
+
+/// Cut `text` into bodies of at most `budget` UTF-16 code units, on char
+/// boundaries.
+fn chunks(text: &str, budget: usize) -> Vec<&str> {
+    let mut chunks = Vec::new();
+    let (mut start, mut units) = (0, 0);
+    for (i, c) in text.char_indices() {
+        if units + c.len_utf16() > budget {
+            chunks.push(&text[start..i]);
+            (start, units) = (i, 0);
+        }
+        units += c.len_utf16();
+    }
+    chunks.push(&text[start..]);
+    chunks
+}
+
+/// Split `text` into frames that fit the server limit. Each part of a long
+/// message is `⁣<group>:<index>/<total>⁣<body>⁣`; short ones are sent as is.
+pub fn split(text: &str) -> Vec<String> {
+    if utf16_len(text) <= FRAME_UNITS {
+        return vec![text.to_string()];
+    }
+
+    let group = format!("{:08x}", OsRng.next_u32());
+    // Three markers, `:`, `/` and the numbers, all one unit per char
+    let budget = |total: usize| FRAME_UNITS - (5 + group.len() + 2 * total.to_string().len());
+    // The header grows with the part count, so settle on a stable size.
+    let mut total = 1;
+    let parts = loop {
+        let parts = chunks(text, budget(total));
+        if parts.len() == total {
+            break parts;
+        }
+        total = parts.len();
+    };
+
+    parts
+        .iter()
+        .enumerate()
+        .map(|(i, body)| format!("{MARKER}{group}:{}/{total}{MARKER}{body}{MARKER}", i + 1))
+        .collect()
+}
+
+struct Part<'a> {
+    group: &'a str,
+    index: usize,
+    total: usize,
+    body: &'a str,
+}
+
+fn parse(text: &str) -> Option<Part<'_>> {
+    let rest = text.strip_prefix(MARKER)?;
+    let (header, body) = rest.split_once(MARKER)?;
+    // Parts from before the closing marker was added end without it
+    let body = body.strip_suffix(MARKER).unwrap_or(body);
+    let (group, position) = header.split_once(':')?;
+    let (index, total) = position.split_once('/')?;
+    let (index, total) = (index.parse().ok()?, total.parse().ok()?);
+    if index == 0 || index > total || total > MAX_PARTS {
+        return None;
+    }
+    Some(Part {
+        group,
+        index,
+        total,
+        body,
+    })
+}
+
+struct Partial {
+    total: usize,
+    parts: BTreeMap<usize, String>,
+    /// Server time of the first part seen, which the whole message keeps.
+    timestamp: i64,
+    updated: Instant,
+    /// Order the group was opened in, oldest lowest.
+    opened: u64,
+}
+
+impl Partial {
+    fn bytes(&self) -> usize {
+        self.parts.values().map(String::len).sum()
+    }
+}
+
+/// Sender and group of an unfinished message.
+type GroupKey = (String, String);
+
+/// Forget the oldest unfinished message whose key passes `filter`.
+fn drop_oldest(partials: &mut HashMap<GroupKey, Partial>, filter: impl Fn(&GroupKey) -> bool) {
+    let oldest = partials
+        .iter()
+        .filter(|(key, _)| filter(key))
+        .min_by_key(|(_, partial)| partial.opened)
+        .map(|(key, _)| key.clone());
+    if let Some(key) = oldest {
+        log::debug!("Dropping an unfinished message from {}", key.0);
+        partials.remove(&key);
+    }
+}
+
+/// Collects the parts of incoming long messages until they're complete.
+/// How many, and how much, is capped so a peer can't fill memory with
+/// messages it never finishes.
+#[derive(Default)]
+pub struct Reassembler {
+    partials: Mutex<HashMap<GroupKey, Partial>>,
+    opened: AtomicU64,
+}
+
+impl Reassembler {
+    /// Feed one incoming frame. Returns the full text and timestamp once a
+    /// message is complete, right away for messages that weren't split.
+    pub fn accept(&self, sender: &str, text: &str, timestamp: i64) -> Option<(String, i64)> {
+        let Some(part) = parse(text) else {
+            return Some((text.to_string(), timestamp));
+        };
+
+        let mut partials = self.partials.lock().unwrap();
+        partials.retain(|_, p| p.updated.elapsed() < PARTIAL_TTL);
+
+        let key = (sender.to_string(), part.group.to_string());
+        if !partials.contains_key(&key) {
+            let open = partials.keys().filter(|(s, _)| s == sender).count();
+            if open >= MAX_GROUPS_PER_SENDER {
+                drop_oldest(&mut partials, |(s, _)| s == sender);
+            }
+        }
+        let partial = partials.entry(key.clone()).or_insert_with(|| Partial {
+            total: part.total,
+            parts: BTreeMap::new(),
+            timestamp,
+            updated: Instant::now(),
+            opened: self.opened.fetch_add(1, Ordering::Relaxed),
+        });
+        if partial.total != part.total {
+            log::debug!("Ignoring part with mismatched count from {}", sender);
+            return None;
+        }
+        partial.parts.insert(part.index, part.body.to_string());
+        partial.timestamp = partial.timestamp.min(timestamp);
+        partial.updated = Instant::now();
+        if partial.parts.len() < partial.total {
+            while partials.values().map(Partial::bytes).sum::<usize>() > MAX_BUFFERED_BYTES {
+                drop_oldest(&mut partials, |_| true);
+            }
+            return None;
+        }
+
+        let partial = partials.remove(&key)?;
+        log::debug!(
+            "Reassembled a {}-part message from {}",
+            partial.total,
+            sender
+        );
+        Some((partial.parts.into_values().collect(), partial.timestamp))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// What the server does to each frame before relaying it.
+    fn relay(frames: Vec<String>) -> Vec<String> {
+        frames.iter().map(|f| f.trim().to_string()).collect()
+    }
+
+    fn reassemble(frames: &[String]) -> String {
+        let reassembler = Reassembler::default();
+        let mut done = None;
+        for (i, frame) in frames.iter().enumerate() {
+            let result = reassembler.accept("alice", frame, 1000 + i as i64);
+            if i + 1 < frames.len() {
+                assert!(result.is_none(), "complete after {} parts", i + 1);
+            }
+            done = result;
+        }
+        let (text, timestamp) = done.expect("never completed");
+        assert_eq!(timestamp, 1000);
+        text
+    }
+
+    fn assert_round_trip(text: &str) {
+        let frames = relay(split(text));
+        for frame in &frames {
+            assert!(
+                utf16_len(frame) <= FRAME_UNITS,
+                "frame too long: {}",
+                utf16_len(frame)
+            );
+        }
+        assert_eq!(reassemble(&frames), text);
+    }
+
+    #[test]
+    fn short_messages_are_sent_as_is() {
+        assert_eq!(split("hello"), vec!["hello".to_string()]);
+        let full = "a".repeat(FRAME_UNITS);
+        assert_eq!(split(&full), vec![full.clone()]);
+    }
+
+    #[test]
+    fn ascii_round_trip() {
+        assert_round_trip(&"abcdefghij".repeat(100));
+    }
+
+    #[test]
+    fn multibyte_round_trip() {
+        assert_round_trip(&"héllo wörld 中文 ".repeat(60));
+    }
+
+    #[test]
+    fn astral_text_is_measured_in_utf16() {
+        // 200 chars but 400 UTF-16 units, over the limit
+        let text = "😀".repeat(200);
+        assert!(split(&text).len() > 1);
+        assert_round_trip(&text);
+        assert_round_trip(&format!("a{}", "👍🏽".repeat(150)));
+    }
+
+    #[test]
+    fn whitespace_at_part_boundaries_survives_trimming() {
+        // Under ten parts, each body holds 285 units
+        for filler in [" ", "\n", " \n\t"] {
+            let text = format!("{}{}{}", "x".repeat(284), filler, " y ".repeat(200));
+            let frames = split(&text);
+            let first_body = frames[0].trim_end_matches(MARKER);
+            assert!(first_body.ends_with(char::is_whitespace));
+            assert_round_trip(&text);
+        }
+        assert_round_trip(&" spaced out \n".repeat(80));
+    }
+
+    #[test]
+    fn parts_can_arrive_out_of_order() {
+        let mut frames = split(&"z".repeat(900));
+        frames.reverse();
+        let reassembler = Reassembler::default();
+        let results: Vec<_> = frames
+            .iter()
+            .map(|f| reassembler.accept("bob", f, 5))
+            .collect();
+        assert!(results[..results.len() - 1].iter().all(Option::is_none));
+        assert_eq!(results.last().unwrap().as_ref().unwrap().0, "z".repeat(900));
+    }
+
+    #[test]
+    fn parts_without_the_closing_marker_still_parse() {
+        let part = parse("\u{2063}0000abcd:1/2\u{2063}body ").unwrap();
+        assert_eq!(part.body, "body ");
+        assert_eq!((part.index, part.total), (1, 2));
+    }
+
+    /// Part `index` of 2 in `group`, with `body`.
+    fn half(group: &str, index: usize, body: &str) -> String {
+        format!("{MARKER}{group}:{index}/2{MARKER}{body}{MARKER}")
+    }
+
+    #[test]
+    fn too_many_unfinished_messages_drop_the_oldest() {
+        let reassembler = Reassembler::default();
+        reassembler.accept("alice", &half("a", 1, "kept"), 1);
+        for group in 0..=MAX_GROUPS_PER_SENDER {
+            let group = group.to_string();
+            assert!(reassembler
+                .accept("mallory", &half(&group, 1, "x"), 1)
+                .is_none());
+        }
+
+        // The first was dropped, so its second half starts over
+        assert!(reassembler
+            .accept("mallory", &half("0", 2, "y"), 1)
+            .is_none());
+        let last = MAX_GROUPS_PER_SENDER.to_string();
+        assert_eq!(
+            reassembler.accept("mallory", &half(&last, 2, "y"), 1),
+            Some(("xy".to_string(), 1))
+        );
+        assert_eq!(
+            reassembler.accept("alice", &half("a", 2, "!"), 1),
+            Some(("kept!".to_string(), 1))
+        );
+    }
+
+    #[test]
+    fn too_many_buffered_bytes_drop_the_oldest() {
+        let reassembler = Reassembler::default();
+        let body = "x".repeat(MAX_BUFFERED_BYTES / 2);
+        for sender in ["a", "b", "c"] {
+            assert!(reassembler
+                .accept(sender, &half("g", 1, &body), 1)
+                .is_none());
+        }
+
+        assert!(reassembler.accept("a", &half("g", 2, ""), 1).is_none());
+        assert_eq!(
+            reassembler.accept("c", &half("g", 2, ""), 1),
+            Some((body, 1))
+        );
+    }
+
+    #[test]
+    fn bad_headers_are_plain_text() {
+        let reassembler = Reassembler::default();
+        for text in [
+            "\u{2063}g:0/2\u{2063}x",
+            "\u{2063}g:3/2\u{2063}x",
+            "\u{2063}nope",
+        ] {
+            assert_eq!(
+                reassembler.accept("carol", text, 1),
+                Some((text.to_string(), 1))
+            );
+        }
+    }
+}
@@ -0,0 +1,91 @@
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, State};
+
+use crate::prefs;
+
+const OFFSET_KEY: &str = "clock_offset_ms";
+
+/// Beyond this the local clock is wrong enough to tell the user about.
+const EXTREME_SKEW_MS: i64 = 5 * 60 * 1000;
+
+/// Local wall-clock time in milliseconds since the Unix epoch.
+pub fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// Offset between the server's clock and ours (`server - local`), measured
+/// during the register handshake and kept across restarts.
+pub struct ClockSkew {
+    offset_ms: AtomicI64,
+}
+
+impl ClockSkew {
+    pub fn load(app: &AppHandle) -> Self {
+        let offset_ms = prefs::load(app, OFFSET_KEY).unwrap_or(0);
+        Self {
+            offset_ms: AtomicI64::new(offset_ms),
+        }
+    }
+
+    fn report(&self) -> SkewReport {
+        let offset_ms = self.offset_ms.load(Ordering::Relaxed);
+        SkewReport {
+            offset_ms,
+            extreme: offset_ms.abs() >= EXTREME_SKEW_MS,
+        }
+    }
+
+    /// Current time on the server's clock.
+    pub fn server_now(&self) -> i64 {
+        now_millis() as i64 + self.offset_ms.load(Ordering::Relaxed)
+    }
+
+    /// Record the server timestamp from the `registered` handshake. When the
+    /// time the register request was sent is known, the round trip is split
+    /// in half (NTP-style) so network latency doesn't count as skew.
+    pub fn record(
+        &self,
+        app: &AppHandle,
+        server_timestamp: i64,
+        request_sent_at: Option<i64>,
+    ) -> Result<SkewReport, String> {
+        let received_at = now_millis() as i64;
+        let local_at = match request_sent_at {
+            Some(sent) if sent <= received_at => sent + (received_at - sent) / 2,
+            _ => received_at,
+        };
+
+        let offset_ms = server_timestamp - local_at;
+        self.offset_ms.store(offset_ms, Ordering::Relaxed);
+        prefs::save(app, OFFSET_KEY, &offset_ms)?;
+
+        let report = self.report();
+        if report.extreme {
+            log::warn!("Local clock is off by {} ms from the server", offset_ms);
+            let _ = app.emit("clock-skew-detected", &report);
+        } else {
+            log::debug!("Clock offset to server: {} ms", offset_ms);
+        }
+
+        Ok(report)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SkewReport {
+    /// Add this to a local timestamp to get server time.
+    pub offset_ms: i64,
+    pub extreme: bool,
+}
+
+#[tauri::command]
+pub fn get_clock_skew(skew: State<'_, ClockSkew>) -> SkewReport {
+    skew.report()
+}
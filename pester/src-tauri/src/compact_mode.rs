@@ -0,0 +1,98 @@
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, LogicalSize, Manager, Size, WebviewWindow};
+
+use crate::prefs;
+use crate::window_state::{self, Geometry};
+
+const COMPACT_KEY: &str = "compact_mode";
+const WIDTH: f64 = 260.0;
+const HEIGHT: f64 = 44.0;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct CompactState {
+    enabled: bool,
+    /// The full window, to go back to.
+    restore: Option<Geometry>,
+    /// Whether it floated before, since compact mode always does.
+    was_on_top: bool,
+}
+
+fn state(app: &AppHandle) -> CompactState {
+    prefs::load(app, COMPACT_KEY).unwrap_or_default()
+}
+
+/// While on, the main window's geometry isn't saved, so it comes back
+/// full size.
+pub fn is_compact(app: &AppHandle) -> bool {
+    state(app).enabled
+}
+
+fn shrink(window: &WebviewWindow) -> Result<(), String> {
+    window
+        .set_size(Size::Logical(LogicalSize::new(WIDTH, HEIGHT)))
+        .map_err(|e| e.to_string())?;
+    window.set_always_on_top(true).map_err(|e| e.to_string())?;
+    window.set_skip_taskbar(true).map_err(|e| e.to_string())?;
+    // A bar this thin looks odd with a drop shadow
+    window.set_shadow(false).map_err(|e| e.to_string())
+}
+
+fn expand(window: &WebviewWindow, state: &CompactState) -> Result<(), String> {
+    window.set_shadow(true).map_err(|e| e.to_string())?;
+    window.set_skip_taskbar(false).map_err(|e| e.to_string())?;
+    window
+        .set_always_on_top(state.was_on_top)
+        .map_err(|e| e.to_string())?;
+    if let Some(geometry) = &state.restore {
+        window_state::apply(window, geometry)?;
+    }
+    Ok(())
+}
+
+/// Shrink the main window back to a bar if it was one when the app quit.
+pub fn restore(window: &WebviewWindow) {
+    if !is_compact(window.app_handle()) {
+        return;
+    }
+    if let Err(e) = shrink(window) {
+        log::warn!("Failed to restore compact mode: {}", e);
+    }
+}
+
+// ── Commands ────────────────────────────────────────────────────────────────
+
+#[tauri::command]
+pub fn get_compact_mode(app: AppHandle) -> bool {
+    is_compact(&app)
+}
+
+/// Turn the main window into a small bar that floats in a screen corner,
+/// or back into the full window where it was.
+#[tauri::command]
+pub fn set_compact_mode(app: AppHandle, enabled: bool) -> Result<(), String> {
+    let mut state = state(&app);
+    if state.enabled == enabled {
+        return Ok(());
+    }
+    let window = app
+        .get_webview_window("main")
+        .ok_or("Main window not found")?;
+
+    // Saved before shrinking so the resize isn't taken as the full size
+    if enabled {
+        state.restore = window_state::capture(&window, None);
+        state.was_on_top = window.is_always_on_top().unwrap_or(false);
+        state.enabled = true;
+        prefs::save(&app, COMPACT_KEY, &state)?;
+        shrink(&window)?;
+    } else {
+        expand(&window, &state)?;
+        state.enabled = false;
+        prefs::save(&app, COMPACT_KEY, &state)?;
+    }
+
+    log::debug!("Compact mode: {}", enabled);
+    let _ = app.emit_to("main", "compact-mode-changed", enabled);
+    Ok(())
+}
@@ -0,0 +1,500 @@
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use futures_util::{SinkExt, StreamExt};
+use serde::Serialize;
+use serde_json::{json, Value};
+use tauri::{AppHandle, Emitter, Manager, State};
+use tokio::net::TcpStream;
+use tokio::sync::{mpsc, oneshot};
+use tokio_tungstenite::{tungstenite::Message, MaybeTlsStream, WebSocketStream};
+
+use crate::a11y::{A11yBus, A11yEvent};
+use crate::catch_up::CatchUp;
+use crate::chunking::{self, Reassembler};
+use crate::clock::{self, ClockSkew};
+use crate::outbox::{Outbox, PendingSend};
+use crate::server;
+use crate::storage::messages::{self, StoredMessage};
+use crate::timeline::{ConnectionEventKind, ConnectionTimeline};
+
+/// First reconnect delay, doubled after every failed attempt.
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConnectionStatus {
+    #[default]
+    Disconnected,
+    Connecting,
+    Connected,
+    Registered,
+}
+
+impl ConnectionStatus {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Disconnected => "disconnected",
+            Self::Connecting => "connecting",
+            Self::Connected => "connected",
+            Self::Registered => "registered",
+        }
+    }
+}
+
+/// Payload of `connection-state` and result of `get_connection_state`.
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConnectionState {
+    pub status: ConnectionStatus,
+    pub user_id: Option<String>,
+    /// Why the last attempt failed or the server dropped us.
+    pub error: Option<String>,
+}
+
+/// A frame for the socket, optionally signalling once it's written.
+struct Outgoing {
+    frame: String,
+    written: Option<oneshot::Sender<()>>,
+}
+
+struct Session {
+    id: u64,
+    user_id: String,
+    /// Frames for the socket. Dropping the sender ends the session.
+    outgoing: mpsc::UnboundedSender<Outgoing>,
+}
+
+/// Owns the server socket: connects, registers, reconnects with exponential
+/// backoff and forwards every server frame to the webview as
+/// `server-message`.
+#[derive(Default)]
+pub struct ConnectionManager {
+    session: Mutex<Option<Session>>,
+    state: Mutex<ConnectionState>,
+    next_id: AtomicU64,
+    reassembler: Reassembler,
+}
+
+impl ConnectionManager {
+    fn is_current(&self, id: u64) -> bool {
+        self.session.lock().unwrap().as_ref().map(|s| s.id) == Some(id)
+    }
+
+    /// Update and broadcast the state, unless session `id` has been replaced
+    /// and is only winding down.
+    fn set_state(&self, app: &AppHandle, id: u64, status: ConnectionStatus, error: Option<String>) {
+        let state = {
+            let session = self.session.lock().unwrap();
+            let Some(session) = session.as_ref().filter(|s| s.id == id) else {
+                return;
+            };
+            let mut state = self.state.lock().unwrap();
+            *state = ConnectionState {
+                status,
+                user_id: Some(session.user_id.clone()),
+                error,
+            };
+            state.clone()
+        };
+        publish_state(app, state);
+    }
+
+    fn status(&self) -> ConnectionStatus {
+        self.state.lock().unwrap().status
+    }
+
+    pub fn is_registered(&self) -> bool {
+        self.status() == ConnectionStatus::Registered
+    }
+
+    pub fn user_id(&self) -> Option<String> {
+        self.state.lock().unwrap().user_id.clone()
+    }
+
+    fn enqueue(&self, frame: Value, written: Option<oneshot::Sender<()>>) -> Result<(), String> {
+        if self.status() != ConnectionStatus::Registered {
+            return Err("Not connected".to_string());
+        }
+        let session = self.session.lock().unwrap();
+        let session = session.as_ref().ok_or("Not connected")?;
+        session
+            .outgoing
+            .send(Outgoing {
+                frame: frame.to_string(),
+                written,
+            })
+            .map_err(|e| e.to_string())
+    }
+
+    fn send(&self, frame: Value) -> Result<(), String> {
+        self.enqueue(frame, None)
+    }
+
+    /// Send one chat frame, resolving the receiver once it's written to the
+    /// socket. If the connection goes first it's dropped unwritten, and the
+    /// outbox queues the message again. Callers go through the outbox so
+    /// the send delay applies and long messages are split.
+    pub fn send_text(
+        &self,
+        target_user_id: &str,
+        text: &str,
+    ) -> Result<oneshot::Receiver<()>, String> {
+        let (written, receiver) = oneshot::channel();
+        self.enqueue(
+            json!({ "type": "message", "targetUserId": target_user_id, "text": text }),
+            Some(written),
+        )?;
+        Ok(receiver)
+    }
+}
+
+fn publish_state(app: &AppHandle, state: ConnectionState) {
+    app.state::<A11yBus>().publish(A11yEvent::StatusChanged {
+        status: state.status.as_str().to_string(),
+    });
+    let _ = app.emit("connection-state", state);
+}
+
+fn record(app: &AppHandle, kind: ConnectionEventKind, detail: Option<String>) {
+    app.state::<ConnectionTimeline>()
+        .record(app, kind, detail, None);
+}
+
+/// How a live connection ended.
+enum Ended {
+    Closed(String),
+    Kicked(String),
+    /// `disconnect` was called or the session was replaced.
+    Stopped,
+}
+
+/// Await `fut` unless the session is stopped first. Frames left over from
+/// the last connection are dropped unwritten, which tells the outbox to
+/// queue their messages; new ones are refused before registration.
+async fn unless_stopped<T>(
+    outgoing: &mut mpsc::UnboundedReceiver<Outgoing>,
+    fut: impl Future<Output = T>,
+) -> Option<T> {
+    tokio::pin!(fut);
+    loop {
+        tokio::select! {
+            value = &mut fut => return Some(value),
+            // `None` means every sender is gone: stop.
+            frame = outgoing.recv() => {
+                frame?;
+            }
+        }
+    }
+}
+
+async fn run(
+    app: AppHandle,
+    id: u64,
+    user_id: String,
+    mut outgoing: mpsc::UnboundedReceiver<Outgoing>,
+) {
+    let manager = app.state::<ConnectionManager>();
+    let mut backoff = INITIAL_BACKOFF;
+
+    loop {
+        manager.set_state(&app, id, ConnectionStatus::Connecting, None);
+        let Some(url) = unless_stopped(&mut outgoing, server::websocket_url(&app)).await else {
+            return;
+        };
+        // Failures carry what goes in the timeline and what the user sees
+        let result = match url {
+            Ok(url) => {
+                record(&app, ConnectionEventKind::Connecting, Some(url.clone()));
+                let Some(result) =
+                    unless_stopped(&mut outgoing, tokio_tungstenite::connect_async(&url)).await
+                else {
+                    return;
+                };
+                result.map_err(|e| {
+                    log::debug!("Connection to {} failed: {}", url, e);
+                    (
+                        e.to_string(),
+                        "Connection failed. Is the server running?".to_string(),
+                    )
+                })
+            }
+            Err(e) => {
+                log::debug!("Couldn't resolve the server: {}", e);
+                Err((e.clone(), e))
+            }
+        };
+
+        match result {
+            Ok((socket, _)) => {
+                manager.set_state(&app, id, ConnectionStatus::Connected, None);
+                record(&app, ConnectionEventKind::Connected, None);
+
+                match drive(&app, id, socket, &user_id, &mut outgoing, &mut backoff).await {
+                    Ended::Closed(reason) => {
+                        log::debug!("Connection closed: {}", reason);
+                        record(&app, ConnectionEventKind::Disconnected, Some(reason));
+                        manager.set_state(&app, id, ConnectionStatus::Disconnected, None);
+                    }
+                    Ended::Kicked(message) => {
+                        log::debug!("Kicked by server: {}", message);
+                        record(&app, ConnectionEventKind::Kicked, Some(message.clone()));
+                        manager.set_state(&app, id, ConnectionStatus::Disconnected, Some(message));
+                        return;
+                    }
+                    Ended::Stopped => return,
+                }
+            }
+            Err((detail, message)) => {
+                record(&app, ConnectionEventKind::Failed, Some(detail));
+                manager.set_state(&app, id, ConnectionStatus::Disconnected, Some(message));
+            }
+        }
+
+        app.state::<ConnectionTimeline>().record(
+            &app,
+            ConnectionEventKind::Backoff,
+            None,
+            Some(backoff.as_millis() as u64),
+        );
+        if unless_stopped(&mut outgoing, tokio::time::sleep(backoff))
+            .await
+            .is_none()
+        {
+            return;
+        }
+        backoff = (backoff * 2).min(MAX_BACKOFF);
+    }
+}
+
+/// Register, then pump frames both ways until the socket closes or the
+/// session is stopped.
+async fn drive(
+    app: &AppHandle,
+    id: u64,
+    socket: WebSocketStream<MaybeTlsStream<TcpStream>>,
+    user_id: &str,
+    outgoing: &mut mpsc::UnboundedReceiver<Outgoing>,
+    backoff: &mut Duration,
+) -> Ended {
+    let (mut sink, mut stream) = socket.split();
+
+    let register_sent_at = clock::now_millis() as i64;
+    let register = json!({ "type": "register", "userId": user_id });
+    if let Err(e) = sink.send(Message::text(register.to_string())).await {
+        return Ended::Closed(e.to_string());
+    }
+
+    loop {
+        tokio::select! {
+            incoming = stream.next() => match incoming {
+                Some(Ok(Message::Text(text))) => {
+                    if let Some(ended) = handle_frame(app, id, &text, register_sent_at, backoff) {
+                        return ended;
+                    }
+                }
+                Some(Ok(Message::Close(frame))) => {
+                    let reason = frame
+                        .map(|f| f.reason.to_string())
+                        .filter(|r| !r.is_empty())
+                        .unwrap_or_else(|| "closed by server".to_string());
+                    return Ended::Closed(reason);
+                }
+                Some(Ok(_)) => {}
+                Some(Err(e)) => return Ended::Closed(e.to_string()),
+                None => return Ended::Closed("closed by server".to_string()),
+            },
+            frame = outgoing.recv() => match frame {
+                Some(Outgoing { frame, written }) => {
+                    // A frame that fails to write drops `written`, so its
+                    // message goes back to the send queue
+                    if let Err(e) = sink.send(Message::text(frame)).await {
+                        return Ended::Closed(e.to_string());
+                    }
+                    if let Some(written) = written {
+                        let _ = written.send(());
+                    }
+                }
+                None => {
+                    let _ = sink.send(Message::Close(None)).await;
+                    return Ended::Stopped;
+                }
+            },
+        }
+    }
+}
+
+fn handle_frame(
+    app: &AppHandle,
+    id: u64,
+    text: &str,
+    register_sent_at: i64,
+    backoff: &mut Duration,
+) -> Option<Ended> {
+    let Ok(mut frame) = serde_json::from_str::<Value>(text) else {
+        log::debug!("Ignoring malformed server frame");
+        return None;
+    };
+    let manager = app.state::<ConnectionManager>();
+    if !manager.is_current(id) {
+        return None;
+    }
+
+    // Parts of a long message only go further once it's complete.
+    if frame["type"] == "message" {
+        let sender = frame["fromUserId"].as_str().unwrap_or_default();
+        let text = frame["text"].as_str().unwrap_or_default();
+        let timestamp = frame["timestamp"].as_i64().unwrap_or_default();
+        let (text, timestamp) = manager.reassembler.accept(sender, text, timestamp)?;
+        frame["text"] = json!(text);
+        frame["timestamp"] = json!(timestamp);
+    }
+
+    match frame["type"].as_str() {
+        Some("registered") => {
+            if let Some(timestamp) = frame["timestamp"].as_i64() {
+                app.state::<CatchUp>().registered(timestamp);
+                if let Err(e) =
+                    app.state::<ClockSkew>()
+                        .record(app, timestamp, Some(register_sent_at))
+                {
+                    log::warn!("Failed to record clock offset: {}", e);
+                }
+            }
+            *backoff = INITIAL_BACKOFF;
+            manager.set_state(app, id, ConnectionStatus::Registered, None);
+            record(app, ConnectionEventKind::Registered, None);
+            let app = app.clone();
+            tauri::async_runtime::spawn(async move { app.state::<Outbox>().flush(&app).await });
+        }
+        Some("message") => {
+            let sender = frame["fromUserId"].as_str().unwrap_or_default();
+            let text = frame["text"].as_str().unwrap_or_default();
+            let timestamp = frame["timestamp"].as_i64().unwrap_or_default();
+            let message_id = messages::new_id();
+            messages::record(
+                app,
+                StoredMessage {
+                    id: message_id.clone(),
+                    conversation_id: sender.to_string(),
+                    from_user_id: sender.to_string(),
+                    text: text.to_string(),
+                    timestamp,
+                },
+            );
+            app.state::<A11yBus>().publish(A11yEvent::MessageReceived {
+                conversation: sender.to_string(),
+                sender: sender.to_string(),
+                summary: text.to_string(),
+            });
+            if app.state::<CatchUp>().accept(app, sender, timestamp) {
+                frame["catchUp"] = json!(true);
+            }
+            frame["id"] = json!(message_id);
+        }
+        _ => {}
+    }
+
+    let _ = app.emit("server-message", &frame);
+
+    if frame["type"] == "kicked" {
+        let message = frame["message"].as_str().unwrap_or("Kicked by server");
+        return Some(Ended::Kicked(message.to_string()));
+    }
+    None
+}
+
+/// Connect and register as `user_id`, reconnecting until `disconnect`.
+/// Calling it again for the same user keeps the existing connection.
+#[tauri::command]
+pub fn connect(
+    app: AppHandle,
+    manager: State<'_, ConnectionManager>,
+    user_id: String,
+) -> Result<(), String> {
+    let user_id = user_id.trim().to_string();
+    if user_id.is_empty() {
+        return Err("User ID cannot be empty".to_string());
+    }
+
+    let mut session = manager.session.lock().unwrap();
+    if session
+        .as_ref()
+        .is_some_and(|s| s.user_id == user_id && !s.outgoing.is_closed())
+    {
+        return Ok(());
+    }
+
+    let id = manager.next_id.fetch_add(1, Ordering::Relaxed);
+    let (outgoing, receiver) = mpsc::unbounded_channel();
+    // Replacing the old session drops its sender, which closes it cleanly.
+    *session = Some(Session {
+        id,
+        user_id: user_id.clone(),
+        outgoing,
+    });
+    drop(session);
+
+    log::debug!("Connecting as {}", user_id);
+    tauri::async_runtime::spawn(run(app, id, user_id, receiver));
+    Ok(())
+}
+
+#[tauri::command]
+pub fn disconnect(app: AppHandle, manager: State<'_, ConnectionManager>) {
+    if manager.session.lock().unwrap().take().is_none() {
+        return;
+    }
+    record(
+        &app,
+        ConnectionEventKind::Disconnected,
+        Some("signed out".to_string()),
+    );
+    let state = ConnectionState::default();
+    *manager.state.lock().unwrap() = state.clone();
+    publish_state(&app, state);
+}
+
+#[tauri::command]
+pub async fn send_message(
+    app: AppHandle,
+    manager: State<'_, ConnectionManager>,
+    outbox: State<'_, Outbox>,
+    target_user_id: String,
+    text: String,
+    incognito: bool,
+) -> Result<PendingSend, String> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Err("Message cannot be empty".to_string());
+    }
+    if chunking::utf16_len(text) > chunking::MAX_MESSAGE_UNITS {
+        return Err(format!(
+            "Message must be {} characters or less",
+            chunking::MAX_MESSAGE_UNITS
+        ));
+    }
+    // Sending while disconnected is fine: the outbox queues the message.
+    if manager.user_id().is_none() {
+        return Err("Not signed in".to_string());
+    }
+    outbox
+        .submit(&app, target_user_id, text.to_string(), incognito)
+        .await
+}
+
+#[tauri::command]
+pub fn send_typing(
+    manager: State<'_, ConnectionManager>,
+    target_user_id: String,
+) -> Result<(), String> {
+    manager.send(json!({ "type": "typing", "targetUserId": target_user_id }))
+}
+
+#[tauri::command]
+pub fn get_connection_state(manager: State<'_, ConnectionManager>) -> ConnectionState {
+    manager.state.lock().unwrap().clone()
+}
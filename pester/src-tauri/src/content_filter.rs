@@ -0,0 +1,248 @@
+use std::collections::{BTreeSet, HashMap};
+use std::sync::LazyLock;
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+use crate::prefs;
+
+const CONFIG_KEY: &str = "content_filter";
+
+struct Rule {
+    id: &'static str,
+    label: &'static str,
+    /// Rule set the rule belongs to; sets can be toggled as a whole.
+    set: &'static str,
+    pattern: &'static str,
+    /// Extra check on each match to cut down false positives.
+    verify: Option<fn(&str) -> bool>,
+}
+
+const RULES: &[Rule] = &[
+    Rule {
+        id: "aws_access_key",
+        label: "AWS access key",
+        set: "secrets",
+        pattern: r"\b(?:AKIA|ASIA)[0-9A-Z]{16}\b",
+        verify: None,
+    },
+    Rule {
+        id: "aws_secret_key",
+        label: "AWS secret key",
+        set: "secrets",
+        pattern: r"(?i)aws_?secret_?(?:access_?)?key\s*[:=]\s*[A-Za-z0-9/+=]{40}\b",
+        verify: None,
+    },
+    Rule {
+        id: "private_key",
+        label: "Private key",
+        set: "secrets",
+        pattern: r"-----BEGIN (?:[A-Z]+ )*PRIVATE KEY(?: BLOCK)?-----",
+        verify: None,
+    },
+    Rule {
+        id: "github_token",
+        label: "GitHub token",
+        set: "secrets",
+        pattern: r"\b(?:gh[pousr]_[A-Za-z0-9]{36}|github_pat_[A-Za-z0-9_]{82})\b",
+        verify: None,
+    },
+    Rule {
+        id: "credit_card",
+        label: "Credit card number",
+        set: "pii",
+        pattern: r"\b\d(?:[ -]?\d){12,18}\b",
+        verify: Some(luhn),
+    },
+];
+
+static COMPILED: LazyLock<Vec<Regex>> = LazyLock::new(|| {
+    RULES
+        .iter()
+        .map(|rule| Regex::new(rule.pattern).expect("built-in filter pattern"))
+        .collect()
+});
+
+/// Card numbers carry a Luhn check digit; random digit runs mostly don't.
+fn luhn(candidate: &str) -> bool {
+    let digits: Vec<u32> = candidate.chars().filter_map(|c| c.to_digit(10)).collect();
+    if !(13..=19).contains(&digits.len()) {
+        return false;
+    }
+    let sum: u32 = digits
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(i, &d)| match i % 2 {
+            0 => d,
+            _ if d * 2 > 9 => d * 2 - 9,
+            _ => d * 2,
+        })
+        .sum();
+    sum.is_multiple_of(10)
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct FilterConfig {
+    /// Rule IDs switched off everywhere.
+    disabled: BTreeSet<String>,
+    /// Conversation ID → rule IDs that are fine to send there.
+    allowlist: HashMap<String, BTreeSet<String>>,
+}
+
+fn load_config(app: &AppHandle) -> FilterConfig {
+    prefs::load(app, CONFIG_KEY).unwrap_or_default()
+}
+
+/// Rule IDs named by `name`, which may be a single rule or a whole set.
+fn resolve(name: &str) -> Result<Vec<&'static str>, String> {
+    let ids: Vec<&'static str> = RULES
+        .iter()
+        .filter(|rule| rule.id == name || rule.set == name)
+        .map(|rule| rule.id)
+        .collect();
+    if ids.is_empty() {
+        Err(format!("Unknown content filter rule: {}", name))
+    } else {
+        Ok(ids)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Finding {
+    pub rule: String,
+    pub label: String,
+    /// Byte range of the match in the scanned text.
+    pub start: usize,
+    pub end: usize,
+    /// The match with everything but the last four characters masked.
+    pub preview: String,
+}
+
+/// Result of a pre-send scan. The frontend must ask the user before sending
+/// when `requires_confirmation` is set.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScanResult {
+    pub requires_confirmation: bool,
+    pub findings: Vec<Finding>,
+}
+
+fn mask(matched: &str) -> String {
+    let chars: Vec<char> = matched.chars().collect();
+    let keep = chars.len().saturating_sub(4);
+    chars
+        .iter()
+        .enumerate()
+        .map(|(i, &c)| {
+            if i < keep && !c.is_whitespace() {
+                '•'
+            } else {
+                c
+            }
+        })
+        .collect()
+}
+
+#[tauri::command]
+pub fn scan_outgoing_message(app: AppHandle, conversation: String, text: String) -> ScanResult {
+    let config = load_config(&app);
+    let allowed = config.allowlist.get(&conversation);
+
+    let mut findings = Vec::new();
+    for (rule, regex) in RULES.iter().zip(COMPILED.iter()) {
+        if config.disabled.contains(rule.id) || allowed.is_some_and(|a| a.contains(rule.id)) {
+            continue;
+        }
+        for m in regex.find_iter(&text) {
+            if rule.verify.is_some_and(|verify| !verify(m.as_str())) {
+                continue;
+            }
+            findings.push(Finding {
+                rule: rule.id.to_string(),
+                label: rule.label.to_string(),
+                start: m.start(),
+                end: m.end(),
+                preview: mask(m.as_str()),
+            });
+        }
+    }
+
+    if !findings.is_empty() {
+        log::debug!(
+            "Outgoing message to {} flagged by {} rule match(es)",
+            conversation,
+            findings.len()
+        );
+    }
+    ScanResult {
+        requires_confirmation: !findings.is_empty(),
+        findings,
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RuleInfo {
+    pub id: String,
+    pub label: String,
+    pub set: String,
+    pub enabled: bool,
+}
+
+#[tauri::command]
+pub fn get_content_filter_rules(app: AppHandle) -> Vec<RuleInfo> {
+    let config = load_config(&app);
+    RULES
+        .iter()
+        .map(|rule| RuleInfo {
+            id: rule.id.to_string(),
+            label: rule.label.to_string(),
+            set: rule.set.to_string(),
+            enabled: !config.disabled.contains(rule.id),
+        })
+        .collect()
+}
+
+/// Turn a rule, or a whole rule set such as "secrets" or "pii", on or off.
+#[tauri::command]
+pub fn set_content_filter_rule(app: AppHandle, rule: String, enabled: bool) -> Result<(), String> {
+    let ids = resolve(&rule)?;
+    let mut config = load_config(&app);
+    for id in ids {
+        if enabled {
+            config.disabled.remove(id);
+        } else {
+            config.disabled.insert(id.to_string());
+        }
+    }
+    prefs::save(&app, CONFIG_KEY, &config)
+}
+
+/// Stop flagging `rule` (or a rule set) in one conversation, e.g. a chat
+/// with yourself used for passing keys between machines.
+#[tauri::command]
+pub fn allow_content_in_conversation(
+    app: AppHandle,
+    conversation: String,
+    rule: String,
+    allowed: bool,
+) -> Result<(), String> {
+    let ids = resolve(&rule)?;
+    let mut config = load_config(&app);
+    let entry = config.allowlist.entry(conversation.clone()).or_default();
+    for id in ids {
+        if allowed {
+            entry.insert(id.to_string());
+        } else {
+            entry.remove(id);
+        }
+    }
+    if entry.is_empty() {
+        config.allowlist.remove(&conversation);
+    }
+    prefs::save(&app, CONFIG_KEY, &config)
+}
@@ -0,0 +1,115 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter};
+
+use crate::{clock, prefs};
+
+const FLAGS_KEY: &str = "conversation_flags";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Flag {
+    Pinned,
+    Archived,
+}
+
+/// Per-conversation list state that isn't part of the messages themselves.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ConversationFlags {
+    pub pinned: BTreeSet<String>,
+    pub archived: BTreeSet<String>,
+    pub muted: BTreeSet<String>,
+    /// Mutes that lift on their own: conversation → end, in milliseconds.
+    pub muted_until: BTreeMap<String, i64>,
+}
+
+impl ConversationFlags {
+    fn set_mut(&mut self, flag: Flag) -> &mut BTreeSet<String> {
+        match flag {
+            Flag::Pinned => &mut self.pinned,
+            Flag::Archived => &mut self.archived,
+        }
+    }
+}
+
+pub fn load(app: &AppHandle) -> ConversationFlags {
+    prefs::load(app, FLAGS_KEY).unwrap_or_default()
+}
+
+fn save(app: &AppHandle, flags: &ConversationFlags) -> Result<(), String> {
+    prefs::save(app, FLAGS_KEY, flags)?;
+    let _ = app.emit("conversation-flags-changed", flags);
+    Ok(())
+}
+
+/// Whether toasts, bubbles and unread badges are held back for
+/// `conversation` right now.
+pub fn is_muted(app: &AppHandle, conversation: &str) -> bool {
+    let flags = load(app);
+    flags.muted.contains(conversation)
+        || flags
+            .muted_until
+            .get(conversation)
+            .is_some_and(|&until| until > clock::now_millis() as i64)
+}
+
+/// Mute `conversation` until `until` (milliseconds), or until unmuted.
+pub fn mute(app: &AppHandle, conversation: &str, until: Option<i64>) -> Result<(), String> {
+    let mut flags = load(app);
+    let now = clock::now_millis() as i64;
+    flags.muted_until.retain(|_, &mut end| end > now);
+    match until {
+        Some(until) => {
+            flags.muted.remove(conversation);
+            flags.muted_until.insert(conversation.to_string(), until);
+        }
+        None => {
+            flags.muted_until.remove(conversation);
+            flags.muted.insert(conversation.to_string());
+        }
+    }
+    log::debug!("Muted {} until {:?}", conversation, until);
+    save(app, &flags)
+}
+
+pub fn unmute(app: &AppHandle, conversation: &str) -> Result<(), String> {
+    let mut flags = load(app);
+    let timed = flags.muted_until.remove(conversation).is_some();
+    if !flags.muted.remove(conversation) && !timed {
+        return Ok(());
+    }
+    save(app, &flags)
+}
+
+/// Turn `flag` on or off for `conversation` and tell the frontend.
+pub fn set(app: &AppHandle, conversation: &str, flag: Flag, on: bool) -> Result<(), String> {
+    let mut flags = load(app);
+    let set = flags.set_mut(flag);
+    let changed = if on {
+        set.insert(conversation.to_string())
+    } else {
+        set.remove(conversation)
+    };
+    if !changed {
+        return Ok(());
+    }
+    save(app, &flags)
+}
+
+#[tauri::command]
+pub fn get_conversation_flags(app: AppHandle) -> ConversationFlags {
+    load(&app)
+}
+
+/// Mute notifications from `user_id` until `until` (milliseconds), or
+/// indefinitely without it.
+#[tauri::command]
+pub fn mute_contact(app: AppHandle, user_id: String, until: Option<i64>) -> Result<(), String> {
+    mute(&app, &user_id, until)
+}
+
+#[tauri::command]
+pub fn unmute_contact(app: AppHandle, user_id: String) -> Result<(), String> {
+    unmute(&app, &user_id)
+}
@@ -0,0 +1,567 @@
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tauri::{AppHandle, Emitter, State};
+
+use crate::storage::Database;
+
+mod ratchet;
+pub mod trust;
+mod x3dh;
+
+use ratchet::{Header, Ratchet};
+use x3dh::{Identity, InitHeader, PrekeyBundle};
+
+type Key = [u8; 32];
+
+const IDENTITY_KEY: &str = "identity";
+
+/// Version byte of the envelope format.
+const ENVELOPE_VERSION: u8 = 1;
+
+/// Earlier init ephemerals remembered per contact, to refuse replays.
+const MAX_SPENT_EPHEMERALS: usize = 32;
+
+/// Base64 (URL-safe, unpadded) for keys and ciphertexts in JSON.
+mod b64 {
+    use super::URL_SAFE_NO_PAD;
+    use base64::Engine;
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer, T: AsRef<[u8]>>(bytes: &T, s: S) -> Result<S::Ok, S::Error> {
+        s.serialize_str(&URL_SAFE_NO_PAD.encode(bytes))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>, T: TryFrom<Vec<u8>>>(
+        d: D,
+    ) -> Result<T, D::Error> {
+        let text = String::deserialize(d)?;
+        let bytes = URL_SAFE_NO_PAD.decode(text).map_err(D::Error::custom)?;
+        T::try_from(bytes).map_err(|_| D::Error::custom("wrong key length"))
+    }
+
+    pub mod option {
+        use serde::{Deserialize, Deserializer, Serializer};
+
+        pub fn serialize<S: Serializer, T: AsRef<[u8]>>(
+            bytes: &Option<T>,
+            s: S,
+        ) -> Result<S::Ok, S::Error> {
+            match bytes {
+                Some(bytes) => super::serialize(bytes, s),
+                None => s.serialize_none(),
+            }
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>, T: TryFrom<Vec<u8>>>(
+            d: D,
+        ) -> Result<Option<T>, D::Error> {
+            #[derive(Deserialize)]
+            struct Wrapped<T: TryFrom<Vec<u8>>>(#[serde(with = "super")] T);
+            Ok(Option::<Wrapped<T>>::deserialize(d)?.map(|Wrapped(bytes)| bytes))
+        }
+    }
+
+    pub mod list {
+        use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+        pub fn serialize<S: Serializer, T: AsRef<[u8]>>(
+            items: &[T],
+            s: S,
+        ) -> Result<S::Ok, S::Error> {
+            #[derive(Serialize)]
+            struct Wrapped<'a, T: AsRef<[u8]>>(#[serde(with = "super")] &'a T);
+            s.collect_seq(items.iter().map(Wrapped))
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>, T: TryFrom<Vec<u8>>>(
+            d: D,
+        ) -> Result<Vec<T>, D::Error> {
+            #[derive(Deserialize)]
+            struct Wrapped<T: TryFrom<Vec<u8>>>(#[serde(with = "super")] T);
+            Ok(Vec::<Wrapped<T>>::deserialize(d)?
+                .into_iter()
+                .map(|Wrapped(bytes)| bytes)
+                .collect())
+        }
+    }
+}
+
+/// Per-contact session, stored next to the identity.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Session {
+    #[serde(with = "b64")]
+    their_identity: Key,
+    ratchet: Ratchet,
+    /// Repeated on our messages until the contact replies.
+    pending_init: Option<InitHeader>,
+    /// Ephemeral key of the init that created this session, if they started
+    /// it, so retransmitted init headers don't reset it.
+    #[serde(with = "b64::option")]
+    their_ephemeral: Option<Key>,
+    /// Ephemeral keys of the inits before it, so replaying an old one
+    /// can't roll the session back.
+    #[serde(with = "b64::list", default)]
+    spent_ephemerals: Vec<Key>,
+}
+
+/// Payload of `contact-identity-changed`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IdentityChanged {
+    pub contact: String,
+    /// Of the new key, to compare out of band before accepting it.
+    pub fingerprint: String,
+}
+
+#[derive(Debug)]
+enum DecryptError {
+    /// A new session from a key other than the one the current session is
+    /// bound to. Not taken until the user accepts it.
+    IdentityChanged(Key),
+    Failed(String),
+}
+
+impl From<String> for DecryptError {
+    fn from(e: String) -> Self {
+        Self::Failed(e)
+    }
+}
+
+impl From<&str> for DecryptError {
+    fn from(e: &str) -> Self {
+        Self::Failed(e.to_string())
+    }
+}
+
+/// What goes over the wire, base64-encoded JSON.
+#[derive(Serialize, Deserialize)]
+struct Envelope {
+    v: u8,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    init: Option<InitHeader>,
+    header: Header,
+    #[serde(with = "b64")]
+    ct: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IdentityInfo {
+    /// Compare out of band to verify a contact.
+    pub fingerprint: String,
+    pub bundle: PrekeyBundle,
+}
+
+// ── Key storage ─────────────────────────────────────────────────────────────
+
+pub fn init(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS crypto_keys (
+             name  TEXT PRIMARY KEY,
+             value TEXT NOT NULL
+         );",
+    )?;
+    trust::init(conn)
+}
+
+fn load<T: DeserializeOwned>(conn: &Connection, name: &str) -> Result<Option<T>, String> {
+    let value: Option<String> = conn
+        .query_row(
+            "SELECT value FROM crypto_keys WHERE name = ?1",
+            [name],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| e.to_string())?;
+    value
+        .map(|v| serde_json::from_str(&v).map_err(|e| e.to_string()))
+        .transpose()
+}
+
+fn save<T: Serialize>(conn: &Connection, name: &str, value: &T) -> Result<(), String> {
+    let json = serde_json::to_string(value).map_err(|e| e.to_string())?;
+    conn.execute(
+        "INSERT OR REPLACE INTO crypto_keys (name, value) VALUES (?1, ?2)",
+        params![name, json],
+    )
+    .map(|_| ())
+    .map_err(|e| e.to_string())
+}
+
+fn session_key(contact: &str) -> String {
+    format!("session:{}", contact)
+}
+
+fn identity(conn: &Connection) -> Result<Identity, String> {
+    load(conn, IDENTITY_KEY)?.ok_or_else(|| "No identity yet; generate one first".to_string())
+}
+
+/// The stored identity as-is, for moving it to another device.
+pub fn export_identity(conn: &Connection) -> Result<Option<serde_json::Value>, String> {
+    load(conn, IDENTITY_KEY)
+}
+
+/// Take over an identity exported on another device. Sessions belong to
+/// the identity they were made with, so they're dropped.
+pub fn import_identity(conn: &Connection, identity: serde_json::Value) -> Result<(), String> {
+    let identity: Identity = serde_json::from_value(identity).map_err(|e| e.to_string())?;
+    conn.execute("DELETE FROM crypto_keys WHERE name LIKE 'session:%'", [])
+        .map_err(|e| e.to_string())?;
+    save(conn, IDENTITY_KEY, &identity)
+}
+
+/// SHA-256 of an identity key in groups of four hex digits.
+fn fingerprint(identity_dh: &Key) -> String {
+    Sha256::digest(identity_dh)[..20]
+        .chunks(2)
+        .map(|pair| format!("{:02X}{:02X}", pair[0], pair[1]))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+// ── Commands ────────────────────────────────────────────────────────────────
+
+/// Create this install's identity, or return the existing one. `replace`
+/// makes a new identity and drops every session.
+#[tauri::command]
+pub fn generate_identity(
+    db: State<'_, Database>,
+    replace: Option<bool>,
+) -> Result<IdentityInfo, String> {
+    let conn = db.lock();
+    let identity = match load::<Identity>(&conn, IDENTITY_KEY)? {
+        Some(identity) if !replace.unwrap_or(false) => identity,
+        _ => {
+            let identity = Identity::generate();
+            conn.execute("DELETE FROM crypto_keys WHERE name LIKE 'session:%'", [])
+                .map_err(|e| e.to_string())?;
+            save(&conn, IDENTITY_KEY, &identity)?;
+            log::debug!("Generated a new identity");
+            identity
+        }
+    };
+    Ok(IdentityInfo {
+        fingerprint: fingerprint(&identity.identity_dh()),
+        bundle: identity.bundle(),
+    })
+}
+
+/// Our public prekey bundle, to hand to a contact.
+#[tauri::command]
+pub fn get_prekey_bundle(db: State<'_, Database>) -> Result<PrekeyBundle, String> {
+    Ok(identity(&db.lock())?.bundle())
+}
+
+fn drop_session(conn: &Connection, contact: &str) -> Result<(), String> {
+    conn.execute(
+        "DELETE FROM crypto_keys WHERE name = ?1",
+        [session_key(contact)],
+    )
+    .map(|_| ())
+    .map_err(|e| e.to_string())
+}
+
+fn start(conn: &Connection, contact: &str, bundle: &PrekeyBundle) -> Result<String, String> {
+    let (sk, ad, init) = identity(conn)?.initiate(bundle)?;
+    let session = Session {
+        their_identity: bundle.identity_dh,
+        ratchet: Ratchet::initiator(sk, bundle.signed_prekey, ad),
+        pending_init: Some(init),
+        their_ephemeral: None,
+        spent_ephemerals: Vec::new(),
+    };
+    save(conn, &session_key(contact), &session)?;
+    log::debug!("Started session with {}", contact);
+    Ok(fingerprint(&bundle.identity_dh))
+}
+
+fn encrypt(conn: &Connection, contact: &str, plaintext: &str) -> Result<String, String> {
+    let mut session: Session = load(conn, &session_key(contact))?
+        .ok_or_else(|| format!("No session with {}; exchange prekey bundles first", contact))?;
+    let (header, ct) = session.ratchet.encrypt(plaintext.as_bytes())?;
+    save(conn, &session_key(contact), &session)?;
+
+    let envelope = Envelope {
+        v: ENVELOPE_VERSION,
+        init: session.pending_init,
+        header,
+        ct,
+    };
+    let json = serde_json::to_vec(&envelope).map_err(|e| e.to_string())?;
+    Ok(URL_SAFE_NO_PAD.encode(json))
+}
+
+fn decrypt(conn: &Connection, contact: &str, ciphertext: &str) -> Result<String, DecryptError> {
+    let json = URL_SAFE_NO_PAD
+        .decode(ciphertext.trim())
+        .map_err(|_| "Not an encrypted message")?;
+    let envelope: Envelope =
+        serde_json::from_slice(&json).map_err(|_| "Not an encrypted message")?;
+    if envelope.v != ENVELOPE_VERSION {
+        return Err(format!("Unsupported message version {}", envelope.v).into());
+    }
+
+    let existing: Option<Session> = load(conn, &session_key(contact))?;
+    // A new or different init header means they started (or restarted) a
+    // session; otherwise it's a retransmission on one we already have.
+    let mut session = match (existing, envelope.init.as_ref()) {
+        (Some(session), Some(init)) if session.their_ephemeral == Some(init.ephemeral) => session,
+        (Some(session), None) => session,
+        (Some(session), Some(init)) if init.identity_dh != session.their_identity => {
+            return Err(DecryptError::IdentityChanged(init.identity_dh));
+        }
+        (Some(session), Some(init)) if session.spent_ephemerals.contains(&init.ephemeral) => {
+            return Err("Replayed session start".into());
+        }
+        (existing, Some(init)) => {
+            let mut spent_ephemerals: Vec<Key> = existing
+                .map(|s| {
+                    s.spent_ephemerals
+                        .into_iter()
+                        .chain(s.their_ephemeral)
+                        .collect()
+                })
+                .unwrap_or_default();
+            let excess = spent_ephemerals.len().saturating_sub(MAX_SPENT_EPHEMERALS);
+            spent_ephemerals.drain(..excess);
+
+            let (sk, ad, prekey_secret, prekey_public) = identity(conn)?.respond(init)?;
+            Session {
+                their_identity: init.identity_dh,
+                ratchet: Ratchet::responder(sk, prekey_secret, prekey_public, ad),
+                pending_init: None,
+                their_ephemeral: Some(init.ephemeral),
+                spent_ephemerals,
+            }
+        }
+        (None, None) => return Err(format!("No session with {}", contact).into()),
+    };
+
+    let plaintext = session.ratchet.decrypt(&envelope.header, &envelope.ct)?;
+    // A reply means they have our session; stop sending the init header.
+    session.pending_init = None;
+    save(conn, &session_key(contact), &session)?;
+
+    String::from_utf8(plaintext).map_err(|e| e.to_string().into())
+}
+
+/// Open a session with `contact` from their bundle. Returns their
+/// fingerprint for verification.
+#[tauri::command]
+pub fn start_session(
+    db: State<'_, Database>,
+    contact: String,
+    bundle: PrekeyBundle,
+) -> Result<String, String> {
+    start(&db.lock(), &contact, &bundle)
+}
+
+#[tauri::command]
+pub fn encrypt_message(
+    db: State<'_, Database>,
+    contact: String,
+    plaintext: String,
+) -> Result<String, String> {
+    encrypt(&db.lock(), &contact, &plaintext)
+}
+
+/// Decrypt a message from `contact`. One that starts a session with a new
+/// identity key is refused and emits `contact-identity-changed`; it can be
+/// decrypted again once the user accepts the key with
+/// `accept_identity_change`.
+#[tauri::command]
+pub fn decrypt_message(
+    app: AppHandle,
+    db: State<'_, Database>,
+    contact: String,
+    ciphertext: String,
+) -> Result<String, String> {
+    match decrypt(&db.lock(), &contact, &ciphertext) {
+        Ok(plaintext) => Ok(plaintext),
+        Err(DecryptError::IdentityChanged(identity_dh)) => {
+            log::warn!("{} started a session with a new identity key", contact);
+            let _ = app.emit(
+                "contact-identity-changed",
+                IdentityChanged {
+                    contact: contact.clone(),
+                    fingerprint: fingerprint(&identity_dh),
+                },
+            );
+            Err(format!(
+                "{}'s identity key changed; check their new fingerprint before accepting it",
+                contact
+            ))
+        }
+        Err(DecryptError::Failed(e)) => Err(e),
+    }
+}
+
+/// Drop the session with `contact` after their identity key changed, so
+/// their next message starts one with the new key.
+#[tauri::command]
+pub fn accept_identity_change(db: State<'_, Database>, contact: String) -> Result<(), String> {
+    drop_session(&db.lock(), &contact)?;
+    log::debug!("Accepted a new identity key for {}", contact);
+    Ok(())
+}
+
+/// Fingerprint of the identity a contact's session is bound to.
+#[tauri::command]
+pub fn get_contact_fingerprint(
+    db: State<'_, Database>,
+    contact: String,
+) -> Result<Option<String>, String> {
+    let session: Option<Session> = load(&db.lock(), &session_key(&contact))?;
+    Ok(session.map(|s| fingerprint(&s.their_identity)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A fresh install with its own identity.
+    fn device() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        init(&conn).unwrap();
+        save(&conn, IDENTITY_KEY, &Identity::generate()).unwrap();
+        conn
+    }
+
+    fn bundle(conn: &Connection) -> PrekeyBundle {
+        identity(conn).unwrap().bundle()
+    }
+
+    fn decrypted(conn: &Connection, contact: &str, ciphertext: &str) -> String {
+        decrypt(conn, contact, ciphertext).unwrap()
+    }
+
+    /// Change the envelope inside `ciphertext` with `f`.
+    fn tamper(ciphertext: &str, f: impl FnOnce(&mut serde_json::Value)) -> String {
+        let json = URL_SAFE_NO_PAD.decode(ciphertext).unwrap();
+        let mut envelope: serde_json::Value = serde_json::from_slice(&json).unwrap();
+        f(&mut envelope);
+        URL_SAFE_NO_PAD.encode(serde_json::to_vec(&envelope).unwrap())
+    }
+
+    /// Alice opens a session with Bob, who reads her first message.
+    fn paired() -> (Connection, Connection) {
+        let (alice, bob) = (device(), device());
+        start(&alice, "bob", &bundle(&bob)).unwrap();
+        let first = encrypt(&alice, "bob", "hi bob").unwrap();
+        assert_eq!(decrypted(&bob, "alice", &first), "hi bob");
+        (alice, bob)
+    }
+
+    #[test]
+    fn round_trip_both_ways() {
+        let (alice, bob) = paired();
+        for turn in 0..3 {
+            let reply = encrypt(&bob, "alice", &format!("reply {}", turn)).unwrap();
+            assert_eq!(decrypted(&alice, "bob", &reply), format!("reply {}", turn));
+            let message = encrypt(&alice, "bob", &format!("message {}", turn)).unwrap();
+            assert_eq!(
+                decrypted(&bob, "alice", &message),
+                format!("message {}", turn)
+            );
+        }
+    }
+
+    #[test]
+    fn init_header_stops_after_a_reply() {
+        let (alice, bob) = paired();
+        let reply = encrypt(&bob, "alice", "hello").unwrap();
+        decrypted(&alice, "bob", &reply);
+        let message = encrypt(&alice, "bob", "no init").unwrap();
+        let json = URL_SAFE_NO_PAD.decode(&message).unwrap();
+        let envelope: Envelope = serde_json::from_slice(&json).unwrap();
+        assert!(envelope.init.is_none());
+    }
+
+    #[test]
+    fn out_of_order_and_skipped_messages() {
+        let (alice, bob) = paired();
+        let sent: Vec<String> = (0..4)
+            .map(|i| encrypt(&alice, "bob", &format!("m{}", i)).unwrap())
+            .collect();
+        assert_eq!(decrypted(&bob, "alice", &sent[3]), "m3");
+        assert_eq!(decrypted(&bob, "alice", &sent[1]), "m1");
+
+        // Skipped keys outlive a ratchet step
+        let reply = encrypt(&bob, "alice", "reply").unwrap();
+        assert_eq!(decrypted(&alice, "bob", &reply), "reply");
+        let later = encrypt(&alice, "bob", "later").unwrap();
+        assert_eq!(decrypted(&bob, "alice", &later), "later");
+        assert_eq!(decrypted(&bob, "alice", &sent[0]), "m0");
+        assert_eq!(decrypted(&bob, "alice", &sent[2]), "m2");
+    }
+
+    #[test]
+    fn replayed_message_fails() {
+        let (alice, bob) = paired();
+        let message = encrypt(&alice, "bob", "once").unwrap();
+        assert_eq!(decrypted(&bob, "alice", &message), "once");
+        assert!(decrypt(&bob, "alice", &message).is_err());
+    }
+
+    #[test]
+    fn tampered_ciphertext_fails_and_keeps_the_session() {
+        let (alice, bob) = paired();
+        let message = encrypt(&alice, "bob", "intact").unwrap();
+        let tampered = tamper(&message, |envelope| {
+            let mut ct = URL_SAFE_NO_PAD
+                .decode(envelope["ct"].as_str().unwrap())
+                .unwrap();
+            ct[0] ^= 1;
+            envelope["ct"] = URL_SAFE_NO_PAD.encode(ct).into();
+        });
+        assert!(decrypt(&bob, "alice", &tampered).is_err());
+        assert_eq!(decrypted(&bob, "alice", &message), "intact");
+    }
+
+    #[test]
+    fn tampered_header_fails() {
+        let (alice, bob) = paired();
+        let message = encrypt(&alice, "bob", "intact").unwrap();
+        for field in ["n", "pn"] {
+            let tampered = tamper(&message, |envelope| {
+                let value = envelope["header"][field].as_u64().unwrap();
+                envelope["header"][field] = (value + 1).into();
+            });
+            assert!(decrypt(&bob, "alice", &tampered).is_err(), "{}", field);
+        }
+        assert_eq!(decrypted(&bob, "alice", &message), "intact");
+    }
+
+    #[test]
+    fn replayed_init_cannot_roll_the_session_back() {
+        let (alice, bob) = paired();
+        let old = encrypt(&alice, "bob", "old session").unwrap();
+        // Alice starts over, e.g. after losing her session
+        start(&alice, "bob", &bundle(&bob)).unwrap();
+        let new = encrypt(&alice, "bob", "new session").unwrap();
+        assert_eq!(decrypted(&bob, "alice", &new), "new session");
+
+        assert!(decrypt(&bob, "alice", &old).is_err());
+        let next = encrypt(&alice, "bob", "still new").unwrap();
+        assert_eq!(decrypted(&bob, "alice", &next), "still new");
+    }
+
+    #[test]
+    fn changed_identity_needs_accepting() {
+        let (_alice, bob) = paired();
+        let impostor = device();
+        start(&impostor, "bob", &bundle(&bob)).unwrap();
+        let message = encrypt(&impostor, "bob", "it's me, alice").unwrap();
+        let Err(DecryptError::IdentityChanged(key)) = decrypt(&bob, "alice", &message) else {
+            panic!("took a session from a new identity key");
+        };
+        assert_eq!(key, identity(&impostor).unwrap().identity_dh());
+
+        drop_session(&bob, "alice").unwrap();
+        assert_eq!(decrypted(&bob, "alice", &message), "it's me, alice");
+    }
+}
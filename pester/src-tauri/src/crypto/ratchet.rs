@@ -0,0 +1,317 @@
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{ChaCha20Poly1305, Key as AeadKey, Nonce};
+use hkdf::Hkdf;
+use hmac::{Hmac, Mac};
+use rand_core::OsRng;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use x25519_dalek::{PublicKey, StaticSecret};
+
+use super::{b64, Key};
+
+/// Most message keys derived ahead for one chain, so a forged header can't
+/// make us spin.
+const MAX_SKIP: u32 = 1000;
+
+/// Most skipped keys kept across chains; the oldest are forgotten first.
+const MAX_STORED_SKIPPED: usize = 2000;
+
+/// Sent in the clear next to every ciphertext and authenticated with it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Header {
+    #[serde(with = "b64")]
+    pub dh: Key,
+    /// Length of the previous sending chain.
+    pub pn: u32,
+    pub n: u32,
+}
+
+impl Header {
+    fn encode(&self) -> Vec<u8> {
+        let mut out = self.dh.to_vec();
+        out.extend_from_slice(&self.pn.to_be_bytes());
+        out.extend_from_slice(&self.n.to_be_bytes());
+        out
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct SkippedKey {
+    #[serde(with = "b64")]
+    dh: Key,
+    n: u32,
+    #[serde(with = "b64")]
+    mk: Key,
+}
+
+/// Double Ratchet state for one conversation, per the Signal spec.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Ratchet {
+    #[serde(with = "b64")]
+    dhs_secret: Key,
+    #[serde(with = "b64")]
+    dhs_public: Key,
+    #[serde(with = "b64::option")]
+    dhr: Option<Key>,
+    #[serde(with = "b64")]
+    rk: Key,
+    #[serde(with = "b64::option")]
+    cks: Option<Key>,
+    #[serde(with = "b64::option")]
+    ckr: Option<Key>,
+    ns: u32,
+    nr: u32,
+    pn: u32,
+    skipped: Vec<SkippedKey>,
+    /// X3DH associated data, mixed into every message.
+    #[serde(with = "b64")]
+    ad: Vec<u8>,
+}
+
+pub fn keypair() -> (Key, Key) {
+    let secret = StaticSecret::random_from_rng(OsRng);
+    let public = PublicKey::from(&secret);
+    (secret.to_bytes(), public.to_bytes())
+}
+
+pub fn dh(secret: &Key, public: &Key) -> Key {
+    StaticSecret::from(*secret)
+        .diffie_hellman(&PublicKey::from(*public))
+        .to_bytes()
+}
+
+fn kdf_rk(rk: &Key, dh_out: &Key) -> (Key, Key) {
+    let mut okm = [0u8; 64];
+    Hkdf::<Sha256>::new(Some(rk), dh_out)
+        .expand(b"Pester ratchet", &mut okm)
+        .expect("64 bytes is a valid HKDF length");
+    let (root, chain) = okm.split_at(32);
+    (root.try_into().unwrap(), chain.try_into().unwrap())
+}
+
+/// Next chain key and this step's message key.
+fn kdf_ck(ck: &Key) -> (Key, Key) {
+    let step = |byte: u8| -> Key {
+        let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(ck).expect("HMAC takes any key length");
+        mac.update(&[byte]);
+        mac.finalize().into_bytes().into()
+    };
+    (step(0x02), step(0x01))
+}
+
+fn cipher(mk: &Key) -> (ChaCha20Poly1305, Nonce) {
+    let mut okm = [0u8; 44];
+    Hkdf::<Sha256>::new(None, mk)
+        .expand(b"Pester message", &mut okm)
+        .expect("44 bytes is a valid HKDF length");
+    let (key, nonce) = okm.split_at(32);
+    (
+        ChaCha20Poly1305::new(AeadKey::from_slice(key)),
+        *Nonce::from_slice(nonce),
+    )
+}
+
+fn seal(mk: &Key, plaintext: &[u8], aad: &[u8]) -> Vec<u8> {
+    let (cipher, nonce) = cipher(mk);
+    cipher
+        .encrypt(
+            &nonce,
+            Payload {
+                msg: plaintext,
+                aad,
+            },
+        )
+        .expect("ChaCha20-Poly1305 encryption does not fail")
+}
+
+fn open(mk: &Key, ciphertext: &[u8], aad: &[u8]) -> Result<Vec<u8>, String> {
+    let (cipher, nonce) = cipher(mk);
+    cipher
+        .decrypt(
+            &nonce,
+            Payload {
+                msg: ciphertext,
+                aad,
+            },
+        )
+        .map_err(|_| "Message could not be decrypted".to_string())
+}
+
+impl Ratchet {
+    /// Alice: she has Bob's signed prekey from his bundle and can send first.
+    pub fn initiator(sk: Key, their_prekey: Key, ad: Vec<u8>) -> Self {
+        let (dhs_secret, dhs_public) = keypair();
+        let (rk, cks) = kdf_rk(&sk, &dh(&dhs_secret, &their_prekey));
+        Self {
+            dhs_secret,
+            dhs_public,
+            dhr: Some(their_prekey),
+            rk,
+            cks: Some(cks),
+            ckr: None,
+            ns: 0,
+            nr: 0,
+            pn: 0,
+            skipped: Vec::new(),
+            ad,
+        }
+    }
+
+    /// Bob: his signed prekey is the first ratchet key.
+    pub fn responder(sk: Key, prekey_secret: Key, prekey_public: Key, ad: Vec<u8>) -> Self {
+        Self {
+            dhs_secret: prekey_secret,
+            dhs_public: prekey_public,
+            dhr: None,
+            rk: sk,
+            cks: None,
+            ckr: None,
+            ns: 0,
+            nr: 0,
+            pn: 0,
+            skipped: Vec::new(),
+            ad,
+        }
+    }
+
+    fn aad(&self, header: &Header) -> Vec<u8> {
+        let mut aad = self.ad.clone();
+        aad.extend(header.encode());
+        aad
+    }
+
+    pub fn encrypt(&mut self, plaintext: &[u8]) -> Result<(Header, Vec<u8>), String> {
+        let ck = self
+            .cks
+            .ok_or("Waiting for the other side to reply before sending")?;
+        let (next, mk) = kdf_ck(&ck);
+        self.cks = Some(next);
+        let header = Header {
+            dh: self.dhs_public,
+            pn: self.pn,
+            n: self.ns,
+        };
+        self.ns += 1;
+        let ciphertext = seal(&mk, plaintext, &self.aad(&header));
+        Ok((header, ciphertext))
+    }
+
+    /// Decrypt a message, leaving the state untouched if it doesn't verify.
+    pub fn decrypt(&mut self, header: &Header, ciphertext: &[u8]) -> Result<Vec<u8>, String> {
+        let mut next = self.clone();
+        let plaintext = next.decrypt_in_place(header, ciphertext)?;
+        *self = next;
+        Ok(plaintext)
+    }
+
+    fn decrypt_in_place(&mut self, header: &Header, ciphertext: &[u8]) -> Result<Vec<u8>, String> {
+        let aad = self.aad(header);
+
+        if let Some(i) = self
+            .skipped
+            .iter()
+            .position(|k| k.dh == header.dh && k.n == header.n)
+        {
+            let key = self.skipped.remove(i);
+            return open(&key.mk, ciphertext, &aad);
+        }
+
+        if self.dhr != Some(header.dh) {
+            self.skip(header.pn)?;
+            self.dh_step(header.dh);
+        }
+        self.skip(header.n)?;
+
+        let ck = self.ckr.ok_or("Message could not be decrypted")?;
+        let (next, mk) = kdf_ck(&ck);
+        self.ckr = Some(next);
+        self.nr += 1;
+        open(&mk, ciphertext, &aad)
+    }
+
+    fn skip(&mut self, until: u32) -> Result<(), String> {
+        let (Some(mut ck), Some(dhr)) = (self.ckr, self.dhr) else {
+            return Ok(());
+        };
+        if until > self.nr + MAX_SKIP {
+            return Err("Too many skipped messages".to_string());
+        }
+        while self.nr < until {
+            let (next, mk) = kdf_ck(&ck);
+            self.skipped.push(SkippedKey {
+                dh: dhr,
+                n: self.nr,
+                mk,
+            });
+            ck = next;
+            self.nr += 1;
+        }
+        self.ckr = Some(ck);
+        let excess = self.skipped.len().saturating_sub(MAX_STORED_SKIPPED);
+        self.skipped.drain(..excess);
+        Ok(())
+    }
+
+    fn dh_step(&mut self, their_dh: Key) {
+        self.pn = self.ns;
+        self.ns = 0;
+        self.nr = 0;
+        self.dhr = Some(their_dh);
+        let (rk, ckr) = kdf_rk(&self.rk, &dh(&self.dhs_secret, &their_dh));
+        self.ckr = Some(ckr);
+        let (dhs_secret, dhs_public) = keypair();
+        self.dhs_secret = dhs_secret;
+        self.dhs_public = dhs_public;
+        let (rk, cks) = kdf_rk(&rk, &dh(&self.dhs_secret, &their_dh));
+        self.rk = rk;
+        self.cks = Some(cks);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pair(alice_ad: &[u8], bob_ad: &[u8]) -> (Ratchet, Ratchet) {
+        let sk = [7; 32];
+        let (prekey_secret, prekey_public) = keypair();
+        (
+            Ratchet::initiator(sk, prekey_public, alice_ad.to_vec()),
+            Ratchet::responder(sk, prekey_secret, prekey_public, bob_ad.to_vec()),
+        )
+    }
+
+    #[test]
+    fn responder_waits_for_the_first_message() {
+        let (mut alice, mut bob) = pair(b"ad", b"ad");
+        assert!(bob.encrypt(b"too early").is_err());
+        let (header, ct) = alice.encrypt(b"first").unwrap();
+        assert_eq!(bob.decrypt(&header, &ct).unwrap(), b"first");
+        assert!(bob.encrypt(b"now").is_ok());
+    }
+
+    #[test]
+    fn associated_data_must_match() {
+        let (mut alice, mut bob) = pair(b"alice+bob", b"alice+eve");
+        let (header, ct) = alice.encrypt(b"first").unwrap();
+        assert!(bob.decrypt(&header, &ct).is_err());
+    }
+
+    #[test]
+    fn too_many_skipped_keys_are_refused() {
+        let (mut alice, mut bob) = pair(b"ad", b"ad");
+        let (header, ct) = alice.encrypt(b"first").unwrap();
+        bob.decrypt(&header, &ct).unwrap();
+
+        let (header, ct) = alice.encrypt(b"second").unwrap();
+        let forged = Header {
+            n: MAX_SKIP + 10,
+            ..header.clone()
+        };
+        assert_eq!(
+            bob.decrypt(&forged, &ct),
+            Err("Too many skipped messages".to_string())
+        );
+        assert_eq!(bob.decrypt(&header, &ct).unwrap(), b"second");
+    }
+}
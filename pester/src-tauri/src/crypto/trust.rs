@@ -0,0 +1,236 @@
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use ed25519_dalek::{Signature, Verifier};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use super::x3dh::PrekeyBundle;
+use super::{b64, identity, load, save, session_key, Key, Session};
+use crate::storage::Database;
+
+/// A signed statement that the signer checked `contact`'s fingerprint out
+/// of band.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Attestation {
+    pub contact: String,
+    #[serde(with = "b64")]
+    pub identity_dh: Key,
+    #[serde(with = "b64")]
+    pub signature: Vec<u8>,
+}
+
+/// Everything we vouch for, for the webview to pass to contacts, e.g. in
+/// an encrypted message; nothing sends it on its own. The bundle ties the
+/// signing key to our identity.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Vouchers {
+    pub bundle: PrekeyBundle,
+    pub attestations: Vec<Attestation>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrustPath {
+    /// We checked their fingerprint ourselves.
+    pub verified: bool,
+    /// Contacts we verified who vouch for their current key.
+    pub vouched_by: Vec<String>,
+}
+
+/// An identity key, as stored for verified contacts.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TrustedKey {
+    #[serde(with = "b64")]
+    identity_dh: Key,
+}
+
+fn verified_key(contact: &str) -> String {
+    format!("verified:{}", contact)
+}
+
+pub fn init(conn: &Connection) -> rusqlite::Result<()> {
+    // Vouches used to be `vouch:<contact>:<voucher>` rows in crypto_keys,
+    // which a `:` in either ID made ambiguous. They're sent again with
+    // the next import.
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS trust_vouches (
+             contact     TEXT NOT NULL,
+             voucher     TEXT NOT NULL,
+             identity_dh TEXT NOT NULL,
+             PRIMARY KEY (contact, voucher)
+         );
+         DELETE FROM crypto_keys WHERE name LIKE 'vouch:%';",
+    )
+}
+
+fn attested_data(contact: &str, identity_dh: &Key) -> Vec<u8> {
+    [
+        b"Pester attestation\0".as_slice(),
+        contact.as_bytes(),
+        b"\0",
+        identity_dh,
+    ]
+    .concat()
+}
+
+fn session_identity(conn: &Connection, contact: &str) -> Result<Key, String> {
+    let session: Session =
+        load(conn, &session_key(contact))?.ok_or_else(|| format!("No session with {}", contact))?;
+    Ok(session.their_identity)
+}
+
+/// Whether `contact`'s current session key is the one we verified.
+fn is_verified(conn: &Connection, contact: &str) -> Result<bool, String> {
+    let Some(verified) = load::<TrustedKey>(conn, &verified_key(contact))? else {
+        return Ok(false);
+    };
+    Ok(session_identity(conn, contact).ok() == Some(verified.identity_dh))
+}
+
+// ── Commands ────────────────────────────────────────────────────────────────
+
+/// Record that the user compared `contact`'s fingerprint out of band. Tied
+/// to their current key, so a new key needs verifying again.
+#[tauri::command]
+pub fn verify_contact(db: State<'_, Database>, contact: String) -> Result<(), String> {
+    let conn = db.lock();
+    let identity_dh = session_identity(&conn, &contact)?;
+    save(&conn, &verified_key(&contact), &TrustedKey { identity_dh })?;
+    log::debug!("Verified {}", contact);
+    Ok(())
+}
+
+#[tauri::command]
+pub fn unverify_contact(db: State<'_, Database>, contact: String) -> Result<(), String> {
+    db.with(|conn| {
+        conn.execute(
+            "DELETE FROM crypto_keys WHERE name = ?1",
+            [verified_key(&contact)],
+        )
+    })?;
+    Ok(())
+}
+
+/// Sign an attestation for every contact we verified, to send to others.
+#[tauri::command]
+pub fn get_attestations(db: State<'_, Database>) -> Result<Vouchers, String> {
+    let conn = db.lock();
+    let identity = identity(&conn)?;
+    let contacts: Vec<String> = {
+        let mut stmt = conn
+            .prepare(
+                "SELECT substr(name, 10) FROM crypto_keys
+                 WHERE substr(name, 1, 9) = 'verified:'",
+            )
+            .map_err(|e| e.to_string())?;
+        let contacts = stmt
+            .query_map([], |row| row.get(0))
+            .and_then(|rows| rows.collect())
+            .map_err(|e| e.to_string())?;
+        contacts
+    };
+
+    let mut attestations = Vec::new();
+    for contact in contacts {
+        if !is_verified(&conn, &contact)? {
+            continue;
+        }
+        let identity_dh = session_identity(&conn, &contact)?;
+        attestations.push(Attestation {
+            signature: identity.sign(&attested_data(&contact, &identity_dh)),
+            contact,
+            identity_dh,
+        });
+    }
+    Ok(Vouchers {
+        bundle: identity.bundle(),
+        attestations,
+    })
+}
+
+/// Take in what `from` vouches for. Only counts if we verified `from`, and
+/// replaces whatever they vouched for before. Returns how many were kept.
+#[tauri::command]
+pub fn import_attestations(
+    db: State<'_, Database>,
+    from: String,
+    vouchers: Vouchers,
+) -> Result<u32, String> {
+    let conn = db.lock();
+    if !is_verified(&conn, &from)? {
+        return Err(format!("{} isn't verified", from));
+    }
+    if session_identity(&conn, &from)? != vouchers.bundle.identity_dh {
+        return Err(format!("Attestations don't come from {}'s key", from));
+    }
+    let signing_key = vouchers.bundle.signing_key()?;
+
+    let tx = conn.unchecked_transaction().map_err(|e| e.to_string())?;
+    tx.execute("DELETE FROM trust_vouches WHERE voucher = ?1", [&from])
+        .map_err(|e| e.to_string())?;
+    let mut kept = 0;
+    for attestation in &vouchers.attestations {
+        let valid = Signature::from_slice(&attestation.signature).is_ok_and(|signature| {
+            signing_key
+                .verify(
+                    &attested_data(&attestation.contact, &attestation.identity_dh),
+                    &signature,
+                )
+                .is_ok()
+        });
+        if !valid {
+            log::warn!("Dropping a bad attestation from {}", from);
+            continue;
+        }
+        tx.execute(
+            "INSERT OR REPLACE INTO trust_vouches (contact, voucher, identity_dh)
+             VALUES (?1, ?2, ?3)",
+            params![
+                attestation.contact,
+                from,
+                URL_SAFE_NO_PAD.encode(attestation.identity_dh)
+            ],
+        )
+        .map_err(|e| e.to_string())?;
+        kept += 1;
+    }
+    tx.commit().map_err(|e| e.to_string())?;
+    log::debug!("Imported {} attestations from {}", kept, from);
+    Ok(kept)
+}
+
+/// Whether `contact`'s current key is verified, by us or by contacts we
+/// verified.
+#[tauri::command]
+pub fn get_trust_path(db: State<'_, Database>, contact: String) -> Result<TrustPath, String> {
+    let conn = db.lock();
+    let identity_dh = session_identity(&conn, &contact)?;
+    let vouches: Vec<(String, String)> = {
+        let mut stmt = conn
+            .prepare("SELECT voucher, identity_dh FROM trust_vouches WHERE contact = ?1")
+            .map_err(|e| e.to_string())?;
+        let vouches = stmt
+            .query_map([&contact], |row| Ok((row.get(0)?, row.get(1)?)))
+            .and_then(|rows| rows.collect())
+            .map_err(|e| e.to_string())?;
+        vouches
+    };
+
+    let current = URL_SAFE_NO_PAD.encode(identity_dh);
+    let mut vouched_by = Vec::new();
+    for (voucher, vouched) in vouches {
+        if vouched == current && is_verified(&conn, &voucher)? {
+            vouched_by.push(voucher);
+        }
+    }
+    vouched_by.sort();
+
+    Ok(TrustPath {
+        verified: is_verified(&conn, &contact)?,
+        vouched_by,
+    })
+}
@@ -0,0 +1,206 @@
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use hkdf::Hkdf;
+use rand_core::OsRng;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+
+use super::ratchet::{dh, keypair};
+use super::{b64, Key};
+
+/// Long-term keys for this install. Never serialized outside the database.
+#[derive(Serialize, Deserialize)]
+pub struct Identity {
+    /// Ed25519 seed; signs the prekey and attestations.
+    #[serde(with = "b64")]
+    sign_secret: Key,
+    #[serde(with = "b64")]
+    dh_secret: Key,
+    #[serde(with = "b64")]
+    prekey_secret: Key,
+    #[serde(with = "b64")]
+    prekey_signature: Vec<u8>,
+}
+
+/// What a contact needs to open a session with us. Public keys only.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PrekeyBundle {
+    #[serde(with = "b64")]
+    pub identity_sign: Key,
+    #[serde(with = "b64")]
+    pub identity_dh: Key,
+    #[serde(with = "b64")]
+    pub signed_prekey: Key,
+    /// Ed25519 signature over `identity_dh || signed_prekey`.
+    #[serde(with = "b64")]
+    pub signature: Vec<u8>,
+}
+
+/// Sent with the initiator's messages until the first reply, so the
+/// responder can derive the same shared secret.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InitHeader {
+    #[serde(with = "b64")]
+    pub identity_dh: Key,
+    #[serde(with = "b64")]
+    pub ephemeral: Key,
+    /// Which of our prekeys was used, so a rotated one is noticed.
+    #[serde(with = "b64")]
+    pub prekey: Key,
+}
+
+impl PrekeyBundle {
+    /// The signing key, once the prekey signature shows it belongs to
+    /// `identity_dh`.
+    pub fn signing_key(&self) -> Result<VerifyingKey, String> {
+        let verifying = VerifyingKey::from_bytes(&self.identity_sign).map_err(|e| e.to_string())?;
+        let signature =
+            Signature::from_slice(&self.signature).map_err(|_| "Malformed prekey signature")?;
+        verifying
+            .verify(
+                &signed_data(&self.identity_dh, &self.signed_prekey),
+                &signature,
+            )
+            .map_err(|_| "Prekey signature does not verify")?;
+        Ok(verifying)
+    }
+}
+
+fn public(secret: &Key) -> Key {
+    x25519_dalek::PublicKey::from(&x25519_dalek::StaticSecret::from(*secret)).to_bytes()
+}
+
+fn signed_data(identity_dh: &Key, prekey: &Key) -> Vec<u8> {
+    [identity_dh.as_slice(), prekey.as_slice()].concat()
+}
+
+/// SK = HKDF(0xFF×32 ‖ DH1 ‖ DH2 ‖ DH3), as in the X3DH spec.
+fn shared_secret(dh1: Key, dh2: Key, dh3: Key) -> Key {
+    let ikm = [[0xFF; 32], dh1, dh2, dh3].concat();
+    let mut sk = [0u8; 32];
+    Hkdf::<Sha256>::new(Some(&[0u8; 32]), &ikm)
+        .expand(b"Pester X3DH", &mut sk)
+        .expect("32 bytes is a valid HKDF length");
+    sk
+}
+
+/// Associated data binding both identities: initiator first.
+fn associated_data(initiator: &Key, responder: &Key) -> Vec<u8> {
+    [initiator.as_slice(), responder.as_slice()].concat()
+}
+
+impl Identity {
+    pub fn generate() -> Self {
+        let sign = SigningKey::generate(&mut OsRng);
+        let (dh_secret, dh_public) = keypair();
+        let (prekey_secret, prekey_public) = keypair();
+        let prekey_signature = sign
+            .sign(&signed_data(&dh_public, &prekey_public))
+            .to_bytes()
+            .to_vec();
+        Self {
+            sign_secret: sign.to_bytes(),
+            dh_secret,
+            prekey_secret,
+            prekey_signature,
+        }
+    }
+
+    pub fn sign(&self, data: &[u8]) -> Vec<u8> {
+        SigningKey::from_bytes(&self.sign_secret)
+            .sign(data)
+            .to_bytes()
+            .to_vec()
+    }
+
+    pub fn identity_dh(&self) -> Key {
+        public(&self.dh_secret)
+    }
+
+    pub fn bundle(&self) -> PrekeyBundle {
+        PrekeyBundle {
+            identity_sign: SigningKey::from_bytes(&self.sign_secret)
+                .verifying_key()
+                .to_bytes(),
+            identity_dh: self.identity_dh(),
+            signed_prekey: public(&self.prekey_secret),
+            signature: self.prekey_signature.clone(),
+        }
+    }
+
+    /// Start a session from a contact's bundle. Returns the shared secret,
+    /// associated data and the header the responder needs.
+    pub fn initiate(&self, bundle: &PrekeyBundle) -> Result<(Key, Vec<u8>, InitHeader), String> {
+        bundle.signing_key()?;
+
+        let (ephemeral_secret, ephemeral) = keypair();
+        let sk = shared_secret(
+            dh(&self.dh_secret, &bundle.signed_prekey),
+            dh(&ephemeral_secret, &bundle.identity_dh),
+            dh(&ephemeral_secret, &bundle.signed_prekey),
+        );
+        let ad = associated_data(&self.identity_dh(), &bundle.identity_dh);
+        let init = InitHeader {
+            identity_dh: self.identity_dh(),
+            ephemeral,
+            prekey: bundle.signed_prekey,
+        };
+        Ok((sk, ad, init))
+    }
+
+    /// The responder's side of `initiate`. Returns the shared secret,
+    /// associated data and our prekey pair, which seeds the ratchet.
+    pub fn respond(&self, init: &InitHeader) -> Result<(Key, Vec<u8>, Key, Key), String> {
+        let prekey_public = public(&self.prekey_secret);
+        if init.prekey != prekey_public {
+            return Err("Session was started with a prekey we no longer have".to_string());
+        }
+        let sk = shared_secret(
+            dh(&self.prekey_secret, &init.identity_dh),
+            dh(&self.dh_secret, &init.ephemeral),
+            dh(&self.prekey_secret, &init.ephemeral),
+        );
+        let ad = associated_data(&init.identity_dh, &self.identity_dh());
+        Ok((sk, ad, self.prekey_secret, prekey_public))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn both_sides_derive_the_same_secret() {
+        let (alice, bob) = (Identity::generate(), Identity::generate());
+        let (sk, ad, init) = alice.initiate(&bob.bundle()).unwrap();
+        let (bob_sk, bob_ad, _, prekey) = bob.respond(&init).unwrap();
+        assert_eq!(sk, bob_sk);
+        assert_eq!(ad, bob_ad);
+        assert_eq!(prekey, bob.bundle().signed_prekey);
+    }
+
+    #[test]
+    fn forged_bundle_is_refused() {
+        let (alice, bob, mallory) = (
+            Identity::generate(),
+            Identity::generate(),
+            Identity::generate(),
+        );
+        let mut swapped = bob.bundle();
+        swapped.signed_prekey = mallory.bundle().signed_prekey;
+        assert!(alice.initiate(&swapped).is_err());
+
+        let mut bad_signature = bob.bundle();
+        bad_signature.signature[0] ^= 1;
+        assert!(alice.initiate(&bad_signature).is_err());
+    }
+
+    #[test]
+    fn unknown_prekey_is_refused() {
+        let (alice, bob) = (Identity::generate(), Identity::generate());
+        let (_, _, mut init) = alice.initiate(&bob.bundle()).unwrap();
+        init.prekey = Identity::generate().bundle().signed_prekey;
+        assert!(bob.respond(&init).is_err());
+    }
+}
@@ -0,0 +1,145 @@
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use tauri::{AppHandle, Emitter, Manager, State, Url};
+use tauri_plugin_deep_link::DeepLinkExt;
+
+const SCHEME: &str = "pester";
+
+/// The last deep link that hasn't been picked up by the frontend yet, in the
+/// same `chat:<id>` form as tray actions. Links can arrive before the webview
+/// is listening (cold start, macOS `Opened`), so the frontend pulls it
+/// instead of relying on a one-shot event.
+#[derive(Default)]
+pub struct PendingDeepLink(Mutex<Option<String>>);
+
+fn check_contact(contact: &str) -> Result<(), String> {
+    let valid = !contact.is_empty()
+        && contact.len() <= 64
+        && contact
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if valid {
+        Ok(())
+    } else {
+        Err(format!("Invalid contact id: {}", contact))
+    }
+}
+
+fn chat_url(contact: &str) -> String {
+    format!("{}://chat/{}", SCHEME, contact)
+}
+
+/// `pester://chat/<contact>` → `chat:<contact>`.
+fn to_action(url: &Url) -> Option<String> {
+    if url.scheme() != SCHEME || url.host_str() != Some("chat") {
+        return None;
+    }
+    let contact = url.path().trim_matches('/');
+    check_contact(contact).ok()?;
+    Some(format!("chat:{}", contact))
+}
+
+fn route(app: &AppHandle, urls: Vec<Url>) {
+    let Some(action) = urls.iter().find_map(to_action) else {
+        log::warn!("Ignoring unrecognised deep link: {:?}", urls);
+        return;
+    };
+    log::debug!("Deep link → {}", action);
+    *app.state::<PendingDeepLink>().0.lock().unwrap() = Some(action);
+    crate::show_main_window(app);
+    let _ = app.emit("deep-link", ());
+}
+
+/// Hook up deep link handling. Called once from `setup`.
+pub fn init(app: &AppHandle) -> Result<(), Box<dyn std::error::Error>> {
+    app.manage(PendingDeepLink::default());
+
+    // Installers register the scheme; AppImages and dev builds don't.
+    #[cfg(any(target_os = "linux", all(debug_assertions, windows)))]
+    app.deep_link().register_all()?;
+
+    if let Some(urls) = app.deep_link().get_current()? {
+        route(app, urls);
+    }
+
+    let handle = app.clone();
+    app.deep_link().on_open_url(move |event| {
+        route(&handle, event.urls());
+    });
+
+    Ok(())
+}
+
+#[tauri::command]
+pub fn take_pending_deep_link(pending: State<'_, PendingDeepLink>) -> Option<String> {
+    pending.0.lock().unwrap().take()
+}
+
+// ── Desktop shortcuts ───────────────────────────────────────────────────────
+
+#[cfg(target_os = "windows")]
+fn write_shortcut(dir: &Path, contact: &str) -> Result<PathBuf, String> {
+    let exe = std::env::current_exe().map_err(|e| e.to_string())?;
+    let path = dir.join(format!("Pester - {}.url", contact));
+    let contents = format!(
+        "[InternetShortcut]\r\nURL={}\r\nIconFile={}\r\nIconIndex=0\r\n",
+        chat_url(contact),
+        exe.display()
+    );
+    std::fs::write(&path, contents).map_err(|e| e.to_string())?;
+    Ok(path)
+}
+
+#[cfg(target_os = "macos")]
+fn write_shortcut(dir: &Path, contact: &str) -> Result<PathBuf, String> {
+    let path = dir.join(format!("Pester - {}.webloc", contact));
+    let contents = format!(
+        concat!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+            "<!DOCTYPE plist PUBLIC \"-//Apple//DTD PLIST 1.0//EN\" ",
+            "\"http://www.apple.com/DTDs/PropertyList-1.0.dtd\">\n",
+            "<plist version=\"1.0\">\n<dict>\n",
+            "\t<key>URL</key>\n\t<string>{}</string>\n",
+            "</dict>\n</plist>\n"
+        ),
+        chat_url(contact)
+    );
+    std::fs::write(&path, contents).map_err(|e| e.to_string())?;
+    Ok(path)
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+fn write_shortcut(dir: &Path, contact: &str) -> Result<PathBuf, String> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let path = dir.join(format!("pester-{}.desktop", contact));
+    let contents = format!(
+        concat!(
+            "[Desktop Entry]\n",
+            "Type=Application\n",
+            "Name=Pester - {}\n",
+            "Exec=xdg-open {}\n",
+            "Icon=pester\n",
+            "Terminal=false\n"
+        ),
+        contact,
+        chat_url(contact)
+    );
+    std::fs::write(&path, contents).map_err(|e| e.to_string())?;
+    // Desktop environments only launch entries marked executable.
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755))
+        .map_err(|e| e.to_string())?;
+    Ok(path)
+}
+
+/// Put a shortcut on the desktop that opens Pester straight into the chat
+/// with `contact`. Returns the path of the created file.
+#[tauri::command]
+pub fn create_chat_shortcut(app: AppHandle, contact: String) -> Result<String, String> {
+    check_contact(&contact)?;
+    let desktop = app.path().desktop_dir().map_err(|e| e.to_string())?;
+    let path = write_shortcut(&desktop, &contact)?;
+    log::debug!("Created chat shortcut at {}", path.display());
+    Ok(path.to_string_lossy().into_owned())
+}
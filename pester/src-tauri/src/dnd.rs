@@ -0,0 +1,214 @@
+use chrono::{DateTime, Datelike, Local, NaiveDate, NaiveTime, TimeZone, Weekday};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter};
+
+use crate::prefs;
+
+const SCHEDULE_KEY: &str = "dnd_schedule";
+const SNOOZE_KEY: &str = "dnd_snoozed_until";
+/// Longest snooze, a day.
+const MAX_SNOOZE_MINUTES: u32 = 24 * 60;
+
+/// Daily quiet hours. A period that ends before it starts runs past
+/// midnight; one that ends when it starts lasts the whole day.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct DndSchedule {
+    pub enabled: bool,
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+    /// Days quiet hours start on, every day when empty.
+    pub days: Vec<Weekday>,
+}
+
+impl Default for DndSchedule {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            start: NaiveTime::from_hms_opt(22, 0, 0).unwrap(),
+            end: NaiveTime::from_hms_opt(7, 0, 0).unwrap(),
+            days: Vec::new(),
+        }
+    }
+}
+
+impl DndSchedule {
+    fn applies_on(&self, day: NaiveDate) -> bool {
+        self.days.is_empty() || self.days.contains(&day.weekday())
+    }
+
+    /// When the quiet period covering `now` ends, if one does.
+    fn current_end(&self, now: DateTime<Local>) -> Option<DateTime<Local>> {
+        if !self.enabled {
+            return None;
+        }
+        let (today, time) = (now.date_naive(), now.time());
+        let yesterday = today.pred_opt()?;
+        let tomorrow = today.succ_opt()?;
+
+        let end_day = if self.start == self.end {
+            // All day: ends when the next day starts
+            self.applies_on(today).then_some(tomorrow)?
+        } else if self.start < self.end {
+            (self.applies_on(today) && self.start <= time && time < self.end).then_some(today)?
+        } else if time >= self.start && self.applies_on(today) {
+            tomorrow
+        } else if time < self.end && self.applies_on(yesterday) {
+            today
+        } else {
+            return None;
+        };
+
+        let end = if self.start == self.end {
+            NaiveTime::MIN
+        } else {
+            self.end
+        };
+        Local.from_local_datetime(&end_day.and_time(end)).earliest()
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DndReason {
+    Schedule,
+    Snooze,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DndState {
+    pub active: bool,
+    pub reason: Option<DndReason>,
+    /// When notifications resume, in milliseconds, while active.
+    pub until: Option<i64>,
+    pub schedule: DndSchedule,
+    pub snoozed_until: Option<i64>,
+}
+
+fn schedule(app: &AppHandle) -> DndSchedule {
+    prefs::load(app, SCHEDULE_KEY).unwrap_or_default()
+}
+
+/// Work out Do Not Disturb right now. A snooze wins over the schedule.
+pub fn state(app: &AppHandle) -> DndState {
+    let now = Local::now();
+    let schedule = schedule(app);
+    let snoozed_until =
+        prefs::load::<_, i64>(app, SNOOZE_KEY).filter(|&until| until > now.timestamp_millis());
+
+    let (reason, until) = match (snoozed_until, schedule.current_end(now)) {
+        (Some(until), _) => (Some(DndReason::Snooze), Some(until)),
+        (None, Some(end)) => (Some(DndReason::Schedule), Some(end.timestamp_millis())),
+        (None, None) => (None, None),
+    };
+
+    DndState {
+        active: reason.is_some(),
+        reason,
+        until,
+        schedule,
+        snoozed_until,
+    }
+}
+
+/// Whether toasts should be held back. Checked by the notification
+/// manager, so it applies whether or not the webview is awake.
+pub fn is_active(app: &AppHandle) -> bool {
+    state(app).active
+}
+
+fn changed(app: &AppHandle) -> DndState {
+    let state = state(app);
+    log::debug!("Do Not Disturb is now {:?}", state.reason);
+    let _ = app.emit("dnd-changed", &state);
+    state
+}
+
+#[tauri::command]
+pub fn get_dnd_state(app: AppHandle) -> DndState {
+    state(&app)
+}
+
+#[tauri::command]
+pub fn set_dnd_schedule(app: AppHandle, schedule: DndSchedule) -> Result<DndState, String> {
+    prefs::save(&app, SCHEDULE_KEY, &schedule)?;
+    Ok(changed(&app))
+}
+
+/// Hold notifications back for `minutes`, up to a day; zero ends a snooze.
+#[tauri::command]
+pub fn snooze_notifications(app: AppHandle, minutes: u32) -> Result<DndState, String> {
+    let until = (minutes > 0).then(|| {
+        Local::now().timestamp_millis() + minutes.min(MAX_SNOOZE_MINUTES) as i64 * 60 * 1000
+    });
+    prefs::save(&app, SNOOZE_KEY, &until)?;
+    Ok(changed(&app))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn time(hour: u32) -> NaiveTime {
+        NaiveTime::from_hms_opt(hour, 0, 0).unwrap()
+    }
+
+    /// `hour` o'clock on `day` January 2026; the 5th is a Monday.
+    fn at(day: u32, hour: u32) -> DateTime<Local> {
+        Local.with_ymd_and_hms(2026, 1, day, hour, 0, 0).unwrap()
+    }
+
+    fn schedule(start: u32, end: u32, days: &[Weekday]) -> DndSchedule {
+        DndSchedule {
+            enabled: true,
+            start: time(start),
+            end: time(end),
+            days: days.to_vec(),
+        }
+    }
+
+    #[test]
+    fn disabled_schedule_is_never_quiet() {
+        let schedule = DndSchedule {
+            enabled: false,
+            ..schedule(9, 17, &[])
+        };
+        assert_eq!(schedule.current_end(at(5, 12)), None);
+    }
+
+    #[test]
+    fn daytime_period_ends_the_same_day() {
+        let schedule = schedule(9, 17, &[]);
+        assert_eq!(schedule.current_end(at(5, 9)), Some(at(5, 17)));
+        assert_eq!(schedule.current_end(at(5, 8)), None);
+        assert_eq!(schedule.current_end(at(5, 17)), None);
+    }
+
+    #[test]
+    fn overnight_period_runs_past_midnight() {
+        let schedule = schedule(22, 7, &[]);
+        assert_eq!(schedule.current_end(at(5, 23)), Some(at(6, 7)));
+        assert_eq!(schedule.current_end(at(6, 3)), Some(at(6, 7)));
+        assert_eq!(schedule.current_end(at(6, 7)), None);
+        assert_eq!(schedule.current_end(at(6, 12)), None);
+    }
+
+    #[test]
+    fn overnight_period_goes_by_the_day_it_starts() {
+        // Monday nights only
+        let schedule = schedule(22, 7, &[Weekday::Mon]);
+        assert_eq!(schedule.current_end(at(5, 23)), Some(at(6, 7)));
+        assert_eq!(schedule.current_end(at(6, 3)), Some(at(6, 7)));
+        assert_eq!(schedule.current_end(at(6, 23)), None);
+        assert_eq!(schedule.current_end(at(5, 3)), None);
+    }
+
+    #[test]
+    fn all_day_period_ends_at_midnight() {
+        let schedule = schedule(9, 9, &[Weekday::Mon]);
+        assert_eq!(schedule.current_end(at(5, 0)), Some(at(6, 0)));
+        assert_eq!(schedule.current_end(at(5, 23)), Some(at(6, 0)));
+        assert_eq!(schedule.current_end(at(6, 12)), None);
+    }
+}
@@ -0,0 +1,167 @@
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+use tauri::{AppHandle, Manager};
+use tauri_plugin_global_shortcut::{GlobalShortcutExt, ShortcutState};
+
+use crate::{actions, features::FeatureFlags, prefs};
+
+const FAVORITES_KEY: &str = "favorites";
+const MODIFIERS_KEY: &str = "favorite_shortcut_modifiers";
+const DEFAULT_MODIFIERS: &str = "CommandOrControl+Alt";
+
+/// Favorite slots, keyed 1–9 like the number row.
+type Slots = BTreeMap<u8, String>;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Favorite {
+    pub slot: u8,
+    pub contact: String,
+    pub shortcut: String,
+}
+
+fn modifiers(app: &AppHandle) -> String {
+    prefs::load(app, MODIFIERS_KEY).unwrap_or_else(|| DEFAULT_MODIFIERS.to_string())
+}
+
+fn accelerator(modifiers: &str, slot: u8) -> String {
+    format!("{}+{}", modifiers, slot)
+}
+
+fn shortcuts_enabled(app: &AppHandle) -> bool {
+    app.state::<FeatureFlags>().is_enabled("global_shortcuts")
+}
+
+fn check_slot(slot: u8) -> Result<(), String> {
+    if (1..=9).contains(&slot) {
+        Ok(())
+    } else {
+        Err(format!("Favorite slot must be 1-9, got {}", slot))
+    }
+}
+
+fn bind(app: &AppHandle, modifiers: &str, slot: u8, contact: String) -> Result<(), String> {
+    let shortcut = accelerator(modifiers, slot);
+    app.global_shortcut()
+        .on_shortcut(shortcut.as_str(), move |app, _shortcut, event| {
+            if event.state != ShortcutState::Pressed {
+                return;
+            }
+            log::debug!("Favorite hotkey {} → {}", slot, contact);
+            actions::open_chat(app, &contact);
+        })
+        .map_err(|e| e.to_string())
+}
+
+fn unbind(app: &AppHandle, modifiers: &str, slot: u8) {
+    let shortcut = accelerator(modifiers, slot);
+    if app.global_shortcut().is_registered(shortcut.as_str()) {
+        if let Err(e) = app.global_shortcut().unregister(shortcut.as_str()) {
+            log::warn!("Failed to unregister {}: {}", shortcut, e);
+        }
+    }
+}
+
+/// Register hotkeys for every saved favorite. Called once from `setup`.
+pub fn register_all(app: &AppHandle) {
+    if !shortcuts_enabled(app) {
+        return;
+    }
+    let modifiers = modifiers(app);
+    let slots: Slots = prefs::load(app, FAVORITES_KEY).unwrap_or_default();
+    for (slot, contact) in slots {
+        if let Err(e) = bind(app, &modifiers, slot, contact) {
+            log::warn!("Failed to register favorite hotkey {}: {}", slot, e);
+        }
+    }
+}
+
+#[tauri::command]
+pub fn get_favorites(app: AppHandle) -> Vec<Favorite> {
+    let modifiers = modifiers(&app);
+    let slots: Slots = prefs::load(&app, FAVORITES_KEY).unwrap_or_default();
+    slots
+        .into_iter()
+        .map(|(slot, contact)| Favorite {
+            slot,
+            contact,
+            shortcut: accelerator(&modifiers, slot),
+        })
+        .collect()
+}
+
+/// Put `contact` in `slot`, replacing whoever was there. A contact only
+/// ever occupies one slot, so an existing slot for it is cleared first.
+#[tauri::command]
+pub fn set_favorite(app: AppHandle, contact: String, slot: u8) -> Result<(), String> {
+    check_slot(slot)?;
+    let modifiers = modifiers(&app);
+    let mut slots: Slots = prefs::load(&app, FAVORITES_KEY).unwrap_or_default();
+
+    let stale: Slots = slots
+        .iter()
+        .filter(|(&s, c)| s == slot || **c == contact)
+        .map(|(&s, c)| (s, c.clone()))
+        .collect();
+    for &s in stale.keys() {
+        slots.remove(&s);
+        if shortcuts_enabled(&app) {
+            unbind(&app, &modifiers, s);
+        }
+    }
+
+    // Only saved once the hotkey works, so a failed one isn't retried on
+    // every launch; the hotkeys cleared for it come back.
+    if shortcuts_enabled(&app) {
+        if let Err(e) = bind(&app, &modifiers, slot, contact.clone()) {
+            for (s, c) in stale {
+                if let Err(e) = bind(&app, &modifiers, s, c) {
+                    log::warn!("Failed to restore favorite hotkey {}: {}", s, e);
+                }
+            }
+            return Err(e);
+        }
+    }
+    slots.insert(slot, contact);
+    prefs::save(&app, FAVORITES_KEY, &slots)
+}
+
+#[tauri::command]
+pub fn clear_favorite(app: AppHandle, slot: u8) -> Result<(), String> {
+    check_slot(slot)?;
+    let mut slots: Slots = prefs::load(&app, FAVORITES_KEY).unwrap_or_default();
+    if slots.remove(&slot).is_some() {
+        prefs::save(&app, FAVORITES_KEY, &slots)?;
+        if shortcuts_enabled(&app) {
+            unbind(&app, &modifiers(&app), slot);
+        }
+    }
+    Ok(())
+}
+
+/// Change the modifier prefix used for every favorite hotkey, e.g.
+/// "CommandOrControl+Shift". The digit is always appended.
+#[tauri::command]
+pub fn set_favorite_shortcut_modifiers(app: AppHandle, modifiers: String) -> Result<(), String> {
+    // Validate before touching any registrations.
+    accelerator(&modifiers, 1)
+        .parse::<tauri_plugin_global_shortcut::Shortcut>()
+        .map_err(|e| e.to_string())?;
+
+    let old = self::modifiers(&app);
+    let slots: Slots = prefs::load(&app, FAVORITES_KEY).unwrap_or_default();
+
+    if shortcuts_enabled(&app) {
+        for &slot in slots.keys() {
+            unbind(&app, &old, slot);
+        }
+    }
+    prefs::save(&app, MODIFIERS_KEY, &modifiers)?;
+    if shortcuts_enabled(&app) {
+        for (slot, contact) in slots {
+            bind(&app, &modifiers, slot, contact)?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,123 @@
+use std::collections::HashMap;
+
+use serde::Serialize;
+use tauri::{AppHandle, State};
+
+use crate::prefs;
+
+/// Subsystems that can be switched off, with their built-in default. New
+/// optional subsystems add themselves here and check `is_enabled` before
+/// they are spawned.
+const FLAGS: &[(&str, bool)] = &[
+    ("autostart", true),
+    ("chat_bubbles", false),
+    ("global_shortcuts", true),
+    ("lan_pairing", true),
+    ("local_ipc", false),
+];
+
+/// Local overrides chosen by the user.
+const CONFIG_KEY: &str = "feature_flags";
+/// Overrides pushed from the server; these win over local config.
+const REMOTE_KEY: &str = "remote_feature_flags";
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FlagSource {
+    Default,
+    Config,
+    Remote,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FeatureFlag {
+    pub name: &'static str,
+    pub enabled: bool,
+    pub source: FlagSource,
+}
+
+/// Flags resolved once at startup. Subsystems are only spawned at launch, so
+/// changes made while running apply on the next start.
+pub struct FeatureFlags {
+    flags: Vec<FeatureFlag>,
+}
+
+impl FeatureFlags {
+    pub fn load(app: &AppHandle) -> Self {
+        let config: HashMap<String, bool> = prefs::load(app, CONFIG_KEY).unwrap_or_default();
+        let remote: HashMap<String, bool> = prefs::load(app, REMOTE_KEY).unwrap_or_default();
+
+        let flags = FLAGS
+            .iter()
+            .map(|&(name, default)| {
+                let (enabled, source) = if let Some(&on) = remote.get(name) {
+                    (on, FlagSource::Remote)
+                } else if let Some(&on) = config.get(name) {
+                    (on, FlagSource::Config)
+                } else {
+                    (default, FlagSource::Default)
+                };
+                FeatureFlag {
+                    name,
+                    enabled,
+                    source,
+                }
+            })
+            .collect::<Vec<_>>();
+
+        for flag in &flags {
+            log::info!(
+                "Feature {}: {} ({:?})",
+                flag.name,
+                if flag.enabled { "on" } else { "off" },
+                flag.source
+            );
+        }
+
+        Self { flags }
+    }
+
+    pub fn is_enabled(&self, name: &str) -> bool {
+        self.flags
+            .iter()
+            .find(|f| f.name == name)
+            .is_some_and(|f| f.enabled)
+    }
+}
+
+fn check_known(name: &str) -> Result<(), String> {
+    if FLAGS.iter().any(|&(known, _)| known == name) {
+        Ok(())
+    } else {
+        Err(format!("Unknown feature flag: {}", name))
+    }
+}
+
+#[tauri::command]
+pub fn get_feature_flags(flags: State<'_, FeatureFlags>) -> Vec<FeatureFlag> {
+    flags.flags.clone()
+}
+
+/// Persist a local override. Takes effect on the next launch.
+#[tauri::command]
+pub fn set_feature_flag(app: AppHandle, name: String, enabled: bool) -> Result<(), String> {
+    check_known(&name)?;
+    let mut config: HashMap<String, bool> = prefs::load(&app, CONFIG_KEY).unwrap_or_default();
+    config.insert(name, enabled);
+    prefs::save(&app, CONFIG_KEY, &config)
+}
+
+/// Replace the server-provided overrides. Unknown names are dropped so a
+/// newer server can't break an older client. Takes effect on the next launch.
+#[tauri::command]
+pub fn set_remote_feature_flags(
+    app: AppHandle,
+    flags: HashMap<String, bool>,
+) -> Result<(), String> {
+    let known: HashMap<String, bool> = flags
+        .into_iter()
+        .filter(|(name, _)| check_known(name).is_ok())
+        .collect();
+    prefs::save(&app, REMOTE_KEY, &known)
+}
@@ -0,0 +1,101 @@
+use serde::Serialize;
+
+/// What the operating system currently thinks about showing notifications.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+#[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+pub enum OsFocusState {
+    /// Notifications are welcome.
+    Available,
+    /// Focus Assist / macOS Focus / quiet hours is on.
+    Focus,
+    /// A full-screen app or game is in the foreground.
+    Busy,
+    /// Presentation mode is on.
+    Presentation,
+    /// The platform gives us no way to tell.
+    Unknown,
+}
+
+impl OsFocusState {
+    pub fn suppresses_toasts(self) -> bool {
+        matches!(self, Self::Focus | Self::Busy | Self::Presentation)
+    }
+}
+
+// ── Windows: SHQueryUserNotificationState ───────────────────────────────────
+
+#[cfg(target_os = "windows")]
+pub fn query<R: tauri::Runtime>(_app: &tauri::AppHandle<R>) -> OsFocusState {
+    use windows_sys::Win32::UI::Shell::{
+        SHQueryUserNotificationState, QUNS_ACCEPTS_NOTIFICATIONS, QUNS_APP, QUNS_BUSY,
+        QUNS_NOT_PRESENT, QUNS_PRESENTATION_MODE, QUNS_QUIET_TIME, QUNS_RUNNING_D3D_FULL_SCREEN,
+    };
+
+    let mut state = 0;
+    // SAFETY: `state` is a valid out pointer for the duration of the call.
+    let hr = unsafe { SHQueryUserNotificationState(&mut state) };
+    if hr < 0 {
+        log::debug!("SHQueryUserNotificationState failed: {:#x}", hr);
+        return OsFocusState::Unknown;
+    }
+
+    match state {
+        QUNS_QUIET_TIME => OsFocusState::Focus,
+        // QUNS_APP is a full-screen app in the foreground
+        QUNS_BUSY | QUNS_RUNNING_D3D_FULL_SCREEN | QUNS_APP => OsFocusState::Busy,
+        QUNS_PRESENTATION_MODE => OsFocusState::Presentation,
+        QUNS_ACCEPTS_NOTIFICATIONS | QUNS_NOT_PRESENT => OsFocusState::Available,
+        _ => OsFocusState::Unknown,
+    }
+}
+
+// ── macOS: Focus assertions written by the DoNotDisturb daemon ──────────────
+
+#[cfg(target_os = "macos")]
+pub fn query<R: tauri::Runtime>(app: &tauri::AppHandle<R>) -> OsFocusState {
+    use tauri::Manager;
+
+    // There is no public API for the Focus state outside of an Intents
+    // extension, so read the assertion store the system keeps for it. An
+    // active Focus shows up as at least one assertion record.
+    let Ok(home) = app.path().home_dir() else {
+        return OsFocusState::Unknown;
+    };
+    let path = home.join("Library/DoNotDisturb/DB/Assertions.json");
+    let Ok(raw) = std::fs::read_to_string(&path) else {
+        return OsFocusState::Unknown;
+    };
+    let Ok(json) = serde_json::from_str::<serde_json::Value>(&raw) else {
+        return OsFocusState::Unknown;
+    };
+
+    let active = json["data"]
+        .as_array()
+        .map(|entries| {
+            entries.iter().any(|entry| {
+                entry["storeAssertionRecords"]
+                    .as_array()
+                    .is_some_and(|records| !records.is_empty())
+            })
+        })
+        .unwrap_or(false);
+
+    if active {
+        OsFocusState::Focus
+    } else {
+        OsFocusState::Available
+    }
+}
+
+// ── Everything else: the notification daemon handles DND on its own ────────
+
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+pub fn query<R: tauri::Runtime>(_app: &tauri::AppHandle<R>) -> OsFocusState {
+    OsFocusState::Unknown
+}
+
+#[tauri::command]
+pub fn get_os_focus_state(app: tauri::AppHandle) -> OsFocusState {
+    query(&app)
+}
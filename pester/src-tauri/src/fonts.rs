@@ -0,0 +1,174 @@
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+use tauri::{AppHandle, Manager};
+
+use crate::prefs;
+
+const MESSAGE_FONT_KEY: &str = "message_font";
+
+/// Color emoji fonts tried after the message font, so emoji render in
+/// color whichever font the user picked.
+const EMOJI_FALLBACK: &[&str] = &[
+    "Apple Color Emoji",
+    "Segoe UI Emoji",
+    "Noto Color Emoji",
+    "Twemoji Mozilla",
+];
+
+/// Formats fontdb can parse, and therefore validate before import.
+const FONT_EXTENSIONS: &[&str] = &["ttf", "otf", "ttc"];
+
+/// A font file the user imported, served to the webview via the asset
+/// protocol from `$APPDATA/fonts`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CustomFont {
+    pub family: String,
+    pub file: String,
+    pub path: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MessageFont {
+    /// Picked family, `None` for the theme default.
+    pub family: Option<String>,
+    /// Ready-to-use CSS `font-family` value with the emoji fallback.
+    pub stack: String,
+}
+
+fn fonts_dir(app: &AppHandle) -> Result<PathBuf, String> {
+    let dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| e.to_string())?
+        .join("fonts");
+    std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    Ok(dir)
+}
+
+/// First family name in a font file, or `None` if it isn't a font.
+fn family_of(data: Vec<u8>) -> Option<String> {
+    let mut db = fontdb::Database::new();
+    db.load_font_data(data);
+    let face = db.faces().next()?;
+    face.families.first().map(|(name, _)| name.clone())
+}
+
+fn read_custom_font(path: &Path) -> Option<CustomFont> {
+    let data = std::fs::read(path).ok()?;
+    Some(CustomFont {
+        family: family_of(data)?,
+        file: path.file_name()?.to_string_lossy().into_owned(),
+        path: path.to_string_lossy().into_owned(),
+    })
+}
+
+fn check_file_name(file: &str) -> Result<(), String> {
+    let valid = !file.is_empty()
+        && !file.starts_with('.')
+        && !file.contains(['/', '\\'])
+        && Path::new(file).file_name().is_some_and(|name| name == file);
+    if valid {
+        Ok(())
+    } else {
+        Err(format!("Invalid font file name: {}", file))
+    }
+}
+
+fn css_stack(family: Option<&str>) -> String {
+    family
+        .into_iter()
+        .chain(EMOJI_FALLBACK.iter().copied())
+        .map(|name| format!("\"{}\"", name.replace('"', "")))
+        .chain(std::iter::once("sans-serif".to_string()))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Font families installed on the system, sorted and deduplicated.
+#[tauri::command]
+pub async fn list_available_fonts() -> Result<Vec<String>, String> {
+    tauri::async_runtime::spawn_blocking(|| {
+        let mut db = fontdb::Database::new();
+        db.load_system_fonts();
+        db.faces()
+            .filter_map(|face| face.families.first().map(|(name, _)| name.clone()))
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect()
+    })
+    .await
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn list_custom_fonts(app: AppHandle) -> Result<Vec<CustomFont>, String> {
+    let dir = fonts_dir(&app)?;
+    let mut fonts: Vec<CustomFont> = std::fs::read_dir(&dir)
+        .map_err(|e| e.to_string())?
+        .flatten()
+        .filter_map(|entry| read_custom_font(&entry.path()))
+        .collect();
+    fonts.sort_by(|a, b| a.family.cmp(&b.family));
+    Ok(fonts)
+}
+
+/// Copy a font file into Pester's font folder so it can be used for
+/// messages. The file must be a TrueType or OpenType font.
+#[tauri::command]
+pub fn register_font(app: AppHandle, path: String) -> Result<CustomFont, String> {
+    let source = PathBuf::from(&path);
+    let extension = source
+        .extension()
+        .map(|e| e.to_string_lossy().to_ascii_lowercase())
+        .unwrap_or_default();
+    if !FONT_EXTENSIONS.contains(&extension.as_str()) {
+        return Err(format!("Unsupported font format: {}", path));
+    }
+    let file = source
+        .file_name()
+        .ok_or_else(|| format!("Not a file: {}", path))?
+        .to_string_lossy()
+        .into_owned();
+
+    let data = std::fs::read(&source).map_err(|e| e.to_string())?;
+    let family = family_of(data.clone()).ok_or_else(|| format!("Not a font file: {}", path))?;
+
+    let target = fonts_dir(&app)?.join(&file);
+    std::fs::write(&target, data).map_err(|e| e.to_string())?;
+    log::debug!("Registered font {} ({})", family, file);
+
+    Ok(CustomFont {
+        family,
+        file,
+        path: target.to_string_lossy().into_owned(),
+    })
+}
+
+#[tauri::command]
+pub fn remove_custom_font(app: AppHandle, file: String) -> Result<(), String> {
+    check_file_name(&file)?;
+    std::fs::remove_file(fonts_dir(&app)?.join(&file)).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn get_message_font(app: AppHandle) -> MessageFont {
+    let family: Option<String> = prefs::load(&app, MESSAGE_FONT_KEY).flatten();
+    MessageFont {
+        stack: css_stack(family.as_deref()),
+        family,
+    }
+}
+
+/// Pick the font messages are rendered in, or `None` for the theme default.
+#[tauri::command]
+pub fn set_message_font(app: AppHandle, family: Option<String>) -> Result<MessageFont, String> {
+    prefs::save(&app, MESSAGE_FONT_KEY, &family)?;
+    Ok(MessageFont {
+        stack: css_stack(family.as_deref()),
+        family,
+    })
+}
@@ -0,0 +1,285 @@
+//! Local IPC endpoint for companion tools (CLI helpers, a future browser
+//! extension bridge). Windows uses a named pipe named after the current
+//! user's SID, everything else a Unix domain socket named after the app
+//! in a directory only the current user can open. Off unless the
+//! `local_ipc` feature flag is turned on.
+//!
+//! Frames are a 4-byte big-endian length followed by a JSON object:
+//!
+//! - `{"type":"ping"}` → `{"type":"pong","version":"…"}`
+//! - `{"type":"unread"}` → `{"type":"unread","total":3,"conversations":{…}}`
+//! - `{"type":"share","text":"…","files":["…"]}` → `{"type":"ok"}`
+//! - `{"type":"action","name":"mark_read","args":{…}}` → `{"type":"result","value":…}`
+//! - `{"type":"subscribe"}` → `{"type":"ok"}`, then one `{"type":"event",…}`
+//!   frame per accessibility event until the client disconnects
+
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::sync::broadcast;
+
+use crate::a11y::{A11yBus, A11yEvent};
+use crate::{actions, unread::UnreadState};
+
+/// Largest request we accept; anything bigger is treated as hostile.
+const MAX_FRAME: usize = 1024 * 1024;
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Request {
+    Ping,
+    Unread,
+    Share {
+        #[serde(default)]
+        text: Option<String>,
+        #[serde(default)]
+        files: Vec<String>,
+    },
+    Action {
+        name: String,
+        #[serde(default)]
+        args: serde_json::Value,
+    },
+    Subscribe,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Response {
+    Pong {
+        version: String,
+    },
+    Unread {
+        total: u32,
+        conversations: HashMap<String, u32>,
+    },
+    Ok,
+    Result {
+        value: serde_json::Value,
+    },
+    Event {
+        #[serde(flatten)]
+        event: A11yEvent,
+    },
+    Error {
+        message: String,
+    },
+}
+
+/// Payload of the `share-received` event.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SharePayload {
+    text: Option<String>,
+    files: Vec<String>,
+}
+
+fn handle(app: &AppHandle, request: Request) -> Response {
+    match request {
+        Request::Ping => Response::Pong {
+            version: app.package_info().version.to_string(),
+        },
+        Request::Unread => {
+            let conversations = app.state::<UnreadState>().counts();
+            Response::Unread {
+                total: conversations.values().sum(),
+                conversations,
+            }
+        }
+        Request::Share { text, files } => {
+            if text.as_deref().is_none_or(str::is_empty) && files.is_empty() {
+                return Response::Error {
+                    message: "Nothing to share".to_string(),
+                };
+            }
+            log::debug!("Received share over IPC ({} files)", files.len());
+            crate::show_main_window(app);
+            let _ = app.emit("share-received", SharePayload { text, files });
+            Response::Ok
+        }
+        Request::Action { name, args } => match actions::invoke(app, &name, &args) {
+            Ok(value) => Response::Result { value },
+            Err(message) => Response::Error { message },
+        },
+        // Handled by `serve_client`, which owns the stream.
+        Request::Subscribe => Response::Ok,
+    }
+}
+
+async fn write_frame<S>(stream: &mut S, response: &Response) -> std::io::Result<()>
+where
+    S: AsyncWrite + Unpin,
+{
+    let out = serde_json::to_vec(response).map_err(std::io::Error::other)?;
+    stream.write_u32(out.len() as u32).await?;
+    stream.write_all(&out).await?;
+    stream.flush().await
+}
+
+/// Push accessibility events to a subscribed client until it goes away.
+async fn stream_events<S>(app: &AppHandle, stream: &mut S) -> std::io::Result<()>
+where
+    S: AsyncWrite + Unpin,
+{
+    let mut events = app.state::<A11yBus>().subscribe();
+    loop {
+        match events.recv().await {
+            Ok(event) => write_frame(stream, &Response::Event { event }).await?,
+            Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                log::debug!("IPC subscriber lagged, skipped {} events", skipped);
+            }
+            Err(broadcast::error::RecvError::Closed) => return Ok(()),
+        }
+    }
+}
+
+async fn serve_client<S>(app: AppHandle, mut stream: S) -> std::io::Result<()>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    loop {
+        let len = match stream.read_u32().await {
+            Ok(len) => len as usize,
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(()),
+            Err(e) => return Err(e),
+        };
+        if len > MAX_FRAME {
+            log::warn!("Dropping IPC client after oversized frame ({} bytes)", len);
+            return Ok(());
+        }
+
+        let mut buf = vec![0; len];
+        stream.read_exact(&mut buf).await?;
+
+        let request = serde_json::from_slice::<Request>(&buf);
+        let subscribe = matches!(request, Ok(Request::Subscribe));
+        let response = match request {
+            Ok(request) => handle(&app, request),
+            Err(e) => Response::Error {
+                message: format!("Invalid request: {}", e),
+            },
+        };
+        write_frame(&mut stream, &response).await?;
+
+        if subscribe {
+            return stream_events(&app, &mut stream).await;
+        }
+    }
+}
+
+// ── Windows: named pipe ─────────────────────────────────────────────────────
+
+/// The current user's SID, e.g. `S-1-5-21-…`. Pipe names are global to
+/// the machine, so it keeps each user's pipe apart.
+#[cfg(target_os = "windows")]
+fn user_sid() -> std::io::Result<String> {
+    use windows_sys::Win32::Foundation::{CloseHandle, LocalFree};
+    use windows_sys::Win32::Security::Authorization::ConvertSidToStringSidW;
+    use windows_sys::Win32::Security::{GetTokenInformation, TokenUser, TOKEN_QUERY, TOKEN_USER};
+    use windows_sys::Win32::System::Threading::{GetCurrentProcess, OpenProcessToken};
+
+    // SAFETY: the token is closed once read, the buffer is u64s so it is
+    // aligned for TOKEN_USER, and the SID string is freed after copying.
+    unsafe {
+        let mut token = std::ptr::null_mut();
+        if OpenProcessToken(GetCurrentProcess(), TOKEN_QUERY, &mut token) == 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        let mut len = 0;
+        GetTokenInformation(token, TokenUser, std::ptr::null_mut(), 0, &mut len);
+        let mut buffer = vec![0u64; (len as usize).div_ceil(8)];
+        let ok = GetTokenInformation(token, TokenUser, buffer.as_mut_ptr().cast(), len, &mut len);
+        let error = std::io::Error::last_os_error();
+        CloseHandle(token);
+        if ok == 0 {
+            return Err(error);
+        }
+
+        let user = &*(buffer.as_ptr() as *const TOKEN_USER);
+        let mut sid = std::ptr::null_mut();
+        if ConvertSidToStringSidW(user.User.Sid, &mut sid) == 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        let len = (0..).take_while(|&i| *sid.add(i) != 0).count();
+        let string = String::from_utf16_lossy(std::slice::from_raw_parts(sid, len));
+        LocalFree(sid.cast());
+        Ok(string)
+    }
+}
+
+#[cfg(target_os = "windows")]
+async fn listen(app: AppHandle) -> std::io::Result<()> {
+    use tokio::net::windows::named_pipe::ServerOptions;
+
+    let name = format!(r"\\.\pipe\{}-{}", app.config().identifier, user_sid()?);
+    let mut server = ServerOptions::new()
+        .first_pipe_instance(true)
+        .reject_remote_clients(true)
+        .create(&name)?;
+    log::info!("IPC listening on {}", name);
+
+    loop {
+        server.connect().await?;
+        let client = server;
+        server = ServerOptions::new()
+            .reject_remote_clients(true)
+            .create(&name)?;
+
+        let app = app.clone();
+        tauri::async_runtime::spawn(async move {
+            if let Err(e) = serve_client(app, client).await {
+                log::debug!("IPC client error: {}", e);
+            }
+        });
+    }
+}
+
+// ── Everything else: Unix domain socket ─────────────────────────────────────
+
+#[cfg(not(target_os = "windows"))]
+async fn listen(app: AppHandle) -> std::io::Result<()> {
+    use std::os::unix::fs::{DirBuilderExt, PermissionsExt};
+    use tokio::net::UnixListener;
+
+    let identifier = &app.config().identifier;
+    let dir = std::env::var_os("XDG_RUNTIME_DIR")
+        .map(|runtime| std::path::PathBuf::from(runtime).join(identifier))
+        .or_else(|| app.path().app_local_data_dir().ok())
+        .ok_or_else(|| std::io::Error::other("No directory for the IPC socket"))?;
+    // Only we can reach the socket, even before its own mode is set
+    std::fs::DirBuilder::new()
+        .recursive(true)
+        .mode(0o700)
+        .create(&dir)?;
+    std::fs::set_permissions(&dir, std::fs::Permissions::from_mode(0o700))?;
+    let path = dir.join(format!("{}.sock", identifier));
+
+    // Single-instance guarantees any existing socket is stale.
+    let _ = std::fs::remove_file(&path);
+    let listener = UnixListener::bind(&path)?;
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600))?;
+    log::info!("IPC listening on {}", path.display());
+
+    loop {
+        let (client, _) = listener.accept().await?;
+        let app = app.clone();
+        tauri::async_runtime::spawn(async move {
+            if let Err(e) = serve_client(app, client).await {
+                log::debug!("IPC client error: {}", e);
+            }
+        });
+    }
+}
+
+/// Start the IPC endpoint in the background. Called from `setup` when the
+/// `local_ipc` feature flag is on.
+pub fn start(app: &AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = listen(app).await {
+            log::error!("IPC endpoint stopped: {}", e);
+        }
+    });
+}
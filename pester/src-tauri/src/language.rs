@@ -0,0 +1,204 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+use whatlang::Lang;
+
+use crate::prefs;
+
+const LANGUAGES_KEY: &str = "chat_languages";
+
+/// Below this much text detection is mostly noise ("ok", "lol", emoji).
+const MIN_SAMPLE_CHARS: usize = 20;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StoredLanguage {
+    /// Last detected language as a BCP-47 tag.
+    detected: Option<String>,
+    confidence: f64,
+    /// Language picked by the user; wins over detection.
+    manual: Option<String>,
+}
+
+/// Conversation ID → language settings.
+type ChatLanguages = HashMap<String, StoredLanguage>;
+
+/// What the UI needs to configure spellcheck and translation for a chat.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChatLanguage {
+    /// Language to use for spellcheck and as the translation source, or
+    /// `None` to leave the system default alone.
+    pub language: Option<String>,
+    pub detected: Option<String>,
+    /// Detection confidence, 0.0–1.0.
+    pub confidence: f64,
+    pub overridden: bool,
+}
+
+impl From<&StoredLanguage> for ChatLanguage {
+    fn from(stored: &StoredLanguage) -> Self {
+        Self {
+            language: stored.manual.clone().or_else(|| stored.detected.clone()),
+            detected: stored.detected.clone(),
+            confidence: stored.confidence,
+            overridden: stored.manual.is_some(),
+        }
+    }
+}
+
+/// whatlang speaks ISO 639-3; spellcheckers and `lang` attributes want
+/// ISO 639-1 where one exists.
+fn bcp47(lang: Lang) -> &'static str {
+    match lang {
+        Lang::Epo => "eo",
+        Lang::Eng => "en",
+        Lang::Rus => "ru",
+        Lang::Cmn => "zh",
+        Lang::Spa => "es",
+        Lang::Por => "pt",
+        Lang::Ita => "it",
+        Lang::Ben => "bn",
+        Lang::Fra => "fr",
+        Lang::Deu => "de",
+        Lang::Ukr => "uk",
+        Lang::Kat => "ka",
+        Lang::Ara => "ar",
+        Lang::Hin => "hi",
+        Lang::Jpn => "ja",
+        Lang::Heb => "he",
+        Lang::Yid => "yi",
+        Lang::Pol => "pl",
+        Lang::Amh => "am",
+        Lang::Jav => "jv",
+        Lang::Kor => "ko",
+        Lang::Nob => "nb",
+        Lang::Dan => "da",
+        Lang::Swe => "sv",
+        Lang::Fin => "fi",
+        Lang::Tur => "tr",
+        Lang::Nld => "nl",
+        Lang::Hun => "hu",
+        Lang::Ces => "cs",
+        Lang::Ell => "el",
+        Lang::Bul => "bg",
+        Lang::Bel => "be",
+        Lang::Mar => "mr",
+        Lang::Kan => "kn",
+        Lang::Ron => "ro",
+        Lang::Slv => "sl",
+        Lang::Hrv => "hr",
+        Lang::Srp => "sr",
+        Lang::Mkd => "mk",
+        Lang::Lit => "lt",
+        Lang::Lav => "lv",
+        Lang::Est => "et",
+        Lang::Tam => "ta",
+        Lang::Vie => "vi",
+        Lang::Urd => "ur",
+        Lang::Tha => "th",
+        Lang::Guj => "gu",
+        Lang::Uzb => "uz",
+        Lang::Pan => "pa",
+        Lang::Aze => "az",
+        Lang::Ind => "id",
+        Lang::Tel => "te",
+        Lang::Pes => "fa",
+        Lang::Mal => "ml",
+        Lang::Ori => "or",
+        Lang::Mya => "my",
+        Lang::Nep => "ne",
+        Lang::Sin => "si",
+        Lang::Khm => "km",
+        Lang::Tuk => "tk",
+        Lang::Aka => "ak",
+        Lang::Zul => "zu",
+        Lang::Sna => "sn",
+        Lang::Afr => "af",
+        Lang::Lat => "la",
+        Lang::Slk => "sk",
+        Lang::Cat => "ca",
+        Lang::Tgl => "tl",
+        Lang::Hye => "hy",
+    }
+}
+
+/// Loose BCP-47 check: a 2–3 letter primary tag plus optional subtags,
+/// e.g. "en", "pt-BR", "zh-Hant".
+fn check_tag(tag: &str) -> Result<(), String> {
+    let mut parts = tag.split('-');
+    let primary = parts.next().unwrap_or_default();
+    let valid = (2..=3).contains(&primary.len())
+        && primary.chars().all(|c| c.is_ascii_alphabetic())
+        && parts
+            .all(|p| (1..=8).contains(&p.len()) && p.chars().all(|c| c.is_ascii_alphanumeric()));
+    if valid {
+        Ok(())
+    } else {
+        Err(format!("Invalid language tag: {}", tag))
+    }
+}
+
+#[tauri::command]
+pub fn get_chat_language(app: AppHandle, conversation: String) -> ChatLanguage {
+    let languages: ChatLanguages = prefs::load(&app, LANGUAGES_KEY).unwrap_or_default();
+    languages
+        .get(&conversation)
+        .map(ChatLanguage::from)
+        .unwrap_or_else(|| ChatLanguage::from(&StoredLanguage::default()))
+}
+
+/// Detect the dominant language of `conversation` from a sample of its
+/// recent messages. Too little text, or text whatlang can't place, keeps
+/// the previous detection.
+#[tauri::command]
+pub fn detect_conversation_language(
+    app: AppHandle,
+    conversation: String,
+    samples: Vec<String>,
+) -> Result<ChatLanguage, String> {
+    let mut languages: ChatLanguages = prefs::load(&app, LANGUAGES_KEY).unwrap_or_default();
+    let entry = languages.entry(conversation.clone()).or_default();
+
+    let text = samples.join("\n");
+    if text.chars().filter(|c| c.is_alphabetic()).count() < MIN_SAMPLE_CHARS {
+        return Ok(ChatLanguage::from(&*entry));
+    }
+    let Some(info) = whatlang::detect(&text) else {
+        return Ok(ChatLanguage::from(&*entry));
+    };
+
+    let tag = bcp47(info.lang());
+    log::debug!(
+        "Detected {} for {} (confidence {:.2})",
+        tag,
+        conversation,
+        info.confidence()
+    );
+    entry.detected = Some(tag.to_string());
+    entry.confidence = info.confidence();
+
+    let result = ChatLanguage::from(&*entry);
+    prefs::save(&app, LANGUAGES_KEY, &languages)?;
+    Ok(result)
+}
+
+/// Pin a chat's language, or pass `None` to go back to detection.
+#[tauri::command]
+pub fn set_chat_language(
+    app: AppHandle,
+    conversation: String,
+    language: Option<String>,
+) -> Result<ChatLanguage, String> {
+    if let Some(tag) = &language {
+        check_tag(tag)?;
+    }
+    let mut languages: ChatLanguages = prefs::load(&app, LANGUAGES_KEY).unwrap_or_default();
+    let entry = languages.entry(conversation).or_default();
+    entry.manual = language;
+
+    let result = ChatLanguage::from(&*entry);
+    prefs::save(&app, LANGUAGES_KEY, &languages)?;
+    Ok(result)
+}
@@ -1,17 +1,125 @@
 use tauri::{
-    menu::{Menu, MenuItem, PredefinedMenuItem},
-    tray::TrayIconEvent,
-    Emitter, Manager, PhysicalPosition, Position,
+    menu::{CheckMenuItem, Menu, MenuItem, PredefinedMenuItem, Submenu},
+    webview::PageLoadEvent,
+    Emitter, Manager,
 };
 
 use log::LevelFilter;
+use unicode_segmentation::UnicodeSegmentation;
 
+mod a11y;
+mod actions;
+mod always_on_top;
+mod analytics;
+mod audio;
+mod backup;
+mod badge;
+mod bubble;
+mod catch_up;
+mod chat_window;
+mod chunking;
+mod clock;
+mod compact_mode;
+mod connection;
+mod content_filter;
+mod conversations;
+mod crypto;
+mod deep_link;
+mod dnd;
+mod favorites;
+mod features;
+mod focus;
+mod fonts;
+mod ipc;
+mod language;
+mod logs;
+mod notes;
+mod notification_permission;
+mod notifications;
+mod outbox;
+mod pairing;
+mod platform;
+mod positioner;
+mod power;
+mod prefs;
+mod presence;
+mod presentation;
+mod pressure;
+mod reminders;
+mod scheduler;
+mod secrets;
+mod send_history;
+mod server;
+mod storage;
+mod tasks;
+mod timeline;
+mod tray_click;
+mod unread;
+mod view_state;
+mod whats_new;
+mod window_state;
+
+/// Bring the main window to the front, restoring it if minimized.
+fn show_main_window<R: tauri::Runtime>(app: &tauri::AppHandle<R>) {
+    if let Some(w) = app.get_webview_window("main") {
+        let _ = w.unminimize();
+        let _ = w.show();
+        let _ = w.set_focus();
+    }
+}
+
+// ── Tray menu ───────────────────────────────────────────────────────────────
+
+const TRAY_LABEL_WIDTH_KEY: &str = "tray_label_width";
+/// Characters shown of a recent chat's name before it's cut off.
+const DEFAULT_TRAY_LABEL_WIDTH: usize = 12;
+const MAX_TRAY_LABEL_WIDTH: usize = 64;
+
+fn tray_label_width(app: &tauri::AppHandle) -> usize {
+    prefs::load(app, TRAY_LABEL_WIDTH_KEY).unwrap_or(DEFAULT_TRAY_LABEL_WIDTH)
+}
+
+/// Cut `name` to `width` user-perceived characters, so emoji and accented
+/// letters are never split.
+fn tray_label(name: &str, width: usize) -> String {
+    let mut graphemes = name.graphemes(true);
+    let label: String = graphemes.by_ref().take(width).collect();
+    if graphemes.next().is_some() {
+        format!("{}…", label)
+    } else {
+        label
+    }
+}
+
+/// A recent chat in the tray menu.
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RecentChat {
+    /// User ID, used for the menu item ID so it stays stable.
+    id: String,
+    /// Display name, the user ID when unset.
+    name: Option<String>,
+    #[serde(default)]
+    unread: u32,
+}
+
+/// Rebuild the tray menu, with `recent_chats` labelled like "Alice (3)".
 #[tauri::command]
-fn update_tray_menu(app: tauri::AppHandle, recent_users: Vec<String>) -> Result<(), String> {
+fn update_tray_menu(
+    app: tauri::AppHandle,
+    mut recent_chats: Vec<RecentChat>,
+    status: Option<presence::Presence>,
+) -> Result<(), String> {
     log::debug!(
-        "Updating tray menu with {} recent users",
-        recent_users.len()
+        "Updating tray menu with {} recent chats",
+        recent_chats.len()
     );
+    storage::sort::sort(
+        &app,
+        &app.state::<storage::Database>().lock(),
+        &mut recent_chats,
+        |chat| &chat.id,
+    )?;
 
     let tray = app.tray_by_id("main-tray").ok_or("Tray not found")?;
 
@@ -28,19 +136,54 @@ fn update_tray_menu(app: tauri::AppHandle, recent_users: Vec<String>) -> Result<
         .map_err(|e| e.to_string())?;
     menu.append(&new_contact).map_err(|e| e.to_string())?;
 
-    if !recent_users.is_empty() {
+    let pinned = CheckMenuItem::with_id(
+        &app,
+        always_on_top::MENU_ID,
+        "Always on Top",
+        true,
+        always_on_top::main_pinned(&app),
+        None::<&str>,
+    )
+    .map_err(|e| e.to_string())?;
+    menu.append(&pinned).map_err(|e| e.to_string())?;
+
+    if let Some(status) = status {
+        let submenu =
+            Submenu::with_id(&app, "status", "Status", true).map_err(|e| e.to_string())?;
+        for presence in presence::Presence::ALL {
+            let item = CheckMenuItem::with_id(
+                &app,
+                presence.menu_id(),
+                presence.label(),
+                true,
+                presence == status,
+                None::<&str>,
+            )
+            .map_err(|e| e.to_string())?;
+            submenu.append(&item).map_err(|e| e.to_string())?;
+        }
+        menu.append(&submenu).map_err(|e| e.to_string())?;
+    }
+    badge::set_presence(&app, status)?;
+
+    if !recent_chats.is_empty() {
         let sep2 = PredefinedMenuItem::separator(&app).map_err(|e| e.to_string())?;
         menu.append(&sep2).map_err(|e| e.to_string())?;
 
-        for user in &recent_users {
-            let label = if user.len() > 12 {
-                format!("{}…", &user[..12])
-            } else {
-                user.clone()
-            };
-            let item =
-                MenuItem::with_id(&app, &format!("chat_{}", user), &label, true, None::<&str>)
-                    .map_err(|e| e.to_string())?;
+        let width = tray_label_width(&app);
+        for chat in &recent_chats {
+            let mut label = tray_label(chat.name.as_deref().unwrap_or(&chat.id), width);
+            if chat.unread > 0 {
+                label = format!("{} ({})", label, chat.unread);
+            }
+            let item = MenuItem::with_id(
+                &app,
+                format!("chat_{}", chat.id),
+                &label,
+                true,
+                None::<&str>,
+            )
+            .map_err(|e| e.to_string())?;
             menu.append(&item).map_err(|e| e.to_string())?;
         }
     }
@@ -57,6 +200,67 @@ fn update_tray_menu(app: tauri::AppHandle, recent_users: Vec<String>) -> Result<
     Ok(())
 }
 
+#[tauri::command]
+fn get_tray_label_width(app: tauri::AppHandle) -> usize {
+    tray_label_width(&app)
+}
+
+/// Applies from the next `update_tray_menu`.
+#[tauri::command]
+fn set_tray_label_width(app: tauri::AppHandle, width: usize) -> Result<(), String> {
+    prefs::save(
+        &app,
+        TRAY_LABEL_WIDTH_KEY,
+        &width.clamp(1, MAX_TRAY_LABEL_WIDTH),
+    )
+}
+
+/// Show `text` on the Dock icon; `None` or empty clears it. Linux shows
+/// numeric labels as the launcher count, Windows has no equivalent (the
+/// tray badge covers it).
+#[tauri::command]
+fn set_dock_badge(app: tauri::AppHandle, text: Option<String>) -> Result<(), String> {
+    let window = app
+        .get_webview_window("main")
+        .ok_or("Main window not found")?;
+    let text = text.filter(|t| !t.is_empty());
+    #[cfg(target_os = "macos")]
+    window.set_badge_label(text).map_err(|e| e.to_string())?;
+    #[cfg(target_os = "linux")]
+    window
+        .set_badge_count(text.and_then(|t| t.parse().ok()))
+        .map_err(|e| e.to_string())?;
+    #[cfg(not(any(target_os = "macos", target_os = "linux")))]
+    let _ = (window, text);
+    Ok(())
+}
+
+/// Bounce the Dock icon (flash the taskbar button elsewhere) until the app
+/// is focused. `critical` keeps bouncing instead of once.
+#[tauri::command]
+fn request_attention(app: tauri::AppHandle, critical: bool) -> Result<(), String> {
+    // A few flashes then a steady highlight, unless it's critical
+    #[cfg(target_os = "windows")]
+    {
+        platform::windows::flash(&app, (!critical).then_some(3))
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    {
+        let window = app
+            .get_webview_window("main")
+            .ok_or("Main window not found")?;
+        let kind = if critical {
+            tauri::UserAttentionType::Critical
+        } else {
+            tauri::UserAttentionType::Informational
+        };
+        window
+            .request_user_attention(Some(kind))
+            .map_err(|e| e.to_string())
+    }
+}
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     // Configure logging based on build mode
@@ -85,10 +289,17 @@ pub fn run() {
                 tauri_plugin_log::TargetKind::LogDir {
                     file_name: Some("pester".to_string()),
                 },
-            ));
+            ))
+            // Keep a few old files for the in-app log viewer
+            .rotation_strategy(tauri_plugin_log::RotationStrategy::KeepSome(5));
     }
 
     tauri::Builder::default()
+        // Must come first so a second launch is caught before anything else
+        .plugin(tauri_plugin_single_instance::init(|app, _args, _cwd| {
+            show_main_window(app);
+        }))
+        .plugin(tauri_plugin_deep_link::init())
         .plugin(
             tauri_plugin_log::Builder::new()
                 .level(tauri_plugin_log::log::LevelFilter::Info)
@@ -99,71 +310,281 @@ pub fn run() {
                 .level(tauri_plugin_log::log::LevelFilter::Info)
                 .build(),
         )
-        .plugin(tauri_plugin_autostart::Builder::new().build())
         .plugin(tauri_plugin_opener::init())
-        .plugin(tauri_plugin_websocket::init())
         .plugin(tauri_plugin_notification::init())
         .plugin(tauri_plugin_clipboard_manager::init())
         .plugin(tauri_plugin_store::Builder::new().build())
-        .plugin(tauri_plugin_global_shortcut::Builder::new().build())
         .plugin(log_builder.build())
-        .invoke_handler(tauri::generate_handler![update_tray_menu])
+        .manage(notifications::NotificationJournal::default())
+        .manage(notifications::MessageGroups::default())
+        .manage(notification_permission::PermissionState::default())
+        .manage(tasks::TaskManager::default())
+        .manage(backup::Backups::default())
+        .manage(scheduler::Scheduler::default())
+        .manage(presentation::Presentation::default())
+        .manage(a11y::A11yBus::default())
+        .manage(bubble::BubbleState::default())
+        .manage(connection::ConnectionManager::default())
+        .manage(catch_up::CatchUp::default())
+        .manage(outbox::Outbox::default())
+        .manage(storage::recovery::DataRecovery::default())
+        .manage(pairing::Pairing::default())
+        .manage(badge::TrayBadge::default())
+        .manage(tray_click::TrayClicks::default())
+        .manage(positioner::TrayRect::default())
+        .manage(audio::AudioOutput::default())
+        .manage(whats_new::WhatsNewState::default())
+        .invoke_handler(tauri::generate_handler![
+            update_tray_menu,
+            get_tray_label_width,
+            set_tray_label_width,
+            set_dock_badge,
+            request_attention,
+            platform::set_taskbar_overlay,
+            platform::flash_taskbar,
+            badge::set_unread_count,
+            chat_window::open_chat_window,
+            always_on_top::set_always_on_top,
+            always_on_top::is_always_on_top,
+            badge::start_tray_flash,
+            badge::stop_tray_flash,
+            tray_click::get_tray_click_settings,
+            tray_click::set_tray_click_settings,
+            focus::get_os_focus_state,
+            notifications::show_notification,
+            notifications::notify_message,
+            notifications::get_notification_journal,
+            notification_permission::get_notification_permission,
+            notification_permission::request_notification_permission,
+            audio::sounds::play_sound,
+            audio::sounds::list_builtin_sounds,
+            audio::sounds::get_sound_settings,
+            audio::sounds::set_sound_settings,
+            dnd::get_dnd_state,
+            dnd::set_dnd_schedule,
+            dnd::snooze_notifications,
+            tasks::list_tasks,
+            tasks::cancel_task,
+            backup::get_backup_status,
+            backup::enable_backup,
+            backup::disable_backup,
+            backup::get_backup_recovery_phrase,
+            backup::run_backup_now,
+            backup::restore_backup,
+            features::get_feature_flags,
+            features::set_feature_flag,
+            features::set_remote_feature_flags,
+            clock::get_clock_skew,
+            favorites::get_favorites,
+            favorites::set_favorite,
+            favorites::clear_favorite,
+            favorites::set_favorite_shortcut_modifiers,
+            send_history::record_sent_message,
+            send_history::get_send_history,
+            send_history::record_incognito_message,
+            send_history::get_incognito_count,
+            deep_link::take_pending_deep_link,
+            deep_link::create_chat_shortcut,
+            unread::get_unread_counts,
+            unread::increment_unread,
+            unread::mark_unread,
+            unread::mark_read,
+            reminders::remind_me,
+            reminders::list_reminders,
+            reminders::cancel_reminder,
+            language::get_chat_language,
+            language::detect_conversation_language,
+            language::set_chat_language,
+            content_filter::scan_outgoing_message,
+            content_filter::get_content_filter_rules,
+            content_filter::set_content_filter_rule,
+            content_filter::allow_content_in_conversation,
+            view_state::save_view_state,
+            view_state::get_view_state,
+            scheduler::get_scheduler_status,
+            actions::invoke_action,
+            actions::list_actions,
+            conversations::get_conversation_flags,
+            conversations::mute_contact,
+            conversations::unmute_contact,
+            presentation::get_presentation_state,
+            presentation::set_presentation_override,
+            notes::get_conversation_note,
+            notes::set_conversation_note,
+            notes::add_checklist_item,
+            notes::toggle_checklist_item,
+            notes::remove_checklist_item,
+            a11y::publish_a11y_event,
+            bubble::show_bubble,
+            bubble::get_bubble,
+            bubble::open_bubble_chat,
+            bubble::dismiss_bubble,
+            fonts::list_available_fonts,
+            fonts::list_custom_fonts,
+            fonts::register_font,
+            fonts::remove_custom_font,
+            fonts::get_message_font,
+            fonts::set_message_font,
+            timeline::record_connection_event,
+            timeline::get_connection_timeline,
+            connection::connect,
+            connection::disconnect,
+            connection::send_message,
+            connection::send_typing,
+            connection::get_connection_state,
+            outbox::cancel_pending_send,
+            outbox::list_pending_sends,
+            outbox::list_queued_sends,
+            outbox::discard_queued_send,
+            outbox::get_send_delay,
+            outbox::set_send_delay,
+            storage::messages::save_message,
+            storage::messages::get_messages,
+            storage::messages::list_stored_conversations,
+            storage::sort::get_conversation_sort,
+            storage::sort::set_conversation_sort,
+            storage::sort::sort_conversations,
+            server::get_server,
+            server::discover_server,
+            server::set_server_endpoints,
+            server::reset_server,
+            logs::read_logs,
+            compact_mode::get_compact_mode,
+            compact_mode::set_compact_mode,
+            storage::snapshot::get_conversation_snapshot,
+            storage::messages::get_activity_heatmap,
+            storage::recovery::get_data_recovery_report,
+            storage::search::search_messages,
+            crypto::generate_identity,
+            crypto::get_prekey_bundle,
+            crypto::start_session,
+            crypto::encrypt_message,
+            crypto::decrypt_message,
+            crypto::accept_identity_change,
+            crypto::get_contact_fingerprint,
+            crypto::trust::verify_contact,
+            crypto::trust::unverify_contact,
+            crypto::trust::get_attestations,
+            crypto::trust::import_attestations,
+            crypto::trust::get_trust_path,
+            analytics::get_usage_analytics_settings,
+            analytics::set_usage_analytics_settings,
+            analytics::get_weekly_summary,
+            secrets::store_secret,
+            secrets::get_secret,
+            secrets::delete_secret,
+            pairing::start_pairing,
+            pairing::cancel_pairing,
+            pairing::join_pairing,
+            whats_new::get_whats_new
+        ])
+        .on_page_load(|webview, payload| {
+            if webview.label() == "main" && payload.event() == PageLoadEvent::Finished {
+                whats_new::announce(webview.app_handle());
+            }
+        })
         .setup(|app| {
-            let window = app.handle().get_webview_window("main").unwrap();
-
-            // Position window near system tray (bottom-right on Windows)
-            #[cfg(target_os = "windows")]
-            {
-                let monitor = window
-                    .current_monitor()
-                    .expect("Failed to get current monitor")
-                    .expect("No monitor found");
-                let size = window.outer_size().expect("Failed to get window size");
-                let x = monitor.size().width as i32 - size.width as i32 - 10;
-                let y = monitor.size().height as i32 - size.height as i32 - 50;
-                window
-                    .set_position(Position::Physical(PhysicalPosition { x, y }))
-                    .expect("Failed to set window position on Windows");
+            // Before anything reads the store or the database
+            if let Err(e) = storage::recovery::check(app.handle()) {
+                log::error!("Startup data check failed: {}", e);
             }
 
-            #[cfg(target_os = "macos")]
-            {
-                window.center().expect("Failed to center window on macOS");
+            // ── Optional subsystems (gated by feature flags) ──────
+            let flags = features::FeatureFlags::load(app.handle());
+            if flags.is_enabled("autostart") {
+                app.handle()
+                    .plugin(tauri_plugin_autostart::Builder::new().build())?;
             }
+            if flags.is_enabled("global_shortcuts") {
+                app.handle()
+                    .plugin(tauri_plugin_global_shortcut::Builder::new().build())?;
+            }
+            let ipc_enabled = flags.is_enabled("local_ipc");
+            app.manage(flags);
+            whats_new::check(app.handle());
+            app.manage(clock::ClockSkew::load(app.handle()));
+            app.manage(unread::UnreadState::load(app.handle()));
+            app.manage(reminders::Reminders::load(app.handle()));
+            app.manage(timeline::ConnectionTimeline::load(app.handle()));
+            let db = storage::Database::open(app.handle()).or_else(|e| {
+                log::error!(
+                    "Failed to open the database, starting with an empty one: {}",
+                    e
+                );
+                storage::Database::in_memory()
+            })?;
+            app.manage(db);
+            reminders::start(app.handle());
+            presentation::start(app.handle());
+            notification_permission::start(app.handle());
+            analytics::start(app.handle());
+            backup::start(app.handle());
+            outbox::Outbox::start(app.handle());
+            favorites::register_all(app.handle());
+            if ipc_enabled {
+                ipc::start(app.handle());
+            }
+            deep_link::init(app.handle())?;
+            scheduler::start(app.handle());
+
+            let window = app.handle().get_webview_window("main").unwrap();
 
-            #[cfg(target_os = "linux")]
-            {
-                window
-                    .set_position(Position::Physical(PhysicalPosition { x: 100, y: 100 }))
-                    .expect("Failed to set window position on Linux");
+            // Back where the user left it, or next to the tray icon the
+            // first time
+            if !window_state::restore_main(&window) {
+                if let Err(e) = positioner::snap_to_tray(&window) {
+                    log::warn!("Failed to position window near the tray: {}", e);
+                }
             }
 
+            always_on_top::restore(&window);
+            compact_mode::restore(&window);
             window.show().expect("Failed to show window");
 
             // ── Prevent window close (hide instead) ───────────────
             let window_clone = window.clone();
-            window.on_window_event(move |event| {
-                if let tauri::WindowEvent::CloseRequested { api, .. } = event {
+            window.on_window_event(move |event| match event {
+                tauri::WindowEvent::CloseRequested { api, .. } => {
                     // Prevent the window from closing/exiting
                     api.prevent_close();
                     // Hide the window instead
                     window_clone.hide().ok();
                 }
+                tauri::WindowEvent::Focused(true) => {
+                    badge::stop_flash(window_clone.app_handle());
+                }
+                tauri::WindowEvent::Moved(_) | tauri::WindowEvent::Resized(_) => {
+                    window_state::save_main(&window_clone);
+                }
+                _ => {}
             });
 
             // ── System tray setup ──────────────────────────────────
-            let handle = app.handle().clone();
-
             // Build initial tray menu
             let open_item = MenuItem::with_id(app, "open", "Open Pester", true, None::<&str>)?;
             let sep1 = PredefinedMenuItem::separator(app)?;
             let new_contact_item =
                 MenuItem::with_id(app, "new_contact", "New Contact…", true, None::<&str>)?;
+            let pinned_item = CheckMenuItem::with_id(
+                app,
+                always_on_top::MENU_ID,
+                "Always on Top",
+                true,
+                always_on_top::main_pinned(app.handle()),
+                None::<&str>,
+            )?;
             let sep2 = PredefinedMenuItem::separator(app)?;
             let quit_item = MenuItem::with_id(app, "quit", "Quit", true, None::<&str>)?;
             let menu = Menu::with_items(
                 app,
-                &[&open_item, &sep1, &new_contact_item, &sep2, &quit_item],
+                &[
+                    &open_item,
+                    &sep1,
+                    &new_contact_item,
+                    &pinned_item,
+                    &sep2,
+                    &quit_item,
+                ],
             )?;
 
             if let Some(tray) = app.tray_by_id("main-tray") {
@@ -173,48 +594,49 @@ pub fn run() {
                     let id = event.id.as_ref();
                     match id {
                         "open" => {
-                            if let Some(w) = app_handle.get_webview_window("main") {
-                                let _ = w.unminimize();
-                                let _ = w.show();
-                                let _ = w.set_focus();
-                            }
+                            show_main_window(app_handle);
                         }
                         "quit" => {
                             app_handle.exit(0);
                         }
                         "new_contact" => {
-                            if let Some(w) = app_handle.get_webview_window("main") {
-                                let _ = w.unminimize();
-                                let _ = w.show();
-                                let _ = w.set_focus();
-                            }
+                            show_main_window(app_handle);
                             let _ = app_handle.emit("tray-action", "new_contact");
                         }
+                        always_on_top::MENU_ID => {
+                            let pinned = !always_on_top::main_pinned(app_handle);
+                            if let Err(e) = always_on_top::set(app_handle, "main", pinned) {
+                                log::warn!("Failed to toggle always-on-top: {}", e);
+                            }
+                        }
                         _ if id.starts_with("chat_") => {
                             let user_id = id.strip_prefix("chat_").unwrap_or("");
-                            if let Some(w) = app_handle.get_webview_window("main") {
-                                let _ = w.unminimize();
-                                let _ = w.show();
-                                let _ = w.set_focus();
+                            actions::open_chat(app_handle, user_id);
+                        }
+                        _ => {
+                            if let Some(status) = presence::Presence::from_menu_id(id) {
+                                // The webview rebuilds the menu with the new check
+                                if let Err(e) = badge::set_presence(app_handle, Some(status)) {
+                                    log::warn!("Failed to update tray status: {}", e);
+                                }
+                                let _ = app_handle.emit("status-changed", status);
                             }
-                            let _ = app_handle.emit("tray-action", format!("chat:{}", user_id));
                         }
-                        _ => {}
                     }
                 });
 
-                let handle_clone = handle.clone();
-                tray.on_tray_icon_event(move |_tray, event| {
-                    if let TrayIconEvent::Click { .. } = event {
-                        if let Some(w) = handle_clone.get_webview_window("main") {
-                            let _ = w.show();
-                        }
-                    }
-                });
+                tray_click::init(&tray);
+                badge::set_tray_tooltip(app.handle(), &app.state::<unread::UnreadState>().counts());
+                tray.on_tray_icon_event(tray_click::handle);
             }
 
             Ok(())
         })
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app, event| {
+            if let tauri::RunEvent::Exit = event {
+                storage::snapshot::save(app);
+            }
+        });
 }
@@ -0,0 +1,205 @@
+use std::cmp::Reverse;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use log::Level;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+
+const DEFAULT_LIMIT: usize = 200;
+const MAX_LIMIT: usize = 1000;
+
+/// One record from the log files.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogEntry {
+    pub level: String,
+    pub module: String,
+    /// Local time, `YYYY-MM-DD HH:MM:SS`.
+    pub timestamp: String,
+    /// Can span several lines.
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct LogFilter {
+    /// Least severe level to include, e.g. `warn` for warnings and errors.
+    pub level: Option<String>,
+    /// Only modules whose path contains this.
+    pub module: Option<String>,
+    /// Only messages containing this, ignoring case.
+    pub text: Option<String>,
+}
+
+/// Where a page left off: entries in `file` before byte `offset` are next.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogCursor {
+    pub file: String,
+    pub offset: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogPage {
+    /// Newest first.
+    pub entries: Vec<LogEntry>,
+    /// `None` once the oldest file is exhausted.
+    pub next_cursor: Option<LogCursor>,
+}
+
+/// `[2026-01-31][12:00:00][pester::connection][ERROR] message`, as the log
+/// plugin writes it. Lines that don't look like that continue the entry
+/// before them.
+fn parse_header(line: &str) -> Option<(String, String, String, &str)> {
+    let mut rest = line;
+    let mut fields = [""; 4];
+    for field in &mut fields {
+        let (value, tail) = rest.strip_prefix('[')?.split_once(']')?;
+        *field = value;
+        rest = tail;
+    }
+    let [date, time, module, level] = fields;
+    level.parse::<Level>().ok()?;
+    Some((
+        format!("{} {}", date, time),
+        module.to_string(),
+        level.to_string(),
+        rest.strip_prefix(' ').unwrap_or(rest),
+    ))
+}
+
+/// Entries in `bytes` with the byte offset each starts at, oldest first.
+fn parse(bytes: &[u8]) -> Vec<(usize, LogEntry)> {
+    let mut entries: Vec<(usize, LogEntry)> = Vec::new();
+    let mut offset = 0;
+    for line in bytes.split_inclusive(|&b| b == b'\n') {
+        let start = offset;
+        offset += line.len();
+        let line = String::from_utf8_lossy(line);
+        let line = line.trim_end_matches(['\n', '\r']);
+        match parse_header(line) {
+            Some((timestamp, module, level, message)) => entries.push((
+                start,
+                LogEntry {
+                    level,
+                    module,
+                    timestamp,
+                    message: message.to_string(),
+                },
+            )),
+            None => {
+                if let Some((_, last)) = entries.last_mut() {
+                    last.message.push('\n');
+                    last.message.push_str(line);
+                }
+            }
+        }
+    }
+    entries
+}
+
+impl LogFilter {
+    fn matches(&self, entry: &LogEntry, min_level: Option<Level>) -> bool {
+        // `Level` orders Error lowest, so "at least as severe" is `<=`
+        let level_ok =
+            min_level.is_none_or(|min| entry.level.parse::<Level>().is_ok_and(|l| l <= min));
+        let module_ok = self
+            .module
+            .as_deref()
+            .is_none_or(|m| entry.module.contains(m));
+        let text_ok = self
+            .text
+            .as_deref()
+            .is_none_or(|t| entry.message.to_lowercase().contains(&t.to_lowercase()));
+        level_ok && module_ok && text_ok
+    }
+}
+
+/// The log files, current one first and then rotated ones newest first.
+fn log_files(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut files: Vec<(SystemTime, PathBuf)> = entries
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            if path.extension()? != "log" {
+                return None;
+            }
+            let modified = path.metadata().and_then(|m| m.modified()).ok()?;
+            Some((modified, path))
+        })
+        .collect();
+    files.sort_by_key(|(modified, _)| Reverse(*modified));
+    files.into_iter().map(|(_, path)| path).collect()
+}
+
+/// Read a page of log entries, newest first, starting where `cursor`
+/// left off or at the latest entry without one.
+#[tauri::command]
+pub fn read_logs(
+    app: AppHandle,
+    filter: Option<LogFilter>,
+    cursor: Option<LogCursor>,
+    limit: Option<usize>,
+) -> Result<LogPage, String> {
+    let filter = filter.unwrap_or_default();
+    let min_level = match filter.level.as_deref() {
+        Some(level) => Some(level.parse::<Level>().map_err(|e| e.to_string())?),
+        None => None,
+    };
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+
+    let dir = app.path().app_log_dir().map_err(|e| e.to_string())?;
+    let files = log_files(&dir);
+    let name = |path: &Path| path.file_name().map(|n| n.to_string_lossy().into_owned());
+    let first = match &cursor {
+        // A file rotated away since simply ends the listing
+        Some(cursor) => match files
+            .iter()
+            .position(|f| name(f).as_deref() == Some(&cursor.file))
+        {
+            Some(index) => index,
+            None => {
+                return Ok(LogPage {
+                    entries: Vec::new(),
+                    next_cursor: None,
+                })
+            }
+        },
+        None => 0,
+    };
+
+    let mut entries = Vec::new();
+    for (index, path) in files.iter().enumerate().skip(first) {
+        let bytes = fs::read(path).map_err(|e| e.to_string())?;
+        let end = match &cursor {
+            Some(cursor) if index == first => (cursor.offset as usize).min(bytes.len()),
+            _ => bytes.len(),
+        };
+        // Where the entries not yet returned end
+        let mut boundary = end;
+        for (start, entry) in parse(&bytes[..end]).into_iter().rev() {
+            if filter.matches(&entry, min_level) {
+                if entries.len() == limit {
+                    return Ok(LogPage {
+                        entries,
+                        next_cursor: name(path).map(|file| LogCursor {
+                            file,
+                            offset: boundary as u64,
+                        }),
+                    });
+                }
+                entries.push(entry);
+            }
+            boundary = start;
+        }
+    }
+    Ok(LogPage {
+        entries,
+        next_cursor: None,
+    })
+}
@@ -0,0 +1,137 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+use crate::{clock, prefs};
+
+/// Private notes stay on this machine; nothing here is ever sent.
+const NOTES_KEY: &str = "conversation_notes";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChecklistItem {
+    pub id: u64,
+    pub text: String,
+    pub done: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ConversationNote {
+    /// Free-form markdown.
+    pub text: String,
+    pub checklist: Vec<ChecklistItem>,
+    pub updated_at: u64,
+}
+
+/// Conversation ID → note.
+pub type Notes = HashMap<String, ConversationNote>;
+
+/// Every note, for backups.
+pub fn all(app: &AppHandle) -> Notes {
+    prefs::load(app, NOTES_KEY).unwrap_or_default()
+}
+
+/// Take in notes from a backup, keeping whichever copy of each is newer.
+pub fn merge(app: &AppHandle, restored: Notes) -> Result<(), String> {
+    let mut notes = all(app);
+    for (conversation, note) in restored {
+        if notes
+            .get(&conversation)
+            .is_none_or(|local| local.updated_at < note.updated_at)
+        {
+            notes.insert(conversation, note);
+        }
+    }
+    prefs::save(app, NOTES_KEY, &notes)
+}
+
+/// Load, change and save the note for `conversation`. Notes left with no
+/// text and no checklist are dropped.
+fn edit<T>(
+    app: &AppHandle,
+    conversation: String,
+    apply: impl FnOnce(&mut ConversationNote) -> Result<T, String>,
+) -> Result<T, String> {
+    let mut notes: Notes = prefs::load(app, NOTES_KEY).unwrap_or_default();
+    let note = notes.entry(conversation.clone()).or_default();
+    let result = apply(note)?;
+    note.updated_at = clock::now_millis();
+    if note.text.trim().is_empty() && note.checklist.is_empty() {
+        notes.remove(&conversation);
+    }
+    prefs::save(app, NOTES_KEY, &notes)?;
+    Ok(result)
+}
+
+fn item_mut(note: &mut ConversationNote, id: u64) -> Result<&mut ChecklistItem, String> {
+    note.checklist
+        .iter_mut()
+        .find(|item| item.id == id)
+        .ok_or_else(|| format!("No checklist item with id {}", id))
+}
+
+#[tauri::command]
+pub fn get_conversation_note(app: AppHandle, conversation: String) -> ConversationNote {
+    let mut notes: Notes = prefs::load(&app, NOTES_KEY).unwrap_or_default();
+    notes.remove(&conversation).unwrap_or_default()
+}
+
+#[tauri::command]
+pub fn set_conversation_note(
+    app: AppHandle,
+    conversation: String,
+    text: String,
+) -> Result<(), String> {
+    edit(&app, conversation, |note| {
+        note.text = text;
+        Ok(())
+    })
+}
+
+/// Append an unchecked item and return it.
+#[tauri::command]
+pub fn add_checklist_item(
+    app: AppHandle,
+    conversation: String,
+    text: String,
+) -> Result<ChecklistItem, String> {
+    let text = text.trim().to_string();
+    if text.is_empty() {
+        return Err("Checklist item cannot be empty".to_string());
+    }
+    edit(&app, conversation, |note| {
+        let id = note.checklist.iter().map(|item| item.id).max().unwrap_or(0) + 1;
+        let item = ChecklistItem {
+            id,
+            text,
+            done: false,
+        };
+        note.checklist.push(item.clone());
+        Ok(item)
+    })
+}
+
+/// Flip an item between done and not done. Returns the new state.
+#[tauri::command]
+pub fn toggle_checklist_item(
+    app: AppHandle,
+    conversation: String,
+    id: u64,
+) -> Result<bool, String> {
+    edit(&app, conversation, |note| {
+        let item = item_mut(note, id)?;
+        item.done = !item.done;
+        Ok(item.done)
+    })
+}
+
+#[tauri::command]
+pub fn remove_checklist_item(app: AppHandle, conversation: String, id: u64) -> Result<(), String> {
+    edit(&app, conversation, |note| {
+        item_mut(note, id)?;
+        note.checklist.retain(|item| item.id != id);
+        Ok(())
+    })
+}
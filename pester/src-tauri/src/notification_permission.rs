@@ -0,0 +1,75 @@
+use std::sync::Mutex;
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::platform;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationPermission {
+    Granted,
+    /// Turned off for Pester in the OS settings, or by policy.
+    Denied,
+    /// Never asked yet (macOS).
+    #[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+    NotDetermined,
+    /// Couldn't tell, e.g. an unbundled dev build on macOS.
+    Unknown,
+}
+
+impl NotificationPermission {
+    /// Whether a toast would go nowhere. Unknown and not yet asked are
+    /// given the benefit of the doubt.
+    pub fn blocks_toasts(self) -> bool {
+        self == Self::Denied
+    }
+}
+
+/// Permission as last checked, read by the notification manager before
+/// every toast.
+pub struct PermissionState(Mutex<NotificationPermission>);
+
+impl Default for PermissionState {
+    fn default() -> Self {
+        Self(Mutex::new(NotificationPermission::Unknown))
+    }
+}
+
+impl PermissionState {
+    pub fn get(&self) -> NotificationPermission {
+        *self.0.lock().unwrap()
+    }
+}
+
+fn update(app: &AppHandle, permission: NotificationPermission) -> NotificationPermission {
+    let previous = std::mem::replace(
+        &mut *app.state::<PermissionState>().0.lock().unwrap(),
+        permission,
+    );
+    if previous != permission {
+        log::info!("Notification permission: {:?}", permission);
+        let _ = app.emit("notification-permission-changed", permission);
+    }
+    permission
+}
+
+/// Check the OS setting once the app is up.
+pub fn start(app: &AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        update(&app, platform::notification_permission(&app).await);
+    });
+}
+
+/// Re-checked on every call, since the user can change it in the OS at any
+/// time.
+#[tauri::command]
+pub async fn get_notification_permission(app: AppHandle) -> NotificationPermission {
+    update(&app, platform::notification_permission(&app).await)
+}
+
+#[tauri::command]
+pub async fn request_notification_permission(app: AppHandle) -> NotificationPermission {
+    update(&app, platform::request_notification_permission(&app).await)
+}
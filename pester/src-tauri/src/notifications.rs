@@ -0,0 +1,309 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use serde_json::json;
+use tauri::{AppHandle, Emitter, Manager, State};
+use tauri_plugin_notification::NotificationExt;
+
+use crate::audio::sounds::{self, SoundEvent};
+use crate::notification_permission::PermissionState;
+use crate::{actions, clock, conversations, dnd, focus, presentation::Presentation};
+
+/// How many notifications the journal remembers.
+const JOURNAL_CAPACITY: usize = 100;
+/// Messages within this long of a conversation's last toast update that
+/// toast instead of adding another.
+const GROUP_WINDOW: Duration = Duration::from_secs(60);
+/// How long a group waits for more messages before its toast is updated.
+const GROUP_DEBOUNCE: Duration = Duration::from_secs(3);
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JournalEntry {
+    pub title: String,
+    pub body: String,
+    pub timestamp: u64,
+    /// Whether a toast was actually shown for this entry.
+    pub shown: bool,
+    /// Why the toast was held back, if it was.
+    pub suppressed_by: Option<focus::OsFocusState>,
+    /// Held back for presenting and shown afterwards.
+    pub deferred: bool,
+    /// Silenced by Pester's own Do Not Disturb.
+    pub do_not_disturb: bool,
+    /// Shown as an in-app banner because the OS blocks Pester's toasts.
+    pub banner: bool,
+}
+
+/// Every notification Pester wanted to show, whether or not a toast made it
+/// to the screen, so nothing is lost while the OS is in a focus mode.
+#[derive(Default)]
+pub struct NotificationJournal {
+    entries: Mutex<VecDeque<JournalEntry>>,
+}
+
+impl NotificationJournal {
+    fn record(&self, entry: JournalEntry) {
+        let mut entries = self.entries.lock().unwrap();
+        if entries.len() == JOURNAL_CAPACITY {
+            entries.pop_front();
+        }
+        entries.push_back(entry);
+    }
+}
+
+// ── Grouping ────────────────────────────────────────────────────────────────
+
+struct Group {
+    sender: String,
+    /// Messages since the group started, including the first toast's.
+    count: u32,
+    /// When the group's toast was last shown.
+    updated: Instant,
+    flush_pending: bool,
+}
+
+/// Message toasts per conversation, so a burst of messages updates one
+/// toast ("3 new messages from Alice") instead of showing one each. On
+/// Windows the update replaces the earlier toast; elsewhere it follows it.
+#[derive(Default)]
+pub struct MessageGroups(Mutex<HashMap<String, Group>>);
+
+impl MessageGroups {
+    /// Start over once the conversation has been read.
+    pub fn forget(&self, conversation: &str) {
+        self.0.lock().unwrap().remove(conversation);
+    }
+}
+
+fn flush_group(app: &AppHandle, conversation: String) {
+    let (sender, count) = {
+        let groups = app.state::<MessageGroups>();
+        let mut groups = groups.0.lock().unwrap();
+        let Some(group) = groups.get_mut(&conversation) else {
+            return;
+        };
+        group.flush_pending = false;
+        group.updated = Instant::now();
+        (group.sender.clone(), group.count)
+    };
+    let body = format!("{} new messages from {}", count, sender);
+    if let Err(e) = notify_actionable(app, conversation, sender, body) {
+        log::warn!("Failed to update grouped notification: {}", e);
+    }
+}
+
+// ── Actions ─────────────────────────────────────────────────────────────────
+
+/// A notification the OS won't show, for the webview to show instead.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InAppNotification {
+    pub title: String,
+    pub body: String,
+}
+
+/// Typed into a message notification's reply field.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NotificationReply {
+    pub conversation: String,
+    pub text: String,
+}
+
+/// What the user did with a message notification.
+pub enum MessageAction {
+    Open,
+    Reply(String),
+    MarkRead,
+    /// Mute the conversation for a while. Only Windows toasts have room
+    /// for the button.
+    #[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+    Mute(Duration),
+}
+
+/// Act on a message notification for `conversation`. Replies go to the
+/// webview, which sends them like anything typed in the chat.
+pub fn handle_action(app: &AppHandle, conversation: &str, action: MessageAction) {
+    let args = json!({ "conversation": conversation });
+    let result = match action {
+        MessageAction::Open => actions::invoke(app, "open_chat", &args),
+        MessageAction::MarkRead => actions::invoke(app, "mark_read", &args),
+        MessageAction::Mute(duration) => {
+            let until = clock::now_millis() as i64 + duration.as_millis() as i64;
+            conversations::mute(app, conversation, Some(until)).map(|_| serde_json::Value::Null)
+        }
+        MessageAction::Reply(text) => {
+            let reply = NotificationReply {
+                conversation: conversation.to_string(),
+                text,
+            };
+            app.emit("notification-reply", &reply)
+                .map(|_| serde_json::Value::Null)
+                .map_err(|e| e.to_string())
+        }
+    };
+    if let Err(e) = result {
+        log::warn!("Failed to handle notification action: {}", e);
+    }
+}
+
+/// Show a toast unless the OS is in Focus / Do Not Disturb, Pester's own
+/// quiet hours or snooze is on, or the user is presenting. Returns whether
+/// the toast was shown; the notification is journaled either way, and
+/// toasts held back for presenting are shown once it ends.
+pub fn notify(app: &AppHandle, title: String, body: String) -> Result<bool, String> {
+    gate(app, title, body, |title, body| {
+        app.notification()
+            .builder()
+            .title(title)
+            .body(body)
+            .show()
+            .map_err(|e| e.to_string())
+    })
+}
+
+/// Like `notify`, but with a reply field and a "Mark read" button where the
+/// OS supports them (Windows and macOS), and the message sound if sounds
+/// are on. The toast itself is silent then.
+pub fn notify_actionable(
+    app: &AppHandle,
+    conversation: String,
+    title: String,
+    body: String,
+) -> Result<bool, String> {
+    let silent = sounds::sound_for(app, SoundEvent::Message, Some(&conversation)).is_some();
+    let shown = gate(app, title, body, |title, body| {
+        #[cfg(any(target_os = "windows", target_os = "macos"))]
+        {
+            crate::platform::show_message_notification(app, &conversation, title, body, silent)
+        }
+        #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+        {
+            app.notification()
+                .builder()
+                .title(title)
+                .body(body)
+                .show()
+                .map_err(|e| e.to_string())
+        }
+    })?;
+    if shown && silent {
+        sounds::play_event(app, SoundEvent::Message, Some(&conversation));
+    }
+    Ok(shown)
+}
+
+/// Run `show` unless Focus / Do Not Disturb, `dnd` or presenting holds the
+/// toast back, and journal it either way.
+fn gate(
+    app: &AppHandle,
+    title: String,
+    body: String,
+    show: impl FnOnce(&str, &str) -> Result<(), String>,
+) -> Result<bool, String> {
+    let focus_state = focus::query(app);
+    let presentation = app.state::<Presentation>();
+    let quiet = dnd::is_active(app);
+    let deferred = !quiet && presentation.refresh(app, focus_state);
+    let suppressed = quiet || deferred || focus_state.suppresses_toasts();
+    let banner = !suppressed && app.state::<PermissionState>().get().blocks_toasts();
+
+    if quiet {
+        log::debug!("Silencing toast, Do Not Disturb is on");
+    } else if deferred {
+        log::debug!("Deferring toast until presenting ends");
+        presentation.defer(title.clone(), body.clone());
+    } else if suppressed {
+        log::debug!("Suppressing toast, OS focus state is {:?}", focus_state);
+    } else if banner {
+        // A toast would be dropped silently, so show it in the app instead
+        log::debug!("Toasts are blocked by the OS, showing a banner");
+        let _ = app.emit(
+            "in-app-notification",
+            InAppNotification {
+                title: title.clone(),
+                body: body.clone(),
+            },
+        );
+    }
+    // Journaled even when the OS refuses the toast, as not shown
+    let result = if suppressed || banner {
+        Ok(())
+    } else {
+        show(&title, &body)
+    };
+    let shown = !suppressed && !banner && result.is_ok();
+
+    app.state::<NotificationJournal>().record(JournalEntry {
+        title,
+        body,
+        timestamp: clock::now_millis(),
+        shown,
+        suppressed_by: (suppressed && !deferred && !quiet).then_some(focus_state),
+        deferred,
+        do_not_disturb: quiet,
+        banner,
+    });
+
+    result.map(|_| shown)
+}
+
+#[tauri::command]
+pub fn show_notification(app: AppHandle, title: String, body: String) -> Result<bool, String> {
+    notify(&app, title, body)
+}
+
+/// Notify about a message from `conversation`, with inline reply and
+/// "Mark read" actions. Replies come back as `notification-reply` events.
+/// Rapid messages are grouped, see `MessageGroups`; those return `true`
+/// and show up in the group's next update.
+#[tauri::command]
+pub fn notify_message(
+    app: AppHandle,
+    groups: State<'_, MessageGroups>,
+    conversation: String,
+    title: String,
+    body: String,
+) -> Result<bool, String> {
+    if conversations::is_muted(&app, &conversation) {
+        log::debug!("Not notifying, {} is muted", conversation);
+        return Ok(false);
+    }
+    {
+        let mut groups = groups.0.lock().unwrap();
+        if let Some(group) = groups
+            .get_mut(&conversation)
+            .filter(|g| g.updated.elapsed() < GROUP_WINDOW)
+        {
+            group.count += 1;
+            group.sender = title;
+            if !group.flush_pending {
+                group.flush_pending = true;
+                let app = app.clone();
+                tauri::async_runtime::spawn(async move {
+                    tokio::time::sleep(GROUP_DEBOUNCE).await;
+                    flush_group(&app, conversation);
+                });
+            }
+            return Ok(true);
+        }
+        groups.insert(
+            conversation.clone(),
+            Group {
+                sender: title.clone(),
+                count: 1,
+                updated: Instant::now(),
+                flush_pending: false,
+            },
+        );
+    }
+    notify_actionable(&app, conversation, title, body)
+}
+
+#[tauri::command]
+pub fn get_notification_journal(journal: State<'_, NotificationJournal>) -> Vec<JournalEntry> {
+    journal.entries.lock().unwrap().iter().cloned().collect()
+}
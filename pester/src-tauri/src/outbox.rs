@@ -0,0 +1,833 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use rusqlite::{params, Connection, Row};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::audio::sounds::{self, SoundEvent};
+use crate::chunking;
+use crate::clock::{self, ClockSkew};
+use crate::connection::ConnectionManager;
+use crate::prefs;
+use crate::scheduler::{Priority, Scheduler};
+use crate::storage::messages::{self, StoredMessage};
+use crate::storage::Database;
+
+const SEND_DELAYS_KEY: &str = "send_delays";
+
+/// Longest undo window a conversation can have.
+const MAX_SEND_DELAY_SECS: u64 = 15;
+
+/// First retry delay for a queued message, doubled after every failure.
+const RETRY_BASE: Duration = Duration::from_secs(2);
+const RETRY_MAX: Duration = Duration::from_secs(5 * 60);
+/// Attempts before a queued message is given up on.
+const MAX_ATTEMPTS: u32 = 10;
+const QUEUE_CHECK_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Conversation ID → seconds a message waits before it goes out.
+type SendDelays = HashMap<String, u64>;
+
+/// A message waiting out its conversation's send delay.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PendingSend {
+    pub id: u64,
+    /// What it's stored in history as, once sent.
+    pub message_id: String,
+    pub conversation: String,
+    pub text: String,
+    /// When the message goes out, in local milliseconds.
+    pub send_at: u64,
+    /// Server time the message is stamped with in history.
+    pub timestamp: i64,
+    /// It couldn't go out yet and waits in the send queue.
+    pub queued: bool,
+    /// Never written to disk: not kept in history, and queued in memory
+    /// only if it can't go out.
+    pub incognito: bool,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SendOutcome {
+    Sent,
+    Cancelled,
+    /// Moved to the send queue to retry once connected.
+    Queued,
+    Failed,
+}
+
+/// Payload of `send-finalized`, emitted once per message when its window
+/// closes one way or another.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SendFinalized {
+    pub id: u64,
+    pub conversation: String,
+    pub outcome: SendOutcome,
+    pub error: Option<String>,
+}
+
+/// Payload of `send-progress`, as each part of a long message is written.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SendProgress {
+    pub id: u64,
+    pub conversation: String,
+    pub timestamp: i64,
+    pub sent: usize,
+    pub total: usize,
+}
+
+/// A message in the persistent send queue.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueuedSend {
+    pub id: u64,
+    pub message_id: String,
+    pub conversation: String,
+    /// Who sent it; only retried while signed in as them.
+    pub from_user_id: String,
+    pub text: String,
+    pub timestamp: i64,
+    pub attempts: u32,
+    /// Local milliseconds of the next try.
+    pub next_attempt_at: u64,
+    pub last_error: Option<String>,
+    pub incognito: bool,
+    #[serde(skip)]
+    partial: Option<PartialSend>,
+}
+
+/// How far a long message got before the connection dropped, so a retry
+/// picks up under the same group instead of starting another.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PartialSend {
+    parts: Vec<String>,
+    /// Parts written so far.
+    sent: usize,
+    /// Local milliseconds the last of them was written.
+    sent_at: u64,
+}
+
+impl PartialSend {
+    /// The first part to write: the first unsent one, or the first of all
+    /// if the receiver has given up on the rest by now.
+    fn resume_at(&self) -> usize {
+        let age = clock::now_millis().saturating_sub(self.sent_at);
+        if age < chunking::PARTIAL_TTL.as_millis() as u64 {
+            self.sent
+        } else {
+            0
+        }
+    }
+
+    fn to_json(partial: &Option<Self>) -> Option<String> {
+        partial.as_ref().and_then(|p| serde_json::to_string(p).ok())
+    }
+}
+
+/// Payload of `message-sent`, for every message once all of it is written
+/// to the socket.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MessageSent {
+    pub id: u64,
+    pub conversation: String,
+    pub timestamp: i64,
+}
+
+/// Payload of `message-failed`, for every failed attempt.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MessageFailed {
+    pub id: u64,
+    pub conversation: String,
+    pub error: String,
+    pub attempts: u32,
+    /// `false` once the message has been dropped from the queue.
+    pub will_retry: bool,
+}
+
+/// Holds outgoing messages during their undo window, and those that
+/// couldn't go out in the send queue. Nothing touches the network until
+/// the window closes, so cancelling is always clean.
+#[derive(Default)]
+pub struct Outbox {
+    pending: Mutex<HashMap<u64, PendingSend>>,
+    next_id: AtomicU64,
+    /// Held while the queue is flushed so no message goes out twice. A
+    /// tokio mutex, since flushes wait on the socket.
+    flushing: tokio::sync::Mutex<()>,
+    /// The send queue's incognito messages, kept out of the database. They
+    /// are lost if the app quits before they go out.
+    incognito_queue: Mutex<HashMap<u64, QueuedSend>>,
+}
+
+// ── Send queue ──────────────────────────────────────────────────────────────
+
+pub fn init(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS send_queue (
+             id              INTEGER PRIMARY KEY,
+             conversation_id TEXT NOT NULL,
+             from_user_id    TEXT NOT NULL,
+             text            TEXT NOT NULL,
+             timestamp       INTEGER NOT NULL,
+             attempts        INTEGER NOT NULL,
+             next_attempt_at INTEGER NOT NULL,
+             last_error      TEXT,
+             message_id      TEXT,
+             partial         TEXT
+         );",
+    )?;
+    // Queues from before these columns get them empty: such messages get
+    // an ID when retried and start over from the first part
+    for column in ["message_id", "partial"] {
+        if conn
+            .prepare(&format!("SELECT {} FROM send_queue", column))
+            .is_err()
+        {
+            conn.execute_batch(&format!(
+                "ALTER TABLE send_queue ADD COLUMN {} TEXT",
+                column
+            ))?;
+        }
+    }
+    Ok(())
+}
+
+fn queued_from_row(row: &Row) -> rusqlite::Result<QueuedSend> {
+    Ok(QueuedSend {
+        id: row.get(0)?,
+        conversation: row.get(1)?,
+        from_user_id: row.get(2)?,
+        text: row.get(3)?,
+        timestamp: row.get(4)?,
+        attempts: row.get(5)?,
+        next_attempt_at: row.get(6)?,
+        last_error: row.get(7)?,
+        message_id: row
+            .get::<_, Option<String>>(8)?
+            .unwrap_or_else(messages::new_id),
+        partial: row
+            .get::<_, Option<String>>(9)?
+            .and_then(|json| serde_json::from_str(&json).ok()),
+        incognito: false,
+    })
+}
+
+fn retry_delay(attempts: u32) -> Duration {
+    RETRY_BASE
+        .saturating_mul(2u32.saturating_pow(attempts.saturating_sub(1)))
+        .min(RETRY_MAX)
+}
+
+fn emit_sent(app: &AppHandle, id: u64, conversation: &str, timestamp: i64) {
+    let _ = app.emit(
+        "message-sent",
+        MessageSent {
+            id,
+            conversation: conversation.to_string(),
+            timestamp,
+        },
+    );
+}
+
+fn emit_failed(app: &AppHandle, queued: &QueuedSend, will_retry: bool) {
+    log::debug!(
+        "Send {} to {} failed (attempt {}): {:?}",
+        queued.id,
+        queued.conversation,
+        queued.attempts,
+        queued.last_error
+    );
+    let _ = app.emit(
+        "message-failed",
+        MessageFailed {
+            id: queued.id,
+            conversation: queued.conversation.clone(),
+            error: queued.last_error.clone().unwrap_or_default(),
+            attempts: queued.attempts,
+            will_retry,
+        },
+    );
+    if !will_retry {
+        sounds::play_event(app, SoundEvent::SendFailed, Some(&queued.conversation));
+    }
+}
+
+/// Keep a message whose first attempt failed, to retry after a backoff,
+/// or one that waits behind older queued messages (`error` is `None`).
+fn enqueue(
+    app: &AppHandle,
+    pending: &PendingSend,
+    error: Option<String>,
+    partial: Option<PartialSend>,
+) -> Result<(), String> {
+    let manager = app.state::<ConnectionManager>();
+    let outbox = app.state::<Outbox>();
+    let queued = QueuedSend {
+        id: pending.id,
+        message_id: pending.message_id.clone(),
+        conversation: pending.conversation.clone(),
+        from_user_id: manager.user_id().ok_or("Not signed in")?,
+        text: pending.text.clone(),
+        timestamp: pending.timestamp,
+        attempts: error.is_some().into(),
+        next_attempt_at: match error {
+            Some(_) => clock::now_millis() + retry_delay(1).as_millis() as u64,
+            None => clock::now_millis(),
+        },
+        last_error: error,
+        incognito: pending.incognito,
+        partial,
+    };
+    app.state::<Database>()
+        .with(|conn| outbox.store(conn, &queued))?;
+    if queued.last_error.is_some() {
+        emit_failed(app, &queued, true);
+    }
+    Ok(())
+}
+
+/// Retry one queued message, dropping it once sent or out of attempts.
+/// Returns whether it was sent.
+async fn retry(app: &AppHandle, db: &Database, mut queued: QueuedSend) -> Result<bool, String> {
+    let outbox = app.state::<Outbox>();
+    let pending = PendingSend {
+        id: queued.id,
+        message_id: queued.message_id.clone(),
+        conversation: queued.conversation.clone(),
+        text: queued.text.clone(),
+        send_at: clock::now_millis(),
+        timestamp: queued.timestamp,
+        queued: true,
+        incognito: queued.incognito,
+    };
+    let (parts, start) = match &queued.partial {
+        Some(partial) => (partial.parts.clone(), partial.resume_at()),
+        None => (chunking::split(&queued.text), 0),
+    };
+    let (error, sent) = match send_now(app, &pending, &parts, start).await {
+        Ok(()) => {
+            db.with(|conn| outbox.remove(conn, queued.id))?;
+            emit_sent(app, queued.id, &queued.conversation, queued.timestamp);
+            return Ok(true);
+        }
+        Err(e) => e,
+    };
+
+    if sent > start {
+        queued.partial = Some(PartialSend {
+            parts,
+            sent,
+            sent_at: clock::now_millis(),
+        });
+    }
+    queued.attempts += 1;
+    queued.last_error = Some(error);
+    queued.next_attempt_at = clock::now_millis() + retry_delay(queued.attempts).as_millis() as u64;
+    let will_retry = queued.attempts < MAX_ATTEMPTS;
+    db.with(|conn| {
+        if will_retry {
+            outbox.store(conn, &queued)
+        } else {
+            outbox.remove(conn, queued.id).map(|_| ())
+        }
+    })?;
+    emit_failed(app, &queued, will_retry);
+    Ok(false)
+}
+
+fn send_delay(app: &AppHandle, conversation: &str) -> u64 {
+    let delays: SendDelays = prefs::load(app, SEND_DELAYS_KEY).unwrap_or_default();
+    delays.get(conversation).copied().unwrap_or(0)
+}
+
+fn finalize(app: &AppHandle, pending: PendingSend, outcome: SendOutcome, error: Option<String>) {
+    log::debug!(
+        "Send {} to {}: {:?}",
+        pending.id,
+        pending.conversation,
+        outcome
+    );
+    let _ = app.emit(
+        "send-finalized",
+        SendFinalized {
+            id: pending.id,
+            conversation: pending.conversation,
+            outcome,
+            error,
+        },
+    );
+}
+
+/// Write `parts` of `pending` in turn from `start`, reporting progress for
+/// long messages. Fails with how many parts are written in all if the
+/// connection drops before the last one.
+async fn write_parts(
+    app: &AppHandle,
+    pending: &PendingSend,
+    parts: &[String],
+    start: usize,
+) -> Result<(), (String, usize)> {
+    let manager = app.state::<ConnectionManager>();
+    let mut progress = SendProgress {
+        id: pending.id,
+        conversation: pending.conversation.clone(),
+        timestamp: pending.timestamp,
+        sent: start,
+        total: parts.len(),
+    };
+    for part in &parts[start..] {
+        let written = manager
+            .send_text(&pending.conversation, part)
+            .map_err(|e| (e, progress.sent))?;
+        if written.await.is_err() {
+            return Err(("Connection lost while sending".to_string(), progress.sent));
+        }
+        progress.sent += 1;
+        if progress.total > 1 {
+            let _ = app.emit("send-progress", &progress);
+        }
+    }
+    Ok(())
+}
+
+/// Keep a copy of a sent message in history, unless it's incognito.
+fn record_sent(conn: &Connection, pending: &PendingSend, user_id: String) -> rusqlite::Result<()> {
+    if pending.incognito {
+        return Ok(());
+    }
+    messages::save(
+        conn,
+        &StoredMessage {
+            id: pending.message_id.clone(),
+            conversation_id: pending.conversation.clone(),
+            from_user_id: user_id,
+            text: pending.text.clone(),
+            timestamp: pending.timestamp,
+        },
+    )
+}
+
+/// Write to the socket, in parts if it's too long for one frame, and keep
+/// a copy in history once all of it is out.
+async fn send_now(
+    app: &AppHandle,
+    pending: &PendingSend,
+    parts: &[String],
+    start: usize,
+) -> Result<(), (String, usize)> {
+    write_parts(app, pending, parts, start).await?;
+    let user_id = app
+        .state::<ConnectionManager>()
+        .user_id()
+        .unwrap_or_default();
+    let recorded = app
+        .state::<Database>()
+        .with(|conn| record_sent(conn, pending, user_id));
+    if let Err(e) = recorded {
+        log::warn!("Failed to store message {}: {}", pending.message_id, e);
+    }
+    Ok(())
+}
+
+/// Send now, or move the message to the send queue if that fails, along
+/// with the parts already written. While older messages to the same
+/// conversation are queued, or a flush is running, it goes to the back of
+/// the queue instead so messages arrive in order.
+async fn deliver(app: &AppHandle, pending: &mut PendingSend) -> (SendOutcome, Option<String>) {
+    let outbox = app.state::<Outbox>();
+    let user_id = app
+        .state::<ConnectionManager>()
+        .user_id()
+        .unwrap_or_default();
+    // Held until sent, so a flush can't start on older messages meanwhile
+    let flushing = outbox.flushing.try_lock().ok();
+    let behind = flushing.is_none()
+        || app
+            .state::<Database>()
+            .with(|conn| outbox.has_queued(conn, &user_id, &pending.conversation))
+            .unwrap_or(true);
+    if behind {
+        drop(flushing);
+        return match enqueue(app, pending, None, None) {
+            Ok(()) => {
+                pending.queued = true;
+                let app = app.clone();
+                tauri::async_runtime::spawn(async move {
+                    app.state::<Outbox>().flush(&app).await;
+                });
+                (SendOutcome::Queued, None)
+            }
+            Err(e) => (SendOutcome::Failed, Some(e)),
+        };
+    }
+
+    let parts = chunking::split(&pending.text);
+    let (error, sent) = match send_now(app, pending, &parts, 0).await {
+        Ok(()) => {
+            emit_sent(app, pending.id, &pending.conversation, pending.timestamp);
+            return (SendOutcome::Sent, None);
+        }
+        Err(e) => e,
+    };
+    let partial = (sent > 0).then(|| PartialSend {
+        parts,
+        sent,
+        sent_at: clock::now_millis(),
+    });
+    match enqueue(app, pending, Some(error.clone()), partial) {
+        Ok(()) => {
+            pending.queued = true;
+            (SendOutcome::Queued, Some(error))
+        }
+        Err(e) => (SendOutcome::Failed, Some(e)),
+    }
+}
+
+impl Outbox {
+    /// Put `queued` in the send queue, or update it there. Incognito
+    /// messages stay in memory.
+    fn store(&self, conn: &Connection, queued: &QueuedSend) -> rusqlite::Result<()> {
+        if queued.incognito {
+            self.incognito_queue
+                .lock()
+                .unwrap()
+                .insert(queued.id, queued.clone());
+            return Ok(());
+        }
+        conn.execute(
+            "INSERT OR REPLACE INTO send_queue
+                 (id, conversation_id, from_user_id, text, timestamp, attempts,
+                  next_attempt_at, last_error, message_id, partial)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+            params![
+                queued.id,
+                queued.conversation,
+                queued.from_user_id,
+                queued.text,
+                queued.timestamp,
+                queued.attempts,
+                queued.next_attempt_at,
+                queued.last_error,
+                queued.message_id,
+                PartialSend::to_json(&queued.partial)
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Drop a message from the send queue. Returns whether it was there.
+    fn remove(&self, conn: &Connection, id: u64) -> rusqlite::Result<bool> {
+        if self.incognito_queue.lock().unwrap().remove(&id).is_some() {
+            return Ok(true);
+        }
+        conn.execute("DELETE FROM send_queue WHERE id = ?1", [id])
+            .map(|removed| removed > 0)
+    }
+
+    /// Whether `user_id` has messages to `conversation` in the send queue.
+    fn has_queued(
+        &self,
+        conn: &Connection,
+        user_id: &str,
+        conversation: &str,
+    ) -> rusqlite::Result<bool> {
+        Ok(self
+            .queued(conn)?
+            .iter()
+            .any(|q| q.from_user_id == user_id && q.conversation == conversation))
+    }
+
+    /// Everything in the send queue, oldest first.
+    fn queued(&self, conn: &Connection) -> rusqlite::Result<Vec<QueuedSend>> {
+        let mut stmt = conn.prepare(
+            "SELECT id, conversation_id, from_user_id, text, timestamp, attempts,
+                    next_attempt_at, last_error, message_id, partial
+             FROM send_queue",
+        )?;
+        let mut queued = stmt
+            .query_map([], queued_from_row)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        queued.extend(self.incognito_queue.lock().unwrap().values().cloned());
+        queued.sort_by_key(|q| q.id);
+        Ok(queued)
+    }
+
+    /// Continue numbering after messages left in the send queue and start
+    /// retrying them. Called from `setup` once the database is open.
+    pub fn start(app: &AppHandle) {
+        let max_id: Option<u64> = app
+            .state::<Database>()
+            .with(|conn| conn.query_row("SELECT MAX(id) FROM send_queue", [], |row| row.get(0)))
+            .unwrap_or_else(|e| {
+                log::warn!("Failed to read the send queue: {}", e);
+                None
+            });
+        if let Some(max_id) = max_id {
+            app.state::<Outbox>()
+                .next_id
+                .store(max_id + 1, Ordering::Relaxed);
+        }
+
+        app.state::<Scheduler>().register(
+            "send_queue",
+            Priority::High,
+            false,
+            QUEUE_CHECK_INTERVAL,
+            |app| async move { app.state::<Outbox>().flush(&app).await },
+        );
+    }
+
+    /// Retry queued messages that are due, oldest first. A message waits
+    /// while an older one to the same conversation is backing off or fails
+    /// again. Does nothing until registered; the connection calls this as
+    /// soon as it is.
+    pub async fn flush(&self, app: &AppHandle) {
+        let manager = app.state::<ConnectionManager>();
+        let Some(user_id) = manager.user_id().filter(|_| manager.is_registered()) else {
+            return;
+        };
+        let _flushing = self.flushing.lock().await;
+
+        let db = app.state::<Database>();
+        let queued = match db.with(|conn| self.queued(conn)) {
+            Ok(queued) => queued,
+            Err(e) => {
+                log::warn!("Failed to read the send queue: {}", e);
+                return;
+            }
+        };
+        let now = clock::now_millis();
+        let mut held_up = HashSet::new();
+        for queued in queued.into_iter().filter(|q| q.from_user_id == user_id) {
+            if held_up.contains(&queued.conversation) {
+                continue;
+            }
+            let conversation = queued.conversation.clone();
+            if queued.next_attempt_at > now {
+                held_up.insert(conversation);
+                continue;
+            }
+            match retry(app, &db, queued).await {
+                Ok(true) => {}
+                Ok(false) => {
+                    held_up.insert(conversation);
+                }
+                Err(e) => {
+                    log::warn!("Failed to update the send queue: {}", e);
+                    held_up.insert(conversation);
+                }
+            }
+        }
+    }
+
+    /// Queue `text` for `conversation`, sending it once the conversation's
+    /// send delay has passed (right away when there is none). Messages that
+    /// can't go out then wait in the send queue.
+    pub async fn submit(
+        &self,
+        app: &AppHandle,
+        conversation: String,
+        text: String,
+        incognito: bool,
+    ) -> Result<PendingSend, String> {
+        let delay = Duration::from_secs(send_delay(app, &conversation));
+        let mut pending = PendingSend {
+            id: self.next_id.fetch_add(1, Ordering::Relaxed),
+            message_id: messages::new_id(),
+            conversation,
+            text,
+            send_at: clock::now_millis() + delay.as_millis() as u64,
+            timestamp: app.state::<ClockSkew>().server_now(),
+            queued: false,
+            incognito,
+        };
+
+        if delay.is_zero() {
+            let (outcome, error) = deliver(app, &mut pending).await;
+            if let SendOutcome::Failed = outcome {
+                return Err(error.unwrap_or_default());
+            }
+            finalize(app, pending.clone(), outcome, error);
+            return Ok(pending);
+        }
+
+        self.pending
+            .lock()
+            .unwrap()
+            .insert(pending.id, pending.clone());
+        let app = app.clone();
+        let id = pending.id;
+        tauri::async_runtime::spawn(async move {
+            tokio::time::sleep(delay).await;
+            let due = app.state::<Outbox>().pending.lock().unwrap().remove(&id);
+            if let Some(mut pending) = due {
+                let (outcome, error) = deliver(&app, &mut pending).await;
+                finalize(&app, pending, outcome, error);
+            }
+        });
+        Ok(pending)
+    }
+}
+
+/// Abort a message still inside its undo window. Returns `false` if it
+/// already went out.
+#[tauri::command]
+pub fn cancel_pending_send(app: AppHandle, outbox: State<'_, Outbox>, id: u64) -> bool {
+    let Some(pending) = outbox.pending.lock().unwrap().remove(&id) else {
+        return false;
+    };
+    finalize(&app, pending, SendOutcome::Cancelled, None);
+    true
+}
+
+#[tauri::command]
+pub fn list_pending_sends(outbox: State<'_, Outbox>) -> Vec<PendingSend> {
+    let mut pending: Vec<PendingSend> = outbox.pending.lock().unwrap().values().cloned().collect();
+    pending.sort_by_key(|p| p.id);
+    pending
+}
+
+/// Messages waiting to be retried, oldest first.
+#[tauri::command]
+pub fn list_queued_sends(
+    outbox: State<'_, Outbox>,
+    db: State<'_, Database>,
+) -> Result<Vec<QueuedSend>, String> {
+    db.with(|conn| outbox.queued(conn))
+}
+
+/// Drop a queued message without sending it. Returns `false` if it
+/// wasn't queued.
+#[tauri::command]
+pub async fn discard_queued_send(
+    outbox: State<'_, Outbox>,
+    db: State<'_, Database>,
+    id: u64,
+) -> Result<bool, String> {
+    let _flushing = outbox.flushing.lock().await;
+    db.with(|conn| outbox.remove(conn, id))
+}
+
+#[tauri::command]
+pub fn get_send_delay(app: AppHandle, conversation: String) -> u64 {
+    send_delay(&app, &conversation)
+}
+
+/// Set how many seconds (0–15) messages to `conversation` can be undone.
+#[tauri::command]
+pub fn set_send_delay(app: AppHandle, conversation: String, seconds: u64) -> Result<(), String> {
+    if seconds > MAX_SEND_DELAY_SECS {
+        return Err(format!(
+            "Send delay must be at most {} seconds",
+            MAX_SEND_DELAY_SECS
+        ));
+    }
+    let mut delays: SendDelays = prefs::load(&app, SEND_DELAYS_KEY).unwrap_or_default();
+    if seconds == 0 {
+        delays.remove(&conversation);
+    } else {
+        delays.insert(conversation, seconds);
+    }
+    prefs::save(&app, SEND_DELAYS_KEY, &delays)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn database() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        messages::init(&conn).unwrap();
+        init(&conn).unwrap();
+        conn
+    }
+
+    fn pending(incognito: bool) -> PendingSend {
+        PendingSend {
+            id: 1,
+            message_id: messages::new_id(),
+            conversation: "bob".to_string(),
+            text: "for your eyes only".to_string(),
+            send_at: 0,
+            timestamp: 1000,
+            queued: false,
+            incognito,
+        }
+    }
+
+    fn queued(pending: &PendingSend) -> QueuedSend {
+        QueuedSend {
+            id: pending.id,
+            message_id: pending.message_id.clone(),
+            conversation: pending.conversation.clone(),
+            from_user_id: "alice".to_string(),
+            text: pending.text.clone(),
+            timestamp: pending.timestamp,
+            attempts: 1,
+            next_attempt_at: 0,
+            last_error: None,
+            incognito: pending.incognito,
+            partial: None,
+        }
+    }
+
+    fn count(conn: &Connection, table: &str) -> u32 {
+        conn.query_row(&format!("SELECT COUNT(*) FROM {}", table), [], |row| {
+            row.get(0)
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn sent_messages_are_kept_in_history() {
+        let conn = database();
+        record_sent(&conn, &pending(false), "alice".to_string()).unwrap();
+        assert_eq!(count(&conn, "messages"), 1);
+    }
+
+    #[test]
+    fn incognito_sends_leave_no_history() {
+        let conn = database();
+        record_sent(&conn, &pending(true), "alice".to_string()).unwrap();
+        assert_eq!(count(&conn, "messages"), 0);
+    }
+
+    #[test]
+    fn incognito_sends_are_queued_in_memory() {
+        let conn = database();
+        let outbox = Outbox::default();
+        let pending = pending(true);
+        outbox.store(&conn, &queued(&pending)).unwrap();
+        assert_eq!(count(&conn, "send_queue"), 0);
+        assert_eq!(outbox.queued(&conn).unwrap()[0].text, pending.text);
+
+        assert!(outbox.remove(&conn, pending.id).unwrap());
+        assert!(outbox.queued(&conn).unwrap().is_empty());
+    }
+
+    #[test]
+    fn new_messages_wait_behind_queued_ones() {
+        let conn = database();
+        let outbox = Outbox::default();
+        assert!(!outbox.has_queued(&conn, "alice", "bob").unwrap());
+
+        for incognito in [false, true] {
+            let queued = queued(&pending(incognito));
+            outbox.store(&conn, &queued).unwrap();
+            assert!(outbox.has_queued(&conn, "alice", "bob").unwrap());
+            assert!(!outbox.has_queued(&conn, "alice", "carol").unwrap());
+            assert!(!outbox.has_queued(&conn, "dave", "bob").unwrap());
+            outbox.remove(&conn, queued.id).unwrap();
+        }
+    }
+}
@@ -0,0 +1,449 @@
+use std::net::{Ipv4Addr, SocketAddr};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use chacha20poly1305::aead::{Aead, KeyInit};
+use chacha20poly1305::{ChaCha20Poly1305, Key as AeadKey, Nonce};
+use hkdf::Hkdf;
+use hmac::{Hmac, Mac};
+use rand_core::{OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::Sha256;
+use spake2::{Ed25519Group, Identity, Password, Spake2};
+use tauri::{AppHandle, Emitter, Manager, State};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream, UdpSocket};
+use tokio::time::{interval, timeout};
+
+use crate::features::FeatureFlags;
+use crate::storage::messages::{self, StoredMessage};
+use crate::storage::Database;
+
+/// Beacons from a device waiting to be paired go to this UDP port.
+const DISCOVERY_PORT: u16 = 47_811;
+const BEACON_INTERVAL: Duration = Duration::from_secs(1);
+const BEACON_TAG: &str = "pester-pairing";
+const PROTOCOL_VERSION: u8 = 1;
+
+/// How long a code stays valid, and how long a new device looks for one.
+const PAIRING_TIMEOUT: Duration = Duration::from_secs(120);
+/// Limit for each step once connected.
+const STEP_TIMEOUT: Duration = Duration::from_secs(30);
+
+const CODE_DIGITS: u32 = 6;
+/// Messages copied to the new device, newest first.
+const HISTORY_LIMIT: u32 = 5000;
+const MAX_FRAME: usize = 16 * 1024 * 1024;
+
+const HOST_ID: &[u8] = b"pester-existing-device";
+const JOINER_ID: &[u8] = b"pester-new-device";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PairingRole {
+    Existing,
+    New,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PairingStatus {
+    Waiting,
+    Verifying,
+    Transferring,
+    Done,
+    Failed,
+}
+
+/// Payload of `pairing-state`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PairingState {
+    pub role: PairingRole,
+    pub status: PairingStatus,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PairingCode {
+    /// Shown on this device and typed on the new one.
+    pub code: String,
+    pub expires_in_secs: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PairingSummary {
+    pub identity_imported: bool,
+    pub messages_imported: usize,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Beacon {
+    tag: String,
+    v: u8,
+    port: u16,
+}
+
+/// Sent once the code has been verified on both sides.
+#[derive(Serialize, Deserialize)]
+struct Transfer {
+    identity: Option<Value>,
+    messages: Vec<StoredMessage>,
+}
+
+/// The pairing this device is offering, if any. Only one at a time.
+#[derive(Default)]
+pub struct Pairing(Mutex<Option<tauri::async_runtime::JoinHandle<()>>>);
+
+impl Pairing {
+    fn replace(&self, task: Option<tauri::async_runtime::JoinHandle<()>>) {
+        if let Some(old) = std::mem::replace(&mut *self.0.lock().unwrap(), task) {
+            old.abort();
+        }
+    }
+}
+
+fn publish(app: &AppHandle, role: PairingRole, status: PairingStatus, error: Option<String>) {
+    log::debug!("Pairing ({:?}): {:?}", role, status);
+    let _ = app.emit(
+        "pairing-state",
+        PairingState {
+            role,
+            status,
+            error,
+        },
+    );
+}
+
+// ── Channel ─────────────────────────────────────────────────────────────────
+
+async fn write_frame(stream: &mut TcpStream, bytes: &[u8]) -> Result<(), String> {
+    let len = u32::try_from(bytes.len()).map_err(|_| "Frame too large")?;
+    stream
+        .write_all(&len.to_be_bytes())
+        .await
+        .map_err(|e| e.to_string())?;
+    stream.write_all(bytes).await.map_err(|e| e.to_string())
+}
+
+async fn read_frame(stream: &mut TcpStream) -> Result<Vec<u8>, String> {
+    let step = async {
+        let mut len = [0u8; 4];
+        stream
+            .read_exact(&mut len)
+            .await
+            .map_err(|e| e.to_string())?;
+        let len = u32::from_be_bytes(len) as usize;
+        if len > MAX_FRAME {
+            return Err("Frame too large".to_string());
+        }
+        let mut bytes = vec![0u8; len];
+        stream
+            .read_exact(&mut bytes)
+            .await
+            .map_err(|e| e.to_string())?;
+        Ok(bytes)
+    };
+    timeout(STEP_TIMEOUT, step)
+        .await
+        .map_err(|_| "The other device stopped responding".to_string())?
+}
+
+/// Keys derived from the PAKE secret: one confirmation and one encryption
+/// key per direction.
+struct ChannelKeys {
+    confirm_host: [u8; 32],
+    confirm_joiner: [u8; 32],
+    seal_host: [u8; 32],
+    seal_joiner: [u8; 32],
+}
+
+impl ChannelKeys {
+    fn derive(secret: &[u8]) -> Self {
+        let mut okm = [0u8; 128];
+        Hkdf::<Sha256>::new(None, secret)
+            .expand(b"Pester pairing", &mut okm)
+            .expect("128 bytes is a valid HKDF length");
+        let key = |i: usize| -> [u8; 32] { okm[i * 32..(i + 1) * 32].try_into().unwrap() };
+        Self {
+            confirm_host: key(0),
+            confirm_joiner: key(1),
+            seal_host: key(2),
+            seal_joiner: key(3),
+        }
+    }
+}
+
+/// Proof of the shared key over both PAKE messages, host's first.
+fn confirmation(key: &[u8; 32], transcript: &[u8]) -> Vec<u8> {
+    let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(key).expect("HMAC takes any key length");
+    mac.update(transcript);
+    mac.finalize().into_bytes().to_vec()
+}
+
+fn verify_confirmation(key: &[u8; 32], transcript: &[u8], proof: &[u8]) -> Result<(), String> {
+    let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(key).expect("HMAC takes any key length");
+    mac.update(transcript);
+    mac.verify_slice(proof)
+        .map_err(|_| "The pairing codes don't match".to_string())
+}
+
+/// Each key seals exactly one frame, so a fixed nonce is safe.
+fn seal(key: &[u8; 32], plaintext: &[u8]) -> Vec<u8> {
+    ChaCha20Poly1305::new(AeadKey::from_slice(key))
+        .encrypt(&Nonce::default(), plaintext)
+        .expect("ChaCha20-Poly1305 encryption does not fail")
+}
+
+fn open(key: &[u8; 32], ciphertext: &[u8]) -> Result<Vec<u8>, String> {
+    ChaCha20Poly1305::new(AeadKey::from_slice(key))
+        .decrypt(&Nonce::default(), ciphertext)
+        .map_err(|_| "Transfer could not be decrypted".to_string())
+}
+
+/// Run SPAKE2 and key confirmation. A wrong code fails here, having given
+/// the other side a single guess.
+async fn handshake(stream: &mut TcpStream, code: &str, host: bool) -> Result<ChannelKeys, String> {
+    let password = Password::new(code.as_bytes());
+    let (hello, ours) = if host {
+        Spake2::<Ed25519Group>::start_a(
+            &password,
+            &Identity::new(HOST_ID),
+            &Identity::new(JOINER_ID),
+        )
+    } else {
+        Spake2::<Ed25519Group>::start_b(
+            &password,
+            &Identity::new(HOST_ID),
+            &Identity::new(JOINER_ID),
+        )
+    };
+    write_frame(stream, &ours).await?;
+    let theirs = read_frame(stream).await?;
+    let secret = hello
+        .finish(&theirs)
+        .map_err(|_| "Malformed pairing message".to_string())?;
+    let keys = ChannelKeys::derive(&secret);
+
+    let transcript = if host {
+        [ours, theirs].concat()
+    } else {
+        [theirs, ours].concat()
+    };
+    let (mine, other) = if host {
+        (&keys.confirm_host, &keys.confirm_joiner)
+    } else {
+        (&keys.confirm_joiner, &keys.confirm_host)
+    };
+    write_frame(stream, &confirmation(mine, &transcript)).await?;
+    verify_confirmation(other, &transcript, &read_frame(stream).await?)?;
+    Ok(keys)
+}
+
+// ── Existing device ─────────────────────────────────────────────────────────
+
+fn new_code() -> String {
+    let code = OsRng.next_u32() % 10u32.pow(CODE_DIGITS);
+    format!("{:0width$}", code, width = CODE_DIGITS as usize)
+}
+
+async fn broadcast_beacons(port: u16) -> Result<(), String> {
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))
+        .await
+        .map_err(|e| e.to_string())?;
+    socket.set_broadcast(true).map_err(|e| e.to_string())?;
+    let beacon = serde_json::to_vec(&Beacon {
+        tag: BEACON_TAG.to_string(),
+        v: PROTOCOL_VERSION,
+        port,
+    })
+    .map_err(|e| e.to_string())?;
+
+    let mut ticks = interval(BEACON_INTERVAL);
+    loop {
+        ticks.tick().await;
+        if let Err(e) = socket
+            .send_to(&beacon, (Ipv4Addr::BROADCAST, DISCOVERY_PORT))
+            .await
+        {
+            log::debug!("Failed to send pairing beacon: {}", e);
+        }
+    }
+}
+
+async fn host(app: &AppHandle, code: &str) -> Result<(), String> {
+    let listener = TcpListener::bind((Ipv4Addr::UNSPECIFIED, 0))
+        .await
+        .map_err(|e| e.to_string())?;
+    let port = listener.local_addr().map_err(|e| e.to_string())?.port();
+    publish(app, PairingRole::Existing, PairingStatus::Waiting, None);
+
+    // Beacons stop once a device connects; the code is spent either way.
+    let (mut stream, peer) = tokio::select! {
+        accepted = timeout(PAIRING_TIMEOUT, listener.accept()) => accepted
+            .map_err(|_| "Pairing code expired".to_string())?
+            .map_err(|e| e.to_string())?,
+        Err(e) = broadcast_beacons(port) => return Err(e),
+    };
+    drop(listener);
+    log::debug!("Pairing connection from {}", peer);
+
+    publish(app, PairingRole::Existing, PairingStatus::Verifying, None);
+    let keys = handshake(&mut stream, code, true).await?;
+
+    publish(
+        app,
+        PairingRole::Existing,
+        PairingStatus::Transferring,
+        None,
+    );
+    let transfer = {
+        let db = app.state::<Database>();
+        let conn = db.lock();
+        Transfer {
+            identity: crate::crypto::export_identity(&conn)?,
+            messages: messages::recent(&conn, HISTORY_LIMIT).map_err(|e| e.to_string())?,
+        }
+    };
+    let json = serde_json::to_vec(&transfer).map_err(|e| e.to_string())?;
+    write_frame(&mut stream, &seal(&keys.seal_host, &json)).await?;
+
+    // Wait for the new device to confirm it stored everything.
+    open(&keys.seal_joiner, &read_frame(&mut stream).await?)?;
+    Ok(())
+}
+
+// ── New device ──────────────────────────────────────────────────────────────
+
+/// Wait for the first beacon on the LAN and return where to connect.
+async fn discover() -> Result<SocketAddr, String> {
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, DISCOVERY_PORT))
+        .await
+        .map_err(|e| e.to_string())?;
+    let search = async {
+        let mut buf = [0u8; 512];
+        loop {
+            let (len, from) = socket
+                .recv_from(&mut buf)
+                .await
+                .map_err(|e| e.to_string())?;
+            match serde_json::from_slice::<Beacon>(&buf[..len]) {
+                Ok(beacon) if beacon.tag == BEACON_TAG && beacon.v == PROTOCOL_VERSION => {
+                    return Ok(SocketAddr::new(from.ip(), beacon.port));
+                }
+                _ => continue,
+            }
+        }
+    };
+    timeout(PAIRING_TIMEOUT, search)
+        .await
+        .map_err(|_| "No device is waiting to pair on this network".to_string())?
+}
+
+async fn join(app: &AppHandle, code: &str) -> Result<PairingSummary, String> {
+    publish(app, PairingRole::New, PairingStatus::Waiting, None);
+    let addr = discover().await?;
+    let mut stream = timeout(STEP_TIMEOUT, TcpStream::connect(addr))
+        .await
+        .map_err(|_| "Could not reach the other device".to_string())?
+        .map_err(|e| e.to_string())?;
+
+    publish(app, PairingRole::New, PairingStatus::Verifying, None);
+    let keys = handshake(&mut stream, code, false).await?;
+
+    publish(app, PairingRole::New, PairingStatus::Transferring, None);
+    let json = open(&keys.seal_host, &read_frame(&mut stream).await?)?;
+    let transfer: Transfer = serde_json::from_slice(&json).map_err(|e| e.to_string())?;
+
+    let summary = {
+        let db = app.state::<Database>();
+        let conn = db.lock();
+        let tx = conn.unchecked_transaction().map_err(|e| e.to_string())?;
+        let identity_imported = transfer.identity.is_some();
+        if let Some(identity) = transfer.identity {
+            crate::crypto::import_identity(&tx, identity)?;
+        }
+        for message in &transfer.messages {
+            messages::save(&tx, message).map_err(|e| e.to_string())?;
+        }
+        tx.commit().map_err(|e| e.to_string())?;
+        PairingSummary {
+            identity_imported,
+            messages_imported: transfer.messages.len(),
+        }
+    };
+    write_frame(&mut stream, &seal(&keys.seal_joiner, b"ok")).await?;
+    Ok(summary)
+}
+
+/// Both sides open sockets on the LAN, so pairing can be switched off with
+/// the `lan_pairing` feature flag.
+fn check_enabled(app: &AppHandle) -> Result<(), String> {
+    if app.state::<FeatureFlags>().is_enabled("lan_pairing") {
+        Ok(())
+    } else {
+        Err("LAN pairing is turned off".into())
+    }
+}
+
+// ── Commands ────────────────────────────────────────────────────────────────
+
+/// Offer this device's identity and recent history to a new device on the
+/// LAN. The code is good for one attempt; `pairing-state` reports progress.
+#[tauri::command]
+pub fn start_pairing(app: AppHandle, pairing: State<'_, Pairing>) -> Result<PairingCode, String> {
+    check_enabled(&app)?;
+    let code = new_code();
+    let task = tauri::async_runtime::spawn({
+        let app = app.clone();
+        let code = code.clone();
+        async move {
+            match host(&app, &code).await {
+                Ok(()) => publish(&app, PairingRole::Existing, PairingStatus::Done, None),
+                Err(e) => {
+                    log::warn!("Pairing failed: {}", e);
+                    publish(&app, PairingRole::Existing, PairingStatus::Failed, Some(e));
+                }
+            }
+        }
+    });
+    pairing.replace(Some(task));
+    Ok(PairingCode {
+        code,
+        expires_in_secs: PAIRING_TIMEOUT.as_secs(),
+    })
+}
+
+#[tauri::command]
+pub fn cancel_pairing(pairing: State<'_, Pairing>) {
+    pairing.replace(None);
+}
+
+/// Pair with a device showing `code`, taking over its identity and
+/// recent history.
+#[tauri::command]
+pub async fn join_pairing(app: AppHandle, code: String) -> Result<PairingSummary, String> {
+    check_enabled(&app)?;
+    let code: String = code.chars().filter(|c| !c.is_whitespace()).collect();
+    if code.len() != CODE_DIGITS as usize || !code.chars().all(|c| c.is_ascii_digit()) {
+        return Err(format!("Pairing codes are {} digits", CODE_DIGITS));
+    }
+    match join(&app, &code).await {
+        Ok(summary) => {
+            publish(&app, PairingRole::New, PairingStatus::Done, None);
+            Ok(summary)
+        }
+        Err(e) => {
+            publish(
+                &app,
+                PairingRole::New,
+                PairingStatus::Failed,
+                Some(e.clone()),
+            );
+            Err(e)
+        }
+    }
+}
@@ -0,0 +1,125 @@
+use std::ptr::NonNull;
+use std::sync::Mutex;
+
+use block2::RcBlock;
+use mac_notification_sys::{MainButton, Notification, NotificationResponse};
+use objc2::runtime::Bool;
+use objc2_foundation::NSError;
+use objc2_user_notifications::{
+    UNAuthorizationOptions, UNAuthorizationStatus, UNNotificationSettings, UNUserNotificationCenter,
+};
+use tauri::AppHandle;
+use tokio::sync::oneshot;
+
+use crate::notification_permission::NotificationPermission;
+use crate::notifications::{self, MessageAction};
+
+/// Show a notification with a reply field and a "Mark read" button. The
+/// call blocks until the user acts on it, so it runs on its own thread.
+pub fn show_message_notification(
+    app: &AppHandle,
+    app_id: &str,
+    conversation: &str,
+    title: &str,
+    body: &str,
+    silent: bool,
+) -> Result<(), String> {
+    // Fails once the application is set, which is fine
+    let _ = mac_notification_sys::set_application(app_id);
+
+    let (app, conversation) = (app.clone(), conversation.to_string());
+    let (title, body) = (title.to_string(), body.to_string());
+    std::thread::spawn(move || {
+        let mut notification = Notification::new();
+        notification
+            .title(&title)
+            .message(&body)
+            .main_button(MainButton::Response("Reply"))
+            .close_button("Mark read");
+        if !silent {
+            notification.default_sound();
+        }
+        let response = notification.send();
+        let action = match response {
+            Ok(NotificationResponse::Reply(text)) => MessageAction::Reply(text),
+            Ok(NotificationResponse::CloseButton(_)) => MessageAction::MarkRead,
+            Ok(NotificationResponse::Click) => MessageAction::Open,
+            Ok(_) => return,
+            Err(e) => {
+                log::warn!("Failed to show message notification: {}", e);
+                return;
+            }
+        };
+        notifications::handle_action(&app, &conversation, action);
+    });
+    Ok(())
+}
+
+// ── Authorization ───────────────────────────────────────────────────────────
+
+/// The notification center aborts the process unless it runs from an app
+/// bundle, which `tauri dev` builds don't.
+fn in_app_bundle() -> bool {
+    std::env::current_exe()
+        .map(|exe| exe.to_string_lossy().contains(".app/Contents/MacOS/"))
+        .unwrap_or(false)
+}
+
+fn from_status(status: UNAuthorizationStatus) -> NotificationPermission {
+    match status {
+        UNAuthorizationStatus::Denied => NotificationPermission::Denied,
+        UNAuthorizationStatus::NotDetermined => NotificationPermission::NotDetermined,
+        _ => NotificationPermission::Granted,
+    }
+}
+
+/// What the user allowed in System Settings › Notifications.
+pub async fn notification_permission() -> NotificationPermission {
+    if !in_app_bundle() {
+        return NotificationPermission::Unknown;
+    }
+    let (tx, rx) = oneshot::channel();
+    let tx = Mutex::new(Some(tx));
+    // The block isn't `Send`, so it must be gone before awaiting
+    {
+        let handler = RcBlock::new(move |settings: NonNull<UNNotificationSettings>| {
+            // SAFETY: the settings object is valid for the duration of the call.
+            let status = unsafe { settings.as_ref() }.authorizationStatus();
+            if let Some(tx) = tx.lock().unwrap().take() {
+                let _ = tx.send(status);
+            }
+        });
+        UNUserNotificationCenter::currentNotificationCenter()
+            .getNotificationSettingsWithCompletionHandler(&handler);
+    }
+    rx.await
+        .map(from_status)
+        .unwrap_or(NotificationPermission::Unknown)
+}
+
+/// Ask for permission. macOS only prompts while it's undetermined; after
+/// that this just reports the answer.
+pub async fn request_notification_permission() -> NotificationPermission {
+    if !in_app_bundle() {
+        return NotificationPermission::Unknown;
+    }
+    let (tx, rx) = oneshot::channel();
+    let tx = Mutex::new(Some(tx));
+    {
+        let handler = RcBlock::new(move |granted: Bool, _error: *mut NSError| {
+            if let Some(tx) = tx.lock().unwrap().take() {
+                let _ = tx.send(granted.as_bool());
+            }
+        });
+        let options = UNAuthorizationOptions::Alert
+            | UNAuthorizationOptions::Sound
+            | UNAuthorizationOptions::Badge;
+        UNUserNotificationCenter::currentNotificationCenter()
+            .requestAuthorizationWithOptions_completionHandler(options, &handler);
+    }
+    match rx.await {
+        Ok(true) => NotificationPermission::Granted,
+        Ok(false) => NotificationPermission::Denied,
+        Err(_) => NotificationPermission::Unknown,
+    }
+}
@@ -0,0 +1,118 @@
+use tauri::AppHandle;
+
+use crate::notification_permission::NotificationPermission;
+
+#[cfg(target_os = "macos")]
+pub mod macos;
+#[cfg(target_os = "windows")]
+pub mod windows;
+
+/// The app ID toasts are shown under. Dev builds aren't registered with
+/// the OS, so they borrow a shell's, like the notification plugin does.
+#[cfg(any(target_os = "windows", target_os = "macos"))]
+fn notification_app_id(app: &AppHandle) -> String {
+    if tauri::is_dev() {
+        #[cfg(target_os = "windows")]
+        let id = r"{1AC14E77-02E7-4E5D-B744-2EB1AE5198B7}\WindowsPowerShell\v1.0\powershell.exe";
+        #[cfg(target_os = "macos")]
+        let id = "com.apple.Terminal";
+        id.to_string()
+    } else {
+        app.config().identifier.clone()
+    }
+}
+
+/// Show a message toast with a reply field and a "Mark read" button,
+/// without the OS sound when `silent`.
+#[cfg(any(target_os = "windows", target_os = "macos"))]
+pub fn show_message_notification(
+    app: &AppHandle,
+    conversation: &str,
+    title: &str,
+    body: &str,
+    silent: bool,
+) -> Result<(), String> {
+    let app_id = notification_app_id(app);
+    #[cfg(target_os = "windows")]
+    return windows::show_message_toast(app, &app_id, conversation, title, body, silent);
+    #[cfg(target_os = "macos")]
+    return macos::show_message_notification(app, &app_id, conversation, title, body, silent);
+}
+
+/// Whether the OS lets Pester show notifications.
+pub async fn notification_permission(app: &AppHandle) -> NotificationPermission {
+    #[cfg(target_os = "windows")]
+    {
+        windows::notification_permission(&notification_app_id(app))
+    }
+    #[cfg(target_os = "macos")]
+    {
+        let _ = app;
+        macos::notification_permission().await
+    }
+    // Notification daemons have no per-app permission to ask for
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    {
+        let _ = app;
+        NotificationPermission::Granted
+    }
+}
+
+/// Prompt for permission where the OS can (macOS). Windows has no prompt,
+/// so this opens its notification settings when toasts are turned off.
+pub async fn request_notification_permission(app: &AppHandle) -> NotificationPermission {
+    #[cfg(target_os = "windows")]
+    {
+        use tauri_plugin_opener::OpenerExt;
+
+        let permission = windows::notification_permission(&notification_app_id(app));
+        if permission == NotificationPermission::Denied {
+            if let Err(e) = app
+                .opener()
+                .open_url("ms-settings:notifications", None::<&str>)
+            {
+                log::warn!("Failed to open notification settings: {}", e);
+            }
+        }
+        permission
+    }
+    #[cfg(target_os = "macos")]
+    {
+        let _ = app;
+        macos::request_notification_permission().await
+    }
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    {
+        notification_permission(app).await
+    }
+}
+
+/// Show `count` as an overlay on the taskbar button; zero clears it.
+/// Windows only, a no-op elsewhere.
+#[tauri::command]
+pub fn set_taskbar_overlay(app: AppHandle, count: u32) -> Result<(), String> {
+    #[cfg(target_os = "windows")]
+    {
+        windows::set_overlay(&app, count)
+    }
+    #[cfg(not(target_os = "windows"))]
+    {
+        let _ = (app, count);
+        Ok(())
+    }
+}
+
+/// Flash the taskbar button `times` times, or until the window comes to
+/// the front when `None`. Windows only, a no-op elsewhere.
+#[tauri::command]
+pub fn flash_taskbar(app: AppHandle, times: Option<u32>) -> Result<(), String> {
+    #[cfg(target_os = "windows")]
+    {
+        windows::flash(&app, times)
+    }
+    #[cfg(not(target_os = "windows"))]
+    {
+        let _ = (app, times);
+        Ok(())
+    }
+}
@@ -0,0 +1,162 @@
+use std::time::Duration;
+
+use tauri::{AppHandle, Manager, WebviewWindow};
+use windows::core::{IInspectable, Interface, HSTRING};
+use windows::Data::Xml::Dom::XmlDocument;
+use windows::Foundation::{IReference, TypedEventHandler};
+use windows::UI::Notifications::{
+    NotificationSetting, ToastActivatedEventArgs, ToastNotification, ToastNotificationManager,
+};
+use windows_sys::Win32::UI::WindowsAndMessaging::{
+    FlashWindowEx, FLASHWINFO, FLASHW_TIMERNOFG, FLASHW_TRAY,
+};
+
+use crate::badge;
+use crate::notification_permission::NotificationPermission;
+use crate::notifications::{self, MessageAction};
+
+/// Overlay icons are drawn at 16 px; render at twice that for high DPI.
+const OVERLAY_SIZE: u32 = 32;
+/// Longest toast tag Windows accepts.
+const TOAST_TAG_CHARS: usize = 64;
+
+fn main_window(app: &AppHandle) -> Result<WebviewWindow, String> {
+    app.get_webview_window("main")
+        .ok_or_else(|| "Main window not found".to_string())
+}
+
+/// Badge the taskbar button through `ITaskbarList3::SetOverlayIcon`.
+pub fn set_overlay(app: &AppHandle, count: u32) -> Result<(), String> {
+    let icon = if count == 0 {
+        None
+    } else {
+        Some(badge::badge_icon(count, OVERLAY_SIZE)?)
+    };
+    main_window(app)?
+        .set_overlay_icon(icon)
+        .map_err(|e| e.to_string())
+}
+
+/// Flash the taskbar button with `FlashWindowEx`: `times` times, then it
+/// stays highlighted, or until the window comes to the front.
+pub fn flash(app: &AppHandle, times: Option<u32>) -> Result<(), String> {
+    let hwnd = main_window(app)?.hwnd().map_err(|e| e.to_string())?;
+    let info = FLASHWINFO {
+        cbSize: std::mem::size_of::<FLASHWINFO>() as u32,
+        hwnd: hwnd.0,
+        dwFlags: match times {
+            Some(_) => FLASHW_TRAY,
+            None => FLASHW_TRAY | FLASHW_TIMERNOFG,
+        },
+        uCount: times.unwrap_or(0),
+        dwTimeout: 0,
+    };
+    // SAFETY: `info` is fully initialised and `hwnd` is the live main window.
+    unsafe { FlashWindowEx(&info) };
+    Ok(())
+}
+
+// ── Actionable toasts ───────────────────────────────────────────────────────
+
+/// How long the toast's "Mute" button silences a conversation.
+const QUICK_MUTE: Duration = Duration::from_secs(60 * 60);
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+/// Which button was pressed, from the toast's activation arguments.
+fn activated_action(args: &ToastActivatedEventArgs) -> windows::core::Result<MessageAction> {
+    Ok(match args.Arguments()?.to_string().as_str() {
+        "reply" => {
+            let text = args
+                .UserInput()?
+                .Lookup(&HSTRING::from("reply"))?
+                .cast::<IReference<HSTRING>>()?
+                .Value()?;
+            MessageAction::Reply(text.to_string())
+        }
+        "read" => MessageAction::MarkRead,
+        "mute" => MessageAction::Mute(QUICK_MUTE),
+        _ => MessageAction::Open,
+    })
+}
+
+/// Show a toast with a reply box and "Send", "Mark read" and "Mute 1h"
+/// buttons. Only works while Pester runs; toasts left in Action Center just
+/// open the app.
+pub fn show_message_toast(
+    app: &AppHandle,
+    app_id: &str,
+    conversation: &str,
+    title: &str,
+    body: &str,
+    silent: bool,
+) -> Result<(), String> {
+    let xml = format!(
+        r#"<toast launch="open">
+            <visual>
+                <binding template="ToastGeneric">
+                    <text>{}</text>
+                    <text>{}</text>
+                </binding>
+            </visual>
+            <actions>
+                <input id="reply" type="text" placeHolderContent="Reply"/>
+                <action content="Send" arguments="reply" hint-inputId="reply"/>
+                <action content="Mark read" arguments="read"/>
+                <action content="Mute 1h" arguments="mute"/>
+            </actions>
+            <audio silent="{}"/>
+        </toast>"#,
+        escape_xml(title),
+        escape_xml(body),
+        silent
+    );
+
+    let show = || -> windows::core::Result<()> {
+        let doc = XmlDocument::new()?;
+        doc.LoadXml(&HSTRING::from(xml))?;
+        let toast = ToastNotification::CreateToastNotification(&doc)?;
+        // Same tag, same toast: a conversation's update replaces the last one
+        let tag: String = conversation.chars().take(TOAST_TAG_CHARS).collect();
+        toast.SetTag(&HSTRING::from(tag))?;
+        toast.SetGroup(&HSTRING::from("messages"))?;
+
+        let (app, conversation) = (app.clone(), conversation.to_string());
+        toast.Activated(&TypedEventHandler::<ToastNotification, IInspectable>::new(
+            move |_, args| {
+                if let Some(args) = args.as_ref() {
+                    let action = activated_action(&args.cast()?)?;
+                    notifications::handle_action(&app, &conversation, action);
+                }
+                Ok(())
+            },
+        ))?;
+
+        ToastNotificationManager::CreateToastNotifierWithId(&HSTRING::from(app_id))?.Show(&toast)
+    };
+    show().map_err(|e| e.to_string())
+}
+
+/// Whether toasts from `app_id` are turned on in Settings, or blocked by
+/// policy.
+pub fn notification_permission(app_id: &str) -> NotificationPermission {
+    let setting = ToastNotificationManager::CreateToastNotifierWithId(&HSTRING::from(app_id))
+        .and_then(|notifier| notifier.Setting());
+    match setting {
+        Ok(NotificationSetting::Enabled) => NotificationPermission::Granted,
+        Ok(setting) => {
+            log::debug!("Toasts are disabled: {:?}", setting);
+            NotificationPermission::Denied
+        }
+        Err(e) => {
+            log::debug!("Failed to read the notification setting: {}", e);
+            NotificationPermission::Unknown
+        }
+    }
+}
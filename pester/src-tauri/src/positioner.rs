@@ -0,0 +1,189 @@
+use std::sync::Mutex;
+
+use tauri::tray::TrayIconEvent;
+use tauri::{AppHandle, Manager, Monitor, PhysicalPosition, Position, Rect, WebviewWindow};
+
+const TRAY_ID: &str = "main-tray";
+/// Gap between the window and the taskbar or screen edge.
+const MARGIN: i32 = 8;
+
+/// Where the tray icon was last seen, from its events, for platforms that
+/// can't tell us on demand.
+#[derive(Default)]
+pub struct TrayRect(Mutex<Option<Rect>>);
+
+/// Physical pixels.
+#[derive(Debug, Clone, Copy)]
+struct Bounds {
+    x: i32,
+    y: i32,
+    width: i32,
+    height: i32,
+}
+
+impl Bounds {
+    fn right(&self) -> i32 {
+        self.x + self.width
+    }
+
+    fn bottom(&self) -> i32 {
+        self.y + self.height
+    }
+
+    fn center(&self) -> (i32, i32) {
+        (self.x + self.width / 2, self.y + self.height / 2)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Edge {
+    Top,
+    Bottom,
+    Left,
+    Right,
+}
+
+/// Note where the tray icon is. Called for every tray event.
+pub fn remember(app: &AppHandle, event: &TrayIconEvent) {
+    let rect = match event {
+        TrayIconEvent::Click { rect, .. }
+        | TrayIconEvent::DoubleClick { rect, .. }
+        | TrayIconEvent::Enter { rect, .. }
+        | TrayIconEvent::Move { rect, .. }
+        | TrayIconEvent::Leave { rect, .. } => *rect,
+        _ => return,
+    };
+    *app.state::<TrayRect>().0.lock().unwrap() = Some(rect);
+}
+
+fn tray_bounds(app: &AppHandle, scale: f64) -> Option<Bounds> {
+    let rect = app
+        .tray_by_id(TRAY_ID)
+        .and_then(|tray| tray.rect().ok().flatten())
+        .or_else(|| *app.state::<TrayRect>().0.lock().unwrap())?;
+    let position = rect.position.to_physical::<i32>(scale);
+    let size = rect.size.to_physical::<u32>(scale);
+    // An empty rect means the platform doesn't know
+    if size.width == 0 && size.height == 0 {
+        return None;
+    }
+    Some(Bounds {
+        x: position.x,
+        y: position.y,
+        width: size.width as i32,
+        height: size.height as i32,
+    })
+}
+
+/// The taskbar edge: the one the work area is cut short on, checking the
+/// top first for the macOS menu bar. With an auto-hiding taskbar the work
+/// area is the whole monitor, so it's the edge nearest the tray icon, and
+/// the bottom when that isn't known either.
+fn edge(monitor: &Bounds, work: &Bounds, tray: Option<&Bounds>) -> Edge {
+    if work.y > monitor.y {
+        return Edge::Top;
+    }
+    if work.bottom() < monitor.bottom() {
+        return Edge::Bottom;
+    }
+    if work.x > monitor.x {
+        return Edge::Left;
+    }
+    if work.right() < monitor.right() {
+        return Edge::Right;
+    }
+    let Some(tray) = tray else {
+        return Edge::Bottom;
+    };
+    let (x, y) = tray.center();
+    [
+        (y - monitor.y, Edge::Top),
+        (monitor.bottom() - y, Edge::Bottom),
+        (x - monitor.x, Edge::Left),
+        (monitor.right() - x, Edge::Right),
+    ]
+    .into_iter()
+    .min_by_key(|(distance, _)| *distance)
+    .map_or(Edge::Bottom, |(_, edge)| edge)
+}
+
+/// Top-left corner for a `width` × `height` window against `edge` of the
+/// work area, lined up with the tray icon, or at the far end without one.
+fn place(work: &Bounds, edge: Edge, tray: Option<&Bounds>, width: i32, height: i32) -> (i32, i32) {
+    let (tray_x, tray_y) = tray.map_or((work.right(), work.bottom()), Bounds::center);
+    let clamp_x = |x: i32| {
+        x.clamp(
+            work.x + MARGIN,
+            (work.right() - width - MARGIN).max(work.x + MARGIN),
+        )
+    };
+    let clamp_y = |y: i32| {
+        y.clamp(
+            work.y + MARGIN,
+            (work.bottom() - height - MARGIN).max(work.y + MARGIN),
+        )
+    };
+    match edge {
+        Edge::Top => (clamp_x(tray_x - width / 2), work.y + MARGIN),
+        Edge::Bottom => (clamp_x(tray_x - width / 2), work.bottom() - height - MARGIN),
+        Edge::Left => (work.x + MARGIN, clamp_y(tray_y - height / 2)),
+        Edge::Right => (work.right() - width - MARGIN, clamp_y(tray_y - height / 2)),
+    }
+}
+
+fn monitor_for(window: &WebviewWindow, tray: Option<&Bounds>) -> Result<Monitor, String> {
+    let on_tray = match tray {
+        Some(tray) => {
+            let (x, y) = tray.center();
+            window
+                .monitor_from_point(x as f64, y as f64)
+                .map_err(|e| e.to_string())?
+        }
+        None => None,
+    };
+    match on_tray {
+        Some(monitor) => Ok(monitor),
+        None => match window.current_monitor().map_err(|e| e.to_string())? {
+            Some(monitor) => Ok(monitor),
+            None => window
+                .primary_monitor()
+                .map_err(|e| e.to_string())?
+                .ok_or_else(|| "No monitor found".to_string()),
+        },
+    }
+}
+
+/// Move `window` next to the tray icon, inside the work area of the
+/// monitor the tray is on and clear of the taskbar wherever it sits.
+pub fn snap_to_tray(window: &WebviewWindow) -> Result<(), String> {
+    let scale = window.scale_factor().map_err(|e| e.to_string())?;
+    let tray = tray_bounds(window.app_handle(), scale);
+    let monitor = monitor_for(window, tray.as_ref())?;
+    let monitor_bounds = Bounds {
+        x: monitor.position().x,
+        y: monitor.position().y,
+        width: monitor.size().width as i32,
+        height: monitor.size().height as i32,
+    };
+    let work_area = monitor.work_area();
+    let work = Bounds {
+        x: work_area.position.x,
+        y: work_area.position.y,
+        width: work_area.size.width as i32,
+        height: work_area.size.height as i32,
+    };
+    let size = window.outer_size().map_err(|e| e.to_string())?;
+
+    let edge = edge(&monitor_bounds, &work, tray.as_ref());
+    let (x, y) = place(
+        &work,
+        edge,
+        tray.as_ref(),
+        size.width as i32,
+        size.height as i32,
+    );
+    log::debug!("Snapping to the tray at ({}, {}), taskbar {:?}", x, y, edge);
+    window
+        .set_position(Position::Physical(PhysicalPosition { x, y }))
+        .map_err(|e| e.to_string())
+}
@@ -0,0 +1,83 @@
+use serde::Serialize;
+
+/// Where the machine is drawing power from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PowerSource {
+    Ac,
+    Battery,
+    /// Desktops without a battery, or platforms we can't ask.
+    Unknown,
+}
+
+// ── Windows: GetSystemPowerStatus ───────────────────────────────────────────
+
+#[cfg(target_os = "windows")]
+pub fn source() -> PowerSource {
+    use windows_sys::Win32::System::Power::{GetSystemPowerStatus, SYSTEM_POWER_STATUS};
+
+    // SAFETY: SYSTEM_POWER_STATUS is plain data, all-zero is a valid value.
+    let mut status: SYSTEM_POWER_STATUS = unsafe { std::mem::zeroed() };
+    // SAFETY: `status` is a valid out pointer for the duration of the call.
+    if unsafe { GetSystemPowerStatus(&mut status) } == 0 {
+        return PowerSource::Unknown;
+    }
+    match status.ACLineStatus {
+        0 => PowerSource::Battery,
+        1 => PowerSource::Ac,
+        _ => PowerSource::Unknown,
+    }
+}
+
+// ── macOS: pmset ────────────────────────────────────────────────────────────
+
+#[cfg(target_os = "macos")]
+pub fn source() -> PowerSource {
+    // First line reads "Now drawing from 'AC Power'" or "'Battery Power'".
+    let Ok(output) = std::process::Command::new("pmset")
+        .args(["-g", "ps"])
+        .output()
+    else {
+        return PowerSource::Unknown;
+    };
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let first = stdout.lines().next().unwrap_or_default();
+    if first.contains("Battery Power") {
+        PowerSource::Battery
+    } else if first.contains("AC Power") {
+        PowerSource::Ac
+    } else {
+        PowerSource::Unknown
+    }
+}
+
+// ── Everything else: sysfs power supplies ───────────────────────────────────
+
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+pub fn source() -> PowerSource {
+    let Ok(entries) = std::fs::read_dir("/sys/class/power_supply") else {
+        return PowerSource::Unknown;
+    };
+    let read = |path: std::path::PathBuf| {
+        std::fs::read_to_string(path)
+            .map(|s| s.trim().to_string())
+            .unwrap_or_default()
+    };
+
+    let mut saw_mains = false;
+    for entry in entries.flatten() {
+        let dir = entry.path();
+        if read(dir.join("type")) != "Mains" {
+            continue;
+        }
+        saw_mains = true;
+        if read(dir.join("online")) == "1" {
+            return PowerSource::Ac;
+        }
+    }
+    if saw_mains {
+        PowerSource::Battery
+    } else {
+        PowerSource::Unknown
+    }
+}
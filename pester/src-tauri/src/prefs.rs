@@ -0,0 +1,31 @@
+use serde::{de::DeserializeOwned, Serialize};
+use tauri::{AppHandle, Runtime};
+use tauri_plugin_store::StoreExt;
+
+/// The same store file the frontend uses through `LazyStore`.
+pub const STORE_FILE: &str = "pester-data.json";
+
+/// Read a backend-owned value from the store, falling back to `None` when the
+/// key is missing or no longer matches the expected shape.
+pub fn load<R: Runtime, T: DeserializeOwned>(app: &AppHandle<R>, key: &str) -> Option<T> {
+    let store = app.store(STORE_FILE).ok()?;
+    let value = store.get(key)?;
+    match serde_json::from_value(value) {
+        Ok(v) => Some(v),
+        Err(e) => {
+            log::warn!("Ignoring malformed store value for {}: {}", key, e);
+            None
+        }
+    }
+}
+
+pub fn save<R: Runtime, T: Serialize>(
+    app: &AppHandle<R>,
+    key: &str,
+    value: &T,
+) -> Result<(), String> {
+    let store = app.store(STORE_FILE).map_err(|e| e.to_string())?;
+    let json = serde_json::to_value(value).map_err(|e| e.to_string())?;
+    store.set(key, json);
+    store.save().map_err(|e| e.to_string())
+}
@@ -0,0 +1,60 @@
+use serde::{Deserialize, Serialize};
+
+/// Menu item IDs for the tray's status submenu start with this.
+const MENU_PREFIX: &str = "status_";
+
+/// The status the user shows to others, picked in the app or the tray.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Presence {
+    Online,
+    Away,
+    DoNotDisturb,
+    Invisible,
+}
+
+impl Presence {
+    pub const ALL: [Presence; 4] = [
+        Presence::Online,
+        Presence::Away,
+        Presence::DoNotDisturb,
+        Presence::Invisible,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Presence::Online => "Online",
+            Presence::Away => "Away",
+            Presence::DoNotDisturb => "Do Not Disturb",
+            Presence::Invisible => "Invisible",
+        }
+    }
+
+    /// Color of the status dot on the tray icon.
+    pub fn color(self) -> (u8, u8, u8) {
+        match self {
+            Presence::Online => (0x30, 0xA4, 0x6C),
+            Presence::Away => (0xF5, 0xA5, 0x24),
+            Presence::DoNotDisturb => (0xE5, 0x48, 0x4D),
+            Presence::Invisible => (0x8B, 0x8D, 0x98),
+        }
+    }
+
+    pub fn menu_id(self) -> String {
+        format!("{}{}", MENU_PREFIX, self.key())
+    }
+
+    pub fn from_menu_id(id: &str) -> Option<Presence> {
+        let key = id.strip_prefix(MENU_PREFIX)?;
+        Self::ALL.into_iter().find(|p| p.key() == key)
+    }
+
+    fn key(self) -> &'static str {
+        match self {
+            Presence::Online => "online",
+            Presence::Away => "away",
+            Presence::DoNotDisturb => "do_not_disturb",
+            Presence::Invisible => "invisible",
+        }
+    }
+}
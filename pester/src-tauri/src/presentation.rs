@@ -0,0 +1,213 @@
+use std::sync::Mutex;
+use std::time::Duration;
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager, State};
+use tauri_plugin_notification::NotificationExt;
+
+use crate::focus::{self, OsFocusState};
+use crate::scheduler::{Priority, Scheduler};
+
+/// How often presenting is re-checked so deferred toasts go out soon after.
+const POLL_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Processes that only run while the screen is being shared or broadcast:
+/// Zoom's share host and OBS.
+const SCREEN_SHARE_PROCESSES: &[&str] = &["cpthost", "obs", "obs64"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PresentingReason {
+    /// The OS reports presentation mode.
+    Os,
+    /// A screen sharing or broadcasting app is running.
+    ScreenShare,
+    /// The user switched presenting on by hand.
+    Manual,
+}
+
+/// Payload of `presentation-detected` and result of `get_presentation_state`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PresentationStatus {
+    pub presenting: bool,
+    pub reason: Option<PresentingReason>,
+    /// Manual override, `None` when detection is automatic.
+    pub manual: Option<bool>,
+    /// Toasts held back until presenting ends.
+    pub deferred: usize,
+}
+
+/// Tracks whether the user is presenting and holds back toasts meanwhile.
+#[derive(Default)]
+pub struct Presentation {
+    manual: Mutex<Option<bool>>,
+    reason: Mutex<Option<PresentingReason>>,
+    deferred: Mutex<Vec<(String, String)>>,
+}
+
+impl Presentation {
+    fn detect(&self, focus_state: OsFocusState) -> Option<PresentingReason> {
+        match *self.manual.lock().unwrap() {
+            Some(true) => return Some(PresentingReason::Manual),
+            Some(false) => return None,
+            None => {}
+        }
+        if focus_state == OsFocusState::Presentation {
+            Some(PresentingReason::Os)
+        } else if screen_share_running() {
+            Some(PresentingReason::ScreenShare)
+        } else {
+            None
+        }
+    }
+
+    /// Re-check presenting, emitting `presentation-detected` on changes and
+    /// releasing deferred toasts once it ends. Returns whether presenting.
+    pub fn refresh(&self, app: &AppHandle, focus_state: OsFocusState) -> bool {
+        let reason = self.detect(focus_state);
+        let previous = std::mem::replace(&mut *self.reason.lock().unwrap(), reason);
+        if previous != reason {
+            log::debug!("Presenting: {:?} → {:?}", previous, reason);
+            let _ = app.emit("presentation-detected", self.status());
+            if reason.is_none() {
+                self.flush(app);
+            }
+        }
+        reason.is_some()
+    }
+
+    pub fn defer(&self, title: String, body: String) {
+        self.deferred.lock().unwrap().push((title, body));
+    }
+
+    /// Show what piled up while presenting: the toast itself if there was
+    /// only one, a summary otherwise. Everything is in the journal already.
+    fn flush(&self, app: &AppHandle) {
+        let deferred = std::mem::take(&mut *self.deferred.lock().unwrap());
+        let (title, body) = match deferred.len() {
+            0 => return,
+            1 => deferred.into_iter().next().unwrap(),
+            n => (
+                "Pester".to_string(),
+                format!("{} notifications arrived while you were presenting", n),
+            ),
+        };
+        if let Err(e) = app.notification().builder().title(title).body(body).show() {
+            log::warn!("Failed to show deferred notifications: {}", e);
+        }
+    }
+
+    fn status(&self) -> PresentationStatus {
+        let reason = *self.reason.lock().unwrap();
+        PresentationStatus {
+            presenting: reason.is_some(),
+            reason,
+            manual: *self.manual.lock().unwrap(),
+            deferred: self.deferred.lock().unwrap().len(),
+        }
+    }
+}
+
+/// Register the job that notices when presenting ends. Called from `setup`.
+pub fn start(app: &AppHandle) {
+    app.state::<Scheduler>().register(
+        "presentation",
+        Priority::High,
+        false,
+        POLL_INTERVAL,
+        |app| async move {
+            let focus_state = focus::query(&app);
+            app.state::<Presentation>().refresh(&app, focus_state);
+        },
+    );
+}
+
+fn screen_share_running() -> bool {
+    process_names().iter().any(|name| {
+        let name = name.to_ascii_lowercase();
+        let name = name.strip_suffix(".exe").unwrap_or(&name);
+        SCREEN_SHARE_PROCESSES.contains(&name)
+    })
+}
+
+// ── Windows: Toolhelp snapshot ──────────────────────────────────────────────
+
+#[cfg(target_os = "windows")]
+fn process_names() -> Vec<String> {
+    use windows_sys::Win32::Foundation::{CloseHandle, INVALID_HANDLE_VALUE};
+    use windows_sys::Win32::System::Diagnostics::ToolHelp::{
+        CreateToolhelp32Snapshot, Process32FirstW, Process32NextW, PROCESSENTRY32W,
+        TH32CS_SNAPPROCESS,
+    };
+
+    let mut names = Vec::new();
+    // SAFETY: the snapshot handle is checked before use and closed below.
+    unsafe {
+        let snapshot = CreateToolhelp32Snapshot(TH32CS_SNAPPROCESS, 0);
+        if snapshot == INVALID_HANDLE_VALUE {
+            return names;
+        }
+        let mut entry: PROCESSENTRY32W = std::mem::zeroed();
+        entry.dwSize = std::mem::size_of::<PROCESSENTRY32W>() as u32;
+        let mut ok = Process32FirstW(snapshot, &mut entry);
+        while ok != 0 {
+            let len = entry
+                .szExeFile
+                .iter()
+                .position(|&c| c == 0)
+                .unwrap_or(entry.szExeFile.len());
+            names.push(String::from_utf16_lossy(&entry.szExeFile[..len]));
+            ok = Process32NextW(snapshot, &mut entry);
+        }
+        CloseHandle(snapshot);
+    }
+    names
+}
+
+// ── macOS: ps ───────────────────────────────────────────────────────────────
+
+#[cfg(target_os = "macos")]
+fn process_names() -> Vec<String> {
+    std::process::Command::new("ps")
+        .args(["-axc", "-o", "comm="])
+        .output()
+        .map(|output| {
+            String::from_utf8_lossy(&output.stdout)
+                .lines()
+                .map(|line| line.trim().to_string())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+// ── Everything else: procfs ─────────────────────────────────────────────────
+
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+fn process_names() -> Vec<String> {
+    let Ok(entries) = std::fs::read_dir("/proc") else {
+        return Vec::new();
+    };
+    entries
+        .flatten()
+        .filter_map(|entry| std::fs::read_to_string(entry.path().join("comm")).ok())
+        .map(|comm| comm.trim().to_string())
+        .collect()
+}
+
+#[tauri::command]
+pub fn get_presentation_state(presentation: State<'_, Presentation>) -> PresentationStatus {
+    presentation.status()
+}
+
+/// Force presenting on or off, or pass `None` to go back to detection.
+#[tauri::command]
+pub fn set_presentation_override(
+    app: AppHandle,
+    presentation: State<'_, Presentation>,
+    presenting: Option<bool>,
+) -> PresentationStatus {
+    *presentation.manual.lock().unwrap() = presenting;
+    presentation.refresh(&app, focus::query(&app));
+    presentation.status()
+}
@@ -0,0 +1,88 @@
+/// Share of physical memory in use past which the machine counts as short
+/// of memory.
+#[cfg(not(target_os = "macos"))]
+const MEMORY_LOAD_LIMIT: f64 = 0.9;
+
+/// One-minute load average per core past which the CPU counts as busy.
+#[cfg(not(target_os = "windows"))]
+const LOAD_PER_CORE_LIMIT: f64 = 1.5;
+
+#[cfg(not(target_os = "windows"))]
+fn cpu_busy(load_average: f64) -> bool {
+    let cores = std::thread::available_parallelism().map_or(1, |n| n.get());
+    load_average / cores as f64 > LOAD_PER_CORE_LIMIT
+}
+
+// ── Windows: GlobalMemoryStatusEx ───────────────────────────────────────────
+
+/// Whether the machine is short of memory or CPU, so work that can wait
+/// should. Windows has no load average, so only memory counts there.
+#[cfg(target_os = "windows")]
+pub fn under_pressure() -> bool {
+    use windows_sys::Win32::System::SystemInformation::{GlobalMemoryStatusEx, MEMORYSTATUSEX};
+
+    // SAFETY: MEMORYSTATUSEX is plain data, all-zero is a valid value.
+    let mut status: MEMORYSTATUSEX = unsafe { std::mem::zeroed() };
+    status.dwLength = std::mem::size_of::<MEMORYSTATUSEX>() as u32;
+    // SAFETY: `status` is a valid out pointer with its length set.
+    if unsafe { GlobalMemoryStatusEx(&mut status) } == 0 {
+        return false;
+    }
+    status.dwMemoryLoad as f64 / 100.0 > MEMORY_LOAD_LIMIT
+}
+
+// ── macOS: memory pressure level and load average ───────────────────────────
+
+#[cfg(target_os = "macos")]
+pub fn under_pressure() -> bool {
+    // 1 is normal, 2 warning and 4 critical, as in Activity Monitor
+    let mut level: libc::c_int = 0;
+    let mut size = std::mem::size_of::<libc::c_int>();
+    // SAFETY: the name is NUL-terminated and `level` is as big as `size` says.
+    let read = unsafe {
+        libc::sysctlbyname(
+            c"kern.memorystatus_vm_pressure_level".as_ptr(),
+            (&mut level as *mut libc::c_int).cast(),
+            &mut size,
+            std::ptr::null_mut(),
+            0,
+        )
+    };
+    if read == 0 && level >= 2 {
+        return true;
+    }
+
+    let mut load = [0f64; 1];
+    // SAFETY: `load` has room for the one sample asked for.
+    let samples = unsafe { libc::getloadavg(load.as_mut_ptr(), 1) };
+    samples == 1 && cpu_busy(load[0])
+}
+
+// ── Everything else: /proc ──────────────────────────────────────────────────
+
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+pub fn under_pressure() -> bool {
+    let meminfo = std::fs::read_to_string("/proc/meminfo").unwrap_or_default();
+    let field = |name: &str| {
+        meminfo
+            .lines()
+            .find_map(|line| line.strip_prefix(name))
+            .and_then(|rest| {
+                rest.trim()
+                    .trim_end_matches("kB")
+                    .trim()
+                    .parse::<f64>()
+                    .ok()
+            })
+    };
+    if let (Some(total), Some(available)) = (field("MemTotal:"), field("MemAvailable:")) {
+        if total > 0.0 && 1.0 - available / total > MEMORY_LOAD_LIMIT {
+            return true;
+        }
+    }
+
+    std::fs::read_to_string("/proc/loadavg")
+        .ok()
+        .and_then(|loadavg| loadavg.split_whitespace().next()?.parse().ok())
+        .is_some_and(cpu_busy)
+}
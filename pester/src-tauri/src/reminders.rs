@@ -0,0 +1,137 @@
+use std::sync::Mutex;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::a11y::{A11yBus, A11yEvent};
+use crate::audio::sounds::{self, SoundEvent};
+use crate::scheduler::{Priority, Scheduler};
+use crate::{clock, notifications, prefs, unread::UnreadState};
+
+const REMINDERS_KEY: &str = "reminders";
+
+/// How often due reminders are checked for.
+const POLL_INTERVAL: Duration = Duration::from_secs(15);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Reminder {
+    pub id: u64,
+    pub conversation: String,
+    pub message_id: String,
+    /// Shown in the notification; the backend keeps no message history.
+    pub preview: String,
+    /// Local time in milliseconds since the Unix epoch.
+    pub due_at: u64,
+}
+
+/// Follow-up reminders, persisted so they still fire after a restart.
+pub struct Reminders {
+    list: Mutex<Vec<Reminder>>,
+}
+
+impl Reminders {
+    pub fn load(app: &AppHandle) -> Self {
+        Self {
+            list: Mutex::new(prefs::load(app, REMINDERS_KEY).unwrap_or_default()),
+        }
+    }
+
+    fn save(&self, app: &AppHandle, list: &[Reminder]) -> Result<(), String> {
+        prefs::save(app, REMINDERS_KEY, &list)
+    }
+}
+
+/// Register the job that fires due reminders. Reminders that came due
+/// while the app was closed fire on the first run.
+pub fn start(app: &AppHandle) {
+    app.state::<Scheduler>().register(
+        "reminders",
+        Priority::High,
+        false,
+        POLL_INTERVAL,
+        |app| async move { fire_due(&app) },
+    );
+}
+
+fn fire_due(app: &AppHandle) {
+    let reminders = app.state::<Reminders>();
+    let now = clock::now_millis();
+
+    let due: Vec<Reminder> = {
+        let mut list = reminders.list.lock().unwrap();
+        let (due, pending): (Vec<_>, Vec<_>) = list.drain(..).partition(|r| r.due_at <= now);
+        *list = pending;
+        if due.is_empty() {
+            return;
+        }
+        if let Err(e) = reminders.save(app, &list) {
+            log::warn!("Failed to persist reminders: {}", e);
+        }
+        due
+    };
+
+    for reminder in due {
+        log::debug!("Reminder {} is due", reminder.id);
+        let title = format!("Reminder: {}", reminder.conversation);
+        match notifications::notify(app, title, reminder.preview.clone()) {
+            Ok(true) => sounds::play_event(app, SoundEvent::Reminder, None),
+            Ok(false) => {}
+            Err(e) => log::warn!("Failed to show reminder notification: {}", e),
+        }
+        // Resurface the conversation so the message isn't forgotten again.
+        app.state::<UnreadState>()
+            .mark_unread(app, reminder.conversation.clone());
+        app.state::<A11yBus>().publish(A11yEvent::ReminderDue {
+            conversation: reminder.conversation.clone(),
+            summary: reminder.preview.clone(),
+        });
+        let _ = app.emit("reminder-due", &reminder);
+    }
+}
+
+/// Schedule a reminder about `message_id` at `when` (ms since the epoch).
+#[tauri::command]
+pub fn remind_me(
+    app: AppHandle,
+    reminders: State<'_, Reminders>,
+    conversation: String,
+    message_id: String,
+    preview: String,
+    when: u64,
+) -> Result<Reminder, String> {
+    let mut list = reminders.list.lock().unwrap();
+    let reminder = Reminder {
+        id: list.iter().map(|r| r.id).max().unwrap_or(0) + 1,
+        conversation,
+        message_id,
+        preview,
+        due_at: when,
+    };
+    list.push(reminder.clone());
+    reminders.save(&app, &list)?;
+    Ok(reminder)
+}
+
+#[tauri::command]
+pub fn list_reminders(reminders: State<'_, Reminders>) -> Vec<Reminder> {
+    let mut list = reminders.list.lock().unwrap().clone();
+    list.sort_by_key(|r| r.due_at);
+    list
+}
+
+#[tauri::command]
+pub fn cancel_reminder(
+    app: AppHandle,
+    reminders: State<'_, Reminders>,
+    id: u64,
+) -> Result<(), String> {
+    let mut list = reminders.list.lock().unwrap();
+    let before = list.len();
+    list.retain(|r| r.id != id);
+    if list.len() == before {
+        return Err(format!("No reminder with id {}", id));
+    }
+    reminders.save(&app, &list)
+}
@@ -0,0 +1,228 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use tauri::{AppHandle, Manager, State};
+
+use crate::power::{self, PowerSource};
+use crate::pressure;
+
+/// How often the scheduler looks for due jobs.
+const TICK: Duration = Duration::from_secs(5);
+/// How long the power source and resource pressure are trusted before
+/// they're read again; on macOS that means running pmset.
+const CONDITIONS_TTL: Duration = Duration::from_secs(60);
+
+/// On battery, normal-priority jobs run this many times less often.
+const BATTERY_STRETCH: u32 = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Priority {
+    /// Can wait for AC power (maintenance, digests, OCR).
+    Low,
+    /// Slowed down on battery.
+    Normal,
+    /// User-visible work that always runs on time.
+    High,
+}
+
+type JobFuture = Pin<Box<dyn Future<Output = ()> + Send>>;
+type JobFn = Arc<dyn Fn(AppHandle) -> JobFuture + Send + Sync>;
+
+struct Job {
+    name: &'static str,
+    priority: Priority,
+    /// Never run on battery, regardless of priority.
+    needs_ac: bool,
+    interval: Duration,
+    last_run: Option<Instant>,
+    deferred: bool,
+    running: Arc<AtomicBool>,
+    run: JobFn,
+}
+
+/// What the machine is up to, as far as deferring work goes.
+#[derive(Debug, Clone, Copy)]
+struct Conditions {
+    power: PowerSource,
+    /// Short of memory or CPU.
+    pressure: bool,
+}
+
+impl Job {
+    fn allowed_in(&self, conditions: Conditions) -> bool {
+        let low = self.priority == Priority::Low;
+        if conditions.pressure && low {
+            return false;
+        }
+        conditions.power != PowerSource::Battery || (!self.needs_ac && !low)
+    }
+
+    fn effective_interval(&self, power: PowerSource) -> Duration {
+        if power == PowerSource::Battery && self.priority == Priority::Normal {
+            self.interval * BATTERY_STRETCH
+        } else {
+            self.interval
+        }
+    }
+}
+
+/// Central scheduler for periodic background work. Jobs declare a priority
+/// and whether they need AC power; low-priority work waits while the laptop
+/// is on battery or short of memory or CPU, instead of every feature
+/// running its own timer.
+#[derive(Default)]
+pub struct Scheduler {
+    jobs: Mutex<Vec<Job>>,
+    conditions: Mutex<Option<(Instant, Conditions)>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JobStatus {
+    pub name: String,
+    pub priority: Priority,
+    pub needs_ac: bool,
+    pub interval_secs: u64,
+    /// Seconds since the job last started, `None` if it never ran.
+    pub last_run_secs_ago: Option<u64>,
+    /// Held back by the current power state or resource pressure.
+    pub deferred: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SchedulerStatus {
+    pub power: PowerSource,
+    /// The machine is short of memory or CPU.
+    pub pressure: bool,
+    pub jobs: Vec<JobStatus>,
+}
+
+impl Scheduler {
+    /// Run `run` every `interval`. Jobs are due immediately after
+    /// registration; a run that is still in progress is never overlapped.
+    pub fn register<F, Fut>(
+        &self,
+        name: &'static str,
+        priority: Priority,
+        needs_ac: bool,
+        interval: Duration,
+        run: F,
+    ) where
+        F: Fn(AppHandle) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        self.jobs.lock().unwrap().push(Job {
+            name,
+            priority,
+            needs_ac,
+            interval,
+            last_run: None,
+            deferred: false,
+            running: Arc::new(AtomicBool::new(false)),
+            run: Arc::new(move |app| Box::pin(run(app))),
+        });
+    }
+
+    /// The power source and resource pressure, read at most once per
+    /// `CONDITIONS_TTL`.
+    fn conditions(&self) -> Conditions {
+        let mut cached = self.conditions.lock().unwrap();
+        match *cached {
+            Some((read_at, conditions)) if read_at.elapsed() < CONDITIONS_TTL => conditions,
+            _ => {
+                let conditions = Conditions {
+                    power: power::source(),
+                    pressure: pressure::under_pressure(),
+                };
+                *cached = Some((Instant::now(), conditions));
+                conditions
+            }
+        }
+    }
+
+    fn tick(&self, app: &AppHandle) {
+        let conditions = self.conditions();
+        let now = Instant::now();
+        let mut jobs = self.jobs.lock().unwrap();
+
+        for job in jobs.iter_mut() {
+            let due = job.last_run.is_none_or(|last| {
+                now.duration_since(last) >= job.effective_interval(conditions.power)
+            });
+            if !due || job.running.load(Ordering::Relaxed) {
+                continue;
+            }
+
+            let deferred = !job.allowed_in(conditions);
+            if deferred != job.deferred {
+                log::debug!(
+                    "{} job {} ({:?})",
+                    if deferred { "Deferring" } else { "Resuming" },
+                    job.name,
+                    conditions
+                );
+                job.deferred = deferred;
+            }
+            if deferred {
+                continue;
+            }
+
+            job.last_run = Some(now);
+            job.running.store(true, Ordering::Relaxed);
+            let running = job.running.clone();
+            let future = (job.run)(app.clone());
+            tauri::async_runtime::spawn(async move {
+                future.await;
+                running.store(false, Ordering::Relaxed);
+            });
+        }
+    }
+
+    fn status(&self) -> SchedulerStatus {
+        let conditions = self.conditions();
+        let now = Instant::now();
+        let jobs = self
+            .jobs
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|job| JobStatus {
+                name: job.name.to_string(),
+                priority: job.priority,
+                needs_ac: job.needs_ac,
+                interval_secs: job.interval.as_secs(),
+                last_run_secs_ago: job.last_run.map(|last| now.duration_since(last).as_secs()),
+                deferred: !job.allowed_in(conditions),
+            })
+            .collect();
+        SchedulerStatus {
+            power: conditions.power,
+            pressure: conditions.pressure,
+            jobs,
+        }
+    }
+}
+
+/// Start the scheduler loop. Called once from `setup`, after features have
+/// registered their jobs.
+pub fn start(app: &AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let mut ticker = tokio::time::interval(TICK);
+        loop {
+            ticker.tick().await;
+            app.state::<Scheduler>().tick(&app);
+        }
+    });
+}
+
+#[tauri::command]
+pub fn get_scheduler_status(scheduler: State<'_, Scheduler>) -> SchedulerStatus {
+    scheduler.status()
+}
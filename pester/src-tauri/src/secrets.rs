@@ -0,0 +1,56 @@
+use keyring::Entry;
+use tauri::AppHandle;
+
+/// Credentials live in the OS keychain (Credential Manager, Keychain,
+/// libsecret) under the app identifier, never in the store file.
+fn entry(app: &AppHandle, key: &str) -> Result<Entry, String> {
+    if key.is_empty() {
+        return Err("Secret name is empty".to_string());
+    }
+    Entry::new(&app.config().identifier, key).map_err(|e| e.to_string())
+}
+
+pub fn store(app: &AppHandle, key: &str, value: &str) -> Result<(), String> {
+    entry(app, key)?
+        .set_password(value)
+        .map_err(|e| e.to_string())?;
+    log::debug!("Stored secret {}", key);
+    Ok(())
+}
+
+/// `None` when nothing is stored under `key`.
+pub fn get(app: &AppHandle, key: &str) -> Result<Option<String>, String> {
+    match entry(app, key)?.get_password() {
+        Ok(value) => Ok(Some(value)),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+/// Deleting a missing secret is not an error.
+pub fn delete(app: &AppHandle, key: &str) -> Result<(), String> {
+    match entry(app, key)?.delete_credential() {
+        Ok(()) | Err(keyring::Error::NoEntry) => {
+            log::debug!("Deleted secret {}", key);
+            Ok(())
+        }
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+// ── Commands ────────────────────────────────────────────────────────────────
+
+#[tauri::command]
+pub fn store_secret(app: AppHandle, key: String, value: String) -> Result<(), String> {
+    store(&app, &key, &value)
+}
+
+#[tauri::command]
+pub fn get_secret(app: AppHandle, key: String) -> Result<Option<String>, String> {
+    get(&app, &key)
+}
+
+#[tauri::command]
+pub fn delete_secret(app: AppHandle, key: String) -> Result<(), String> {
+    delete(&app, &key)
+}
@@ -0,0 +1,61 @@
+use std::collections::HashMap;
+
+use tauri::AppHandle;
+
+use crate::prefs;
+
+const HISTORY_KEY: &str = "send_history";
+/// Conversation ID → number of incognito messages sent.
+const INCOGNITO_KEY: &str = "incognito_sent";
+
+/// Sent messages remembered per conversation for composer recall.
+const MAX_ENTRIES: usize = 50;
+
+/// Conversation ID → sent texts, newest first.
+type SendHistory = HashMap<String, Vec<String>>;
+
+/// Remember a sent message for IRC-style recall. Repeating the previous
+/// message doesn't add a second entry.
+#[tauri::command]
+pub fn record_sent_message(
+    app: AppHandle,
+    conversation: String,
+    text: String,
+) -> Result<(), String> {
+    let mut history: SendHistory = prefs::load(&app, HISTORY_KEY).unwrap_or_default();
+    let entries = history.entry(conversation).or_default();
+
+    if entries.first() == Some(&text) {
+        return Ok(());
+    }
+    entries.insert(0, text);
+    entries.truncate(MAX_ENTRIES);
+
+    prefs::save(&app, HISTORY_KEY, &history)
+}
+
+/// The `index`-th most recent message sent in `conversation` (0 = last one),
+/// or `None` once the history runs out.
+#[tauri::command]
+pub fn get_send_history(app: AppHandle, conversation: String, index: usize) -> Option<String> {
+    let history: SendHistory = prefs::load(&app, HISTORY_KEY).unwrap_or_default();
+    history.get(&conversation)?.get(index).cloned()
+}
+
+/// Count an incognito send instead of storing its text, so the history
+/// shows something was sent without keeping what.
+#[tauri::command]
+pub fn record_incognito_message(app: AppHandle, conversation: String) -> Result<u32, String> {
+    let mut counts: HashMap<String, u32> = prefs::load(&app, INCOGNITO_KEY).unwrap_or_default();
+    let count = counts.entry(conversation).or_default();
+    *count += 1;
+    let count = *count;
+    prefs::save(&app, INCOGNITO_KEY, &counts)?;
+    Ok(count)
+}
+
+#[tauri::command]
+pub fn get_incognito_count(app: AppHandle, conversation: String) -> u32 {
+    let counts: HashMap<String, u32> = prefs::load(&app, INCOGNITO_KEY).unwrap_or_default();
+    counts.get(&conversation).copied().unwrap_or(0)
+}
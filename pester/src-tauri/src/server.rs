@@ -0,0 +1,212 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter};
+
+use crate::{clock, prefs};
+
+const SERVER_KEY: &str = "server";
+const DISCOVERY_KEY: &str = "server_discovery";
+const DEFAULT_WEBSOCKET: &str = "ws://localhost:4000";
+
+/// How long a discovered server is trusted when the domain doesn't say.
+const DEFAULT_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+const MAX_TTL: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+const DISCOVERY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Where a Pester server listens.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Endpoints {
+    /// `ws://` or `wss://` URL the connection registers on.
+    pub websocket: String,
+    /// HTTP base URL, for servers that have one.
+    #[serde(default)]
+    pub api: Option<String>,
+}
+
+/// Which server to use, as picked in settings.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ServerConfig {
+    /// The server bundled with the app.
+    #[default]
+    Default,
+    /// Looked up from `/.well-known/pester` on a domain and refreshed when
+    /// the cached answer expires.
+    Discovered { domain: String },
+    /// Entered by hand, for domains without discovery.
+    Manual { endpoints: Endpoints },
+}
+
+/// `/.well-known/pester` as served by a domain.
+#[derive(Deserialize)]
+struct WellKnown {
+    websocket: String,
+    api: Option<String>,
+    /// Seconds to cache this for.
+    ttl: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Discovered {
+    endpoints: Endpoints,
+    /// Milliseconds.
+    expires_at: i64,
+}
+
+/// Domain → what it last served.
+type Cache = HashMap<String, Discovered>;
+
+/// `user@example.com` or a bare `example.com` → `example.com`.
+fn domain_of(address: &str) -> Result<String, String> {
+    let domain = address
+        .rsplit_once('@')
+        .map_or(address, |(_, domain)| domain)
+        .trim()
+        .trim_end_matches('.')
+        .to_ascii_lowercase();
+    let valid = !domain.is_empty()
+        && !domain.starts_with(['.', '-', ':'])
+        && domain
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | ':'));
+    if !valid {
+        return Err(format!("\"{}\" isn't a domain", address.trim()));
+    }
+    Ok(domain)
+}
+
+fn check(endpoints: &Endpoints) -> Result<(), String> {
+    if !endpoints.websocket.starts_with("ws://") && !endpoints.websocket.starts_with("wss://") {
+        return Err("The websocket endpoint must start with ws:// or wss://".to_string());
+    }
+    if let Some(api) = &endpoints.api {
+        if !api.starts_with("http://") && !api.starts_with("https://") {
+            return Err("The API endpoint must start with http:// or https://".to_string());
+        }
+    }
+    Ok(())
+}
+
+async fn fetch(domain: &str) -> Result<(Endpoints, Duration), String> {
+    let client = reqwest::Client::builder()
+        .timeout(DISCOVERY_TIMEOUT)
+        .build()
+        .map_err(|e| e.to_string())?;
+    let well_known: WellKnown = client
+        .get(format!("https://{}/.well-known/pester", domain))
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| e.to_string())?
+        .json()
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let endpoints = Endpoints {
+        websocket: well_known.websocket,
+        api: well_known.api,
+    };
+    check(&endpoints)?;
+    let ttl = well_known
+        .ttl
+        .map_or(DEFAULT_TTL, Duration::from_secs)
+        .min(MAX_TTL);
+    Ok((endpoints, ttl))
+}
+
+/// `domain`'s endpoints, from the cache unless it expired or `refresh` is
+/// set. A stale answer beats none when the domain can't be reached.
+async fn discover(app: &AppHandle, domain: &str, refresh: bool) -> Result<Endpoints, String> {
+    let now = clock::now_millis() as i64;
+    let mut cache: Cache = prefs::load(app, DISCOVERY_KEY).unwrap_or_default();
+    let cached = cache.remove(domain);
+    if let Some(cached) = cached.as_ref().filter(|c| !refresh && c.expires_at > now) {
+        return Ok(cached.endpoints.clone());
+    }
+
+    match fetch(domain).await {
+        Ok((endpoints, ttl)) => {
+            log::debug!("Discovered {} at {}", domain, endpoints.websocket);
+            cache.insert(
+                domain.to_string(),
+                Discovered {
+                    endpoints: endpoints.clone(),
+                    expires_at: now + ttl.as_millis() as i64,
+                },
+            );
+            prefs::save(app, DISCOVERY_KEY, &cache)?;
+            Ok(endpoints)
+        }
+        Err(e) => match cached {
+            Some(cached) => {
+                log::warn!(
+                    "Rediscovering {} failed, keeping the old answer: {}",
+                    domain,
+                    e
+                );
+                Ok(cached.endpoints)
+            }
+            None => Err(format!("Couldn't discover a server for {}: {}", domain, e)),
+        },
+    }
+}
+
+fn set_config(app: &AppHandle, config: &ServerConfig) -> Result<(), String> {
+    prefs::save(app, SERVER_KEY, config)?;
+    log::debug!("Server set to {:?}", config);
+    let _ = app.emit("server-changed", config);
+    Ok(())
+}
+
+/// The websocket URL to connect to, rediscovering it if needed.
+pub async fn websocket_url(app: &AppHandle) -> Result<String, String> {
+    match prefs::load(app, SERVER_KEY).unwrap_or_default() {
+        ServerConfig::Default => Ok(DEFAULT_WEBSOCKET.to_string()),
+        ServerConfig::Discovered { domain } => Ok(discover(app, &domain, false).await?.websocket),
+        ServerConfig::Manual { endpoints } => Ok(endpoints.websocket),
+    }
+}
+
+// ── Commands ────────────────────────────────────────────────────────────────
+
+#[tauri::command]
+pub fn get_server(app: AppHandle) -> ServerConfig {
+    prefs::load(&app, SERVER_KEY).unwrap_or_default()
+}
+
+/// Use the server that `address` (`user@example.com` or `example.com`)
+/// points to. When this fails the user can enter endpoints by hand with
+/// `set_server_endpoints`. Takes effect on the next connection.
+#[tauri::command]
+pub async fn discover_server(app: AppHandle, address: String) -> Result<Endpoints, String> {
+    let domain = domain_of(&address)?;
+    let endpoints = discover(&app, &domain, true).await?;
+    set_config(&app, &ServerConfig::Discovered { domain })?;
+    Ok(endpoints)
+}
+
+#[tauri::command]
+pub fn set_server_endpoints(
+    app: AppHandle,
+    websocket: String,
+    api: Option<String>,
+) -> Result<(), String> {
+    let endpoints = Endpoints {
+        websocket: websocket.trim().to_string(),
+        api: api
+            .map(|api| api.trim().to_string())
+            .filter(|api| !api.is_empty()),
+    };
+    check(&endpoints)?;
+    set_config(&app, &ServerConfig::Manual { endpoints })
+}
+
+/// Go back to the bundled server.
+#[tauri::command]
+pub fn reset_server(app: AppHandle) -> Result<(), String> {
+    set_config(&app, &ServerConfig::Default)
+}
@@ -0,0 +1,316 @@
+use chrono::{DateTime, Days, Local, NaiveDate, NaiveTime, Offset, TimeDelta, TimeZone};
+use rand_core::{OsRng, RngCore};
+use rusqlite::{params, Connection, Row};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager, State};
+
+use super::Database;
+
+/// Page size when the caller doesn't ask for one.
+const DEFAULT_LIMIT: u32 = 50;
+const MAX_LIMIT: u32 = 500;
+
+/// Longest activity strip, in days.
+const MAX_HEATMAP_DAYS: u32 = 366;
+/// Every UTC offset is a whole number of quarter hours.
+const BUCKET_MS: i64 = 15 * 60 * 1000;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StoredMessage {
+    /// From `new_id`, and the same in the webview. Older messages have
+    /// `<fromUserId>-<timestamp>`.
+    pub id: String,
+    /// The other user, whichever direction the message went.
+    pub conversation_id: String,
+    pub from_user_id: String,
+    pub text: String,
+    /// Server time in milliseconds.
+    pub timestamp: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConversationSummary {
+    pub conversation_id: String,
+    pub message_count: u32,
+    pub last_timestamp: i64,
+}
+
+pub fn init(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS messages (
+             id              TEXT PRIMARY KEY,
+             conversation_id TEXT NOT NULL,
+             from_user_id    TEXT NOT NULL,
+             text            TEXT NOT NULL,
+             timestamp       INTEGER NOT NULL
+         );
+         CREATE INDEX IF NOT EXISTS messages_by_conversation
+             ON messages (conversation_id, timestamp);
+         CREATE INDEX IF NOT EXISTS messages_by_sender
+             ON messages (from_user_id, timestamp);",
+    )
+}
+
+pub fn from_row(row: &Row) -> rusqlite::Result<StoredMessage> {
+    Ok(StoredMessage {
+        id: row.get(0)?,
+        conversation_id: row.get(1)?,
+        from_user_id: row.get(2)?,
+        text: row.get(3)?,
+        timestamp: row.get(4)?,
+    })
+}
+
+/// A fresh message ID: 128 random bits in hex, so two messages from one
+/// sender in the same millisecond don't collide.
+pub fn new_id() -> String {
+    let mut bytes = [0u8; 16];
+    OsRng.fill_bytes(&mut bytes);
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Insert a message, ignoring it if one with the same ID is stored already.
+pub fn save(conn: &Connection, message: &StoredMessage) -> rusqlite::Result<()> {
+    conn.execute(
+        "INSERT OR IGNORE INTO messages (id, conversation_id, from_user_id, text, timestamp)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![
+            message.id,
+            message.conversation_id,
+            message.from_user_id,
+            message.text,
+            message.timestamp
+        ],
+    )?;
+    Ok(())
+}
+
+/// Save a message from the connection or outbox. Failures are logged, not
+/// surfaced: losing history must never block chatting.
+pub fn record(app: &AppHandle, message: StoredMessage) {
+    if let Err(e) = app.state::<Database>().with(|conn| save(conn, &message)) {
+        log::warn!("Failed to store message {}: {}", message.id, e);
+    }
+}
+
+/// The newest `limit` messages across every conversation, oldest first.
+pub fn recent(conn: &Connection, limit: u32) -> rusqlite::Result<Vec<StoredMessage>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, conversation_id, from_user_id, text, timestamp FROM messages
+         ORDER BY timestamp DESC LIMIT ?1",
+    )?;
+    let mut messages = stmt
+        .query_map([limit], from_row)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    messages.reverse();
+    Ok(messages)
+}
+
+#[tauri::command]
+pub fn save_message(db: State<'_, Database>, message: StoredMessage) -> Result<(), String> {
+    db.with(|conn| save(conn, &message))
+}
+
+/// Up to `limit` messages older than `before` (newest page when `None`),
+/// returned oldest first.
+#[tauri::command]
+pub fn get_messages(
+    db: State<'_, Database>,
+    conversation_id: String,
+    before: Option<i64>,
+    limit: Option<u32>,
+) -> Result<Vec<StoredMessage>, String> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT);
+    db.with(|conn| {
+        let mut stmt = conn.prepare(
+            "SELECT id, conversation_id, from_user_id, text, timestamp FROM messages
+             WHERE conversation_id = ?1 AND timestamp < ?2
+             ORDER BY timestamp DESC LIMIT ?3",
+        )?;
+        let mut messages = stmt
+            .query_map(
+                params![conversation_id, before.unwrap_or(i64::MAX), limit],
+                from_row,
+            )?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        messages.reverse();
+        Ok(messages)
+    })
+}
+
+/// Conversations with stored history, in the order set with
+/// `set_conversation_sort`.
+#[tauri::command]
+pub fn list_stored_conversations(
+    app: AppHandle,
+    db: State<'_, Database>,
+) -> Result<Vec<ConversationSummary>, String> {
+    let conn = db.lock();
+    let mut summaries = {
+        let mut stmt = conn
+            .prepare(
+                "SELECT conversation_id, COUNT(*), MAX(timestamp) FROM messages
+                 GROUP BY conversation_id ORDER BY MAX(timestamp) DESC",
+            )
+            .map_err(|e| e.to_string())?;
+        let summaries = stmt
+            .query_map([], |row| {
+                Ok(ConversationSummary {
+                    conversation_id: row.get(0)?,
+                    message_count: row.get(1)?,
+                    last_timestamp: row.get(2)?,
+                })
+            })
+            .and_then(|rows| rows.collect::<rusqlite::Result<Vec<_>>>())
+            .map_err(|e| e.to_string())?;
+        summaries
+    };
+    super::sort::sort(&app, &conn, &mut summaries, |s| &s.conversation_id)?;
+    Ok(summaries)
+}
+
+/// The first instant of `day` in `tz`. Where a DST change skips midnight
+/// that's the moment the clocks jump, i.e. midnight at the offset before.
+fn start_of_day<Tz: TimeZone>(tz: &Tz, day: NaiveDate) -> Option<DateTime<Tz>> {
+    let midnight = day.and_time(NaiveTime::MIN);
+    if let Some(start) = tz.from_local_datetime(&midnight).earliest() {
+        return Some(start);
+    }
+    let before = tz
+        .from_local_datetime(&(midnight - TimeDelta::hours(12)))
+        .earliest()?;
+    let start = midnight
+        .and_local_timezone(before.offset().fix())
+        .single()?;
+    Some(start.with_timezone(tz))
+}
+
+/// Messages per local day with `contact` over the last `days` days, oldest
+/// first and ending today, for activity strips.
+#[tauri::command]
+pub fn get_activity_heatmap(
+    db: State<'_, Database>,
+    contact: String,
+    days: u32,
+) -> Result<Vec<u32>, String> {
+    let days = days.clamp(1, MAX_HEATMAP_DAYS);
+    let first_day = Local::now()
+        .date_naive()
+        .checked_sub_days(Days::new(days as u64 - 1))
+        .ok_or("Date out of range")?;
+    let since = start_of_day(&Local, first_day)
+        .ok_or("Date out of range")?
+        .timestamp_millis();
+
+    // Count per quarter hour in SQL, off the conversation index, then put
+    // those into local days here so DST changes land on the right day.
+    let buckets = db.with(|conn| {
+        let mut stmt = conn.prepare(
+            "SELECT timestamp / ?3, COUNT(*) FROM messages
+             WHERE conversation_id = ?1 AND timestamp >= ?2
+             GROUP BY timestamp / ?3",
+        )?;
+        let buckets = stmt
+            .query_map(params![contact, since, BUCKET_MS], |row| {
+                Ok((row.get::<_, i64>(0)?, row.get::<_, u32>(1)?))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>();
+        buckets
+    })?;
+
+    let mut counts = vec![0u32; days as usize];
+    for (bucket, count) in buckets {
+        let Some(time) = Local.timestamp_millis_opt(bucket * BUCKET_MS).single() else {
+            continue;
+        };
+        let day = (time.date_naive() - first_day).num_days();
+        if let Some(slot) = usize::try_from(day).ok().and_then(|d| counts.get_mut(d)) {
+            *slot += count;
+        }
+    }
+    Ok(counts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{FixedOffset, LocalResult, NaiveDateTime, Utc};
+
+    /// A zone that moves from UTC-4 to UTC-3 at local midnight on
+    /// 2024-09-08, so that midnight never happens, as in Santiago.
+    #[derive(Clone, Copy, Debug)]
+    struct SkipsMidnight;
+
+    impl SkipsMidnight {
+        const BEFORE: i32 = -4 * 3600;
+        const AFTER: i32 = -3 * 3600;
+
+        fn change() -> NaiveDateTime {
+            NaiveDate::from_ymd_opt(2024, 9, 8)
+                .unwrap()
+                .and_hms_opt(4, 0, 0)
+                .unwrap()
+        }
+
+        fn offset(seconds: i32) -> FixedOffset {
+            FixedOffset::east_opt(seconds).unwrap()
+        }
+    }
+
+    impl TimeZone for SkipsMidnight {
+        type Offset = FixedOffset;
+
+        fn from_offset(_: &FixedOffset) -> Self {
+            SkipsMidnight
+        }
+
+        fn offset_from_local_date(&self, local: &NaiveDate) -> LocalResult<FixedOffset> {
+            self.offset_from_local_datetime(&local.and_time(NaiveTime::MIN))
+        }
+
+        fn offset_from_local_datetime(&self, local: &NaiveDateTime) -> LocalResult<FixedOffset> {
+            for seconds in [Self::BEFORE, Self::AFTER] {
+                let utc = *local - TimeDelta::seconds(seconds as i64);
+                if self.offset_from_utc_datetime(&utc).local_minus_utc() == seconds {
+                    return LocalResult::Single(Self::offset(seconds));
+                }
+            }
+            LocalResult::None
+        }
+
+        fn offset_from_utc_date(&self, utc: &NaiveDate) -> FixedOffset {
+            self.offset_from_utc_datetime(&utc.and_time(NaiveTime::MIN))
+        }
+
+        fn offset_from_utc_datetime(&self, utc: &NaiveDateTime) -> FixedOffset {
+            Self::offset(if *utc < Self::change() {
+                Self::BEFORE
+            } else {
+                Self::AFTER
+            })
+        }
+    }
+
+    #[test]
+    fn days_start_at_local_midnight() {
+        let day = NaiveDate::from_ymd_opt(2024, 9, 7).unwrap();
+        let start = start_of_day(&SkipsMidnight, day).unwrap();
+        assert_eq!(
+            start.with_timezone(&Utc).naive_utc(),
+            day.and_hms_opt(4, 0, 0).unwrap()
+        );
+    }
+
+    #[test]
+    fn days_without_midnight_start_when_the_clocks_jump() {
+        let day = NaiveDate::from_ymd_opt(2024, 9, 8).unwrap();
+        let start = start_of_day(&SkipsMidnight, day).unwrap();
+        assert_eq!(
+            start.with_timezone(&Utc).naive_utc(),
+            SkipsMidnight::change()
+        );
+        assert_eq!(start.naive_local(), day.and_hms_opt(1, 0, 0).unwrap());
+    }
+}
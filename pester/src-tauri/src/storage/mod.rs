@@ -0,0 +1,60 @@
+use std::path::PathBuf;
+use std::sync::{Mutex, MutexGuard};
+
+use rusqlite::Connection;
+use tauri::{AppHandle, Manager};
+
+pub mod messages;
+pub mod recovery;
+pub mod search;
+pub mod snapshot;
+pub mod sort;
+
+const DATABASE_FILE: &str = "history.db";
+
+pub fn database_path(app: &AppHandle) -> Result<PathBuf, String> {
+    let dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
+    std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    Ok(dir.join(DATABASE_FILE))
+}
+
+/// Local SQLite database for everything too big or too structured for the
+/// key-value store, starting with message history.
+pub struct Database(Mutex<Connection>);
+
+impl Database {
+    pub fn open(app: &AppHandle) -> Result<Self, String> {
+        let path = database_path(app)?;
+        let conn = Connection::open(&path).map_err(|e| e.to_string())?;
+        conn.pragma_update(None, "journal_mode", "WAL")
+            .map_err(|e| e.to_string())?;
+        let db = Self::with_tables(conn)?;
+        log::debug!("Opened database at {}", path.display());
+        Ok(db)
+    }
+
+    /// An empty database that lasts until the app quits, for when the real
+    /// one can't be opened.
+    pub fn in_memory() -> Result<Self, String> {
+        Self::with_tables(Connection::open_in_memory().map_err(|e| e.to_string())?)
+    }
+
+    fn with_tables(conn: Connection) -> Result<Self, String> {
+        messages::init(&conn).map_err(|e| e.to_string())?;
+        search::init(&conn).map_err(|e| e.to_string())?;
+        crate::crypto::init(&conn).map_err(|e| e.to_string())?;
+        crate::outbox::init(&conn).map_err(|e| e.to_string())?;
+        Ok(Self(Mutex::new(conn)))
+    }
+
+    /// Hold the connection across several statements, for callers whose
+    /// errors aren't SQLite errors.
+    pub fn lock(&self) -> MutexGuard<'_, Connection> {
+        self.0.lock().unwrap()
+    }
+
+    /// Run `f` against the connection, mapping SQLite errors for commands.
+    pub fn with<T>(&self, f: impl FnOnce(&Connection) -> rusqlite::Result<T>) -> Result<T, String> {
+        f(&self.0.lock().unwrap()).map_err(|e| e.to_string())
+    }
+}
@@ -0,0 +1,258 @@
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use rusqlite::{Connection, OpenFlags};
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::{clock, prefs};
+
+const BACKUP_DIR: &str = "backups";
+const QUARANTINE_DIR: &str = "quarantine";
+
+/// Daily backups kept per file.
+const KEEP_BACKUPS: usize = 3;
+
+const DAY_MS: u64 = 24 * 60 * 60 * 1000;
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RecoveryAction {
+    /// Replaced with the newest backup that passed its check. Database
+    /// backups leave out the identity and sessions, so those start over.
+    RestoredFromBackup,
+    /// No usable backup; the file starts over empty. The server keeps no
+    /// history, so there is nothing to resync from.
+    Reset,
+}
+
+/// One damaged file found at startup and what was done about it.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecoveredFile {
+    pub file: String,
+    pub problem: String,
+    pub action: RecoveryAction,
+    /// Backup that was restored, if any.
+    pub backup: Option<String>,
+    /// Where the damaged copy was moved for later inspection.
+    pub quarantined_to: String,
+}
+
+/// What the startup check recovered, kept for the webview since it isn't
+/// listening yet when `data-recovery` is emitted.
+#[derive(Default)]
+pub struct DataRecovery(Mutex<Vec<RecoveredFile>>);
+
+/// A file kind the startup check knows how to verify.
+struct Checked {
+    name: &'static str,
+    /// Extension the backups are saved with.
+    extension: &'static str,
+    /// Sidecar files that belong to the main file (SQLite's WAL).
+    sidecars: &'static [&'static str],
+    verify: fn(&Path) -> Result<(), String>,
+    backup: fn(&Path, &Path) -> Result<(), String>,
+}
+
+const CHECKED: &[Checked] = &[
+    Checked {
+        name: prefs::STORE_FILE,
+        extension: "json",
+        sidecars: &[],
+        verify: verify_store,
+        backup: copy_file,
+    },
+    Checked {
+        name: super::DATABASE_FILE,
+        extension: "db",
+        sidecars: &["-wal", "-shm"],
+        verify: verify_database,
+        backup: backup_database,
+    },
+];
+
+fn verify_store(path: &Path) -> Result<(), String> {
+    let data = std::fs::read(path).map_err(|e| e.to_string())?;
+    serde_json::from_slice::<serde_json::Map<String, serde_json::Value>>(&data)
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
+
+fn verify_database(path: &Path) -> Result<(), String> {
+    let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .map_err(|e| e.to_string())?;
+    let result: String = conn
+        .query_row("PRAGMA quick_check", [], |row| row.get(0))
+        .map_err(|e| e.to_string())?;
+    if result == "ok" {
+        Ok(())
+    } else {
+        Err(result)
+    }
+}
+
+fn copy_file(from: &Path, to: &Path) -> Result<(), String> {
+    std::fs::copy(from, to)
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
+
+/// `VACUUM INTO` gives a consistent copy even with a live WAL. Copies sit
+/// unencrypted next to the database, so the keys are dropped from them.
+fn backup_database(from: &Path, to: &Path) -> Result<(), String> {
+    let conn = Connection::open(from).map_err(|e| e.to_string())?;
+    conn.execute("VACUUM INTO ?1", [to.to_string_lossy()])
+        .map_err(|e| e.to_string())?;
+    let scrubbed = scrub_keys(to);
+    if scrubbed.is_err() {
+        let _ = std::fs::remove_file(to);
+    }
+    scrubbed
+}
+
+/// Drop the crypto tables from a database copy. The second vacuum
+/// rebuilds the file so no freed page still holds them, and the journal
+/// stays in memory so they never land in one on disk.
+fn scrub_keys(path: &Path) -> Result<(), String> {
+    let conn = Connection::open(path).map_err(|e| e.to_string())?;
+    conn.pragma_update(None, "journal_mode", "MEMORY")
+        .map_err(|e| e.to_string())?;
+    conn.pragma_update(None, "secure_delete", true)
+        .map_err(|e| e.to_string())?;
+    conn.execute_batch("DROP TABLE IF EXISTS crypto_keys; VACUUM;")
+        .map_err(|e| e.to_string())
+}
+
+fn stem(name: &str) -> &str {
+    name.rsplit_once('.').map_or(name, |(stem, _)| stem)
+}
+
+/// Backups of `checked`, newest first.
+fn backups_of(dir: &Path, checked: &Checked) -> Vec<PathBuf> {
+    let prefix = format!("{}-", stem(checked.name));
+    let suffix = format!(".{}", checked.extension);
+    let mut backups: Vec<PathBuf> = std::fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .flatten()
+                .map(|entry| entry.path())
+                .filter(|path| {
+                    path.file_name()
+                        .map(|n| n.to_string_lossy())
+                        .is_some_and(|n| n.starts_with(&prefix) && n.ends_with(&suffix))
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    // Names carry a fixed-width day number, so they sort by age.
+    backups.sort();
+    backups.reverse();
+    backups
+}
+
+/// Take today's backup of a file that just passed its check, pruning old ones.
+fn snapshot(data_dir: &Path, checked: &Checked, path: &Path) -> Result<(), String> {
+    let dir = data_dir.join(BACKUP_DIR);
+    std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    let day = clock::now_millis() / DAY_MS;
+    let target = dir.join(format!(
+        "{}-{:06}.{}",
+        stem(checked.name),
+        day,
+        checked.extension
+    ));
+    if !target.exists() {
+        (checked.backup)(path, &target)?;
+    }
+    for old in backups_of(&dir, checked).into_iter().skip(KEEP_BACKUPS) {
+        let _ = std::fs::remove_file(old);
+    }
+    Ok(())
+}
+
+/// Move a damaged file and its sidecars out of the way.
+fn quarantine(data_dir: &Path, checked: &Checked, path: &Path) -> Result<PathBuf, String> {
+    let dir = data_dir
+        .join(QUARANTINE_DIR)
+        .join(clock::now_millis().to_string());
+    std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    std::fs::rename(path, dir.join(checked.name)).map_err(|e| e.to_string())?;
+    for sidecar in checked.sidecars {
+        let name = format!("{}{}", checked.name, sidecar);
+        let from = data_dir.join(&name);
+        if from.exists() {
+            std::fs::rename(from, dir.join(name)).map_err(|e| e.to_string())?;
+        }
+    }
+    Ok(dir)
+}
+
+fn recover(data_dir: &Path, checked: &Checked, problem: String) -> Result<RecoveredFile, String> {
+    let path = data_dir.join(checked.name);
+    let quarantined_to = quarantine(data_dir, checked, &path)?;
+
+    let backup = backups_of(&data_dir.join(BACKUP_DIR), checked)
+        .into_iter()
+        .find(|backup| (checked.verify)(backup).is_ok());
+    let action = match &backup {
+        Some(backup) => {
+            std::fs::copy(backup, &path).map_err(|e| e.to_string())?;
+            RecoveryAction::RestoredFromBackup
+        }
+        None => RecoveryAction::Reset,
+    };
+
+    Ok(RecoveredFile {
+        file: checked.name.to_string(),
+        problem,
+        action,
+        backup: backup.map(|b| b.to_string_lossy().into_owned()),
+        quarantined_to: quarantined_to.to_string_lossy().into_owned(),
+    })
+}
+
+/// Verify the store and database before anything opens them, restoring
+/// damaged ones from backup. Called first thing in `setup`; a file that
+/// can't be recovered is left for its owner to fail on.
+pub fn check(app: &AppHandle) -> Result<(), String> {
+    let data_dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
+    let mut recovered = Vec::new();
+
+    for checked in CHECKED {
+        let path = data_dir.join(checked.name);
+        if !path.exists() {
+            continue;
+        }
+        match (checked.verify)(&path) {
+            Ok(()) => {
+                if let Err(e) = snapshot(&data_dir, checked, &path) {
+                    log::warn!("Failed to back up {}: {}", checked.name, e);
+                }
+            }
+            Err(problem) => {
+                log::warn!("{} is damaged: {}", checked.name, problem);
+                match recover(&data_dir, checked, problem) {
+                    Ok(file) => {
+                        log::warn!("Recovered {}: {:?}", file.file, file.action);
+                        recovered.push(file);
+                    }
+                    // Opening it fails later, and the app starts over
+                    Err(e) => log::error!("Failed to recover {}: {}", checked.name, e),
+                }
+            }
+        }
+    }
+
+    if !recovered.is_empty() {
+        let _ = app.emit("data-recovery", &recovered);
+    }
+    *app.state::<DataRecovery>().0.lock().unwrap() = recovered;
+    Ok(())
+}
+
+/// Files recovered at startup; empty when everything was healthy.
+#[tauri::command]
+pub fn get_data_recovery_report(recovery: State<'_, DataRecovery>) -> Vec<RecoveredFile> {
+    recovery.0.lock().unwrap().clone()
+}
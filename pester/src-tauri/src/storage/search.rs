@@ -0,0 +1,389 @@
+use chrono::{Days, Local, NaiveDate, TimeZone};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use super::messages::{self, StoredMessage};
+use super::Database;
+use crate::connection::ConnectionManager;
+
+const DEFAULT_LIMIT: u32 = 50;
+const MAX_LIMIT: u32 = 200;
+
+/// Messages shown either side of a hit.
+const CONTEXT_MESSAGES: u32 = 1;
+
+/// Extensions that make a link count for `has:image`.
+const IMAGE_EXTENSIONS: [&str; 6] = [".png", ".jpg", ".jpeg", ".gif", ".webp", ".svg"];
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SearchFilters {
+    pub conversation_id: Option<String>,
+    pub from_user_id: Option<String>,
+    /// Server-time bounds in milliseconds, exclusive.
+    pub before: Option<i64>,
+    pub after: Option<i64>,
+    pub has_link: bool,
+    pub has_image: bool,
+    pub limit: Option<u32>,
+}
+
+/// An operator taken out of the query, for the UI to show as a chip.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AppliedFilter {
+    /// `from`, `in`, `has`, `before` or `after`.
+    pub operator: String,
+    pub value: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchHit {
+    pub message: StoredMessage,
+    /// BM25 score; lower is a better match. Zero without search words.
+    pub rank: f64,
+    /// Neighbouring messages in the same conversation, oldest first.
+    pub before: Vec<StoredMessage>,
+    pub after: Vec<StoredMessage>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchResults {
+    /// Words the query matched on, for highlighting.
+    pub terms: Vec<String>,
+    pub filters: Vec<AppliedFilter>,
+    pub hits: Vec<SearchHit>,
+}
+
+/// Full-text index over `messages.text`, kept in sync by triggers.
+pub fn init(conn: &Connection) -> rusqlite::Result<()> {
+    let exists = conn
+        .query_row(
+            "SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'messages_fts'",
+            [],
+            |_| Ok(()),
+        )
+        .optional()?
+        .is_some();
+
+    conn.execute_batch(
+        "CREATE VIRTUAL TABLE IF NOT EXISTS messages_fts USING fts5(
+             text, content = 'messages', content_rowid = 'rowid',
+             tokenize = 'unicode61 remove_diacritics 2'
+         );
+         CREATE TRIGGER IF NOT EXISTS messages_fts_insert AFTER INSERT ON messages BEGIN
+             INSERT INTO messages_fts (rowid, text) VALUES (new.rowid, new.text);
+         END;
+         CREATE TRIGGER IF NOT EXISTS messages_fts_delete AFTER DELETE ON messages BEGIN
+             INSERT INTO messages_fts (messages_fts, rowid, text)
+                 VALUES ('delete', old.rowid, old.text);
+         END;
+         CREATE TRIGGER IF NOT EXISTS messages_fts_update AFTER UPDATE ON messages BEGIN
+             INSERT INTO messages_fts (messages_fts, rowid, text)
+                 VALUES ('delete', old.rowid, old.text);
+             INSERT INTO messages_fts (rowid, text) VALUES (new.rowid, new.text);
+         END;",
+    )?;
+
+    // Index history stored before the index existed.
+    if !exists {
+        conn.execute(
+            "INSERT INTO messages_fts (messages_fts) VALUES ('rebuild')",
+            [],
+        )?;
+    }
+    Ok(())
+}
+
+/// Turn free text into an FTS5 query: every word must match, quoted so
+/// punctuation can't be read as query syntax, and the last word also
+/// matches as a prefix so results update while typing.
+fn fts_query(terms: &[String]) -> String {
+    terms
+        .iter()
+        .enumerate()
+        .map(|(i, term)| {
+            let quoted = format!("\"{}\"", term.replace('"', "\"\""));
+            if i + 1 == terms.len() {
+                format!("{}*", quoted)
+            } else {
+                quoted
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Start of a local calendar day in milliseconds.
+fn day_start_ms(day: NaiveDate) -> Option<i64> {
+    Local
+        .from_local_datetime(&day.and_hms_opt(0, 0, 0)?)
+        .earliest()
+        .map(|t| t.timestamp_millis())
+}
+
+/// Apply one `operator:value` token to `filters`. Returns false when it
+/// isn't a known operator with a usable value, so it's searched as text.
+fn apply_operator(
+    filters: &mut SearchFilters,
+    operator: &str,
+    value: &str,
+    me: Option<&str>,
+) -> bool {
+    match operator {
+        "from" => {
+            let from = match value {
+                "me" => me,
+                other => Some(other),
+            };
+            filters.from_user_id = from.map(str::to_string);
+            from.is_some()
+        }
+        "in" => {
+            filters.conversation_id = Some(value.to_string());
+            true
+        }
+        "has" => match value {
+            "link" => {
+                filters.has_link = true;
+                true
+            }
+            "image" => {
+                filters.has_image = true;
+                true
+            }
+            _ => false,
+        },
+        "before" | "after" => {
+            let Ok(day) = NaiveDate::parse_from_str(value, "%Y-%m-%d") else {
+                return false;
+            };
+            // `after:` a day means from the next day on.
+            if operator == "before" {
+                filters.before = day_start_ms(day);
+                filters.before.is_some()
+            } else {
+                filters.after = day
+                    .checked_add_days(Days::new(1))
+                    .and_then(day_start_ms)
+                    .map(|ms| ms - 1);
+                filters.after.is_some()
+            }
+        }
+        _ => false,
+    }
+}
+
+/// Split a query into search words and `from:`, `in:`, `has:`, `before:`
+/// and `after:` operators, which override the matching `filters`.
+fn parse_query(
+    query: &str,
+    filters: &mut SearchFilters,
+    me: Option<&str>,
+) -> (Vec<String>, Vec<AppliedFilter>) {
+    let mut terms = Vec::new();
+    let mut applied = Vec::new();
+    for token in query.split_whitespace() {
+        let operator = token
+            .split_once(':')
+            .filter(|(_, value)| !value.is_empty())
+            .map(|(operator, value)| (operator.to_lowercase(), value));
+        match operator {
+            Some((operator, value)) if apply_operator(filters, &operator, value, me) => {
+                applied.push(AppliedFilter {
+                    operator,
+                    value: value.to_string(),
+                });
+            }
+            _ => terms.push(token.to_string()),
+        }
+    }
+    (terms, applied)
+}
+
+fn context(
+    conn: &Connection,
+    message: &StoredMessage,
+) -> rusqlite::Result<(Vec<StoredMessage>, Vec<StoredMessage>)> {
+    let mut stmt = conn.prepare_cached(
+        "SELECT id, conversation_id, from_user_id, text, timestamp FROM messages
+         WHERE conversation_id = ?1 AND timestamp < ?2
+         ORDER BY timestamp DESC LIMIT ?3",
+    )?;
+    let mut before = stmt
+        .query_map(
+            params![message.conversation_id, message.timestamp, CONTEXT_MESSAGES],
+            messages::from_row,
+        )?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    before.reverse();
+
+    let mut stmt = conn.prepare_cached(
+        "SELECT id, conversation_id, from_user_id, text, timestamp FROM messages
+         WHERE conversation_id = ?1 AND timestamp > ?2
+         ORDER BY timestamp ASC LIMIT ?3",
+    )?;
+    let after = stmt
+        .query_map(
+            params![message.conversation_id, message.timestamp, CONTEXT_MESSAGES],
+            messages::from_row,
+        )?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    Ok((before, after))
+}
+
+/// Ranked full-text search over stored history. Operators in the query
+/// become filters and are listed in the result; with only operators, the
+/// newest matching messages come first.
+#[tauri::command]
+pub fn search_messages(
+    db: State<'_, Database>,
+    connection: State<'_, ConnectionManager>,
+    query: String,
+    filters: Option<SearchFilters>,
+) -> Result<SearchResults, String> {
+    let mut filters = filters.unwrap_or_default();
+    let (terms, applied) = parse_query(&query, &mut filters, connection.user_id().as_deref());
+    if terms.is_empty() && applied.is_empty() {
+        return Ok(SearchResults {
+            terms,
+            filters: applied,
+            hits: Vec::new(),
+        });
+    }
+    let limit = filters.limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT);
+
+    // Without words there's nothing for the FTS index to match on.
+    let source = if terms.is_empty() {
+        "0.0 AS rank FROM messages m WHERE ?1 IS NULL"
+    } else {
+        "bm25(messages_fts) AS rank
+         FROM messages_fts JOIN messages m ON m.rowid = messages_fts.rowid
+         WHERE messages_fts MATCH ?1"
+    };
+    let image_clause = IMAGE_EXTENSIONS
+        .iter()
+        .map(|ext| format!("m.text LIKE '%://%{}%'", ext))
+        .collect::<Vec<_>>()
+        .join(" OR ");
+    let sql = format!(
+        "SELECT m.id, m.conversation_id, m.from_user_id, m.text, m.timestamp, {}
+           AND (?2 IS NULL OR m.conversation_id = ?2)
+           AND (?3 IS NULL OR m.from_user_id = ?3)
+           AND (?4 IS NULL OR m.timestamp < ?4)
+           AND (?5 IS NULL OR m.timestamp > ?5)
+           AND (NOT ?6 OR m.text LIKE '%://%')
+           AND (NOT ?7 OR {})
+         ORDER BY rank, m.timestamp DESC LIMIT ?8",
+        source, image_clause
+    );
+
+    let hits = db.with(|conn| {
+        let mut stmt = conn.prepare(&sql)?;
+        let matches = stmt
+            .query_map(
+                params![
+                    (!terms.is_empty()).then(|| fts_query(&terms)),
+                    filters.conversation_id,
+                    filters.from_user_id,
+                    filters.before,
+                    filters.after,
+                    filters.has_link,
+                    filters.has_image,
+                    limit
+                ],
+                |row| Ok((messages::from_row(row)?, row.get::<_, f64>(5)?)),
+            )?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        matches
+            .into_iter()
+            .map(|(message, rank)| {
+                let (before, after) = context(conn, &message)?;
+                Ok(SearchHit {
+                    message,
+                    rank,
+                    before,
+                    after,
+                })
+            })
+            .collect()
+    })?;
+
+    Ok(SearchResults {
+        terms,
+        filters: applied,
+        hits,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(query: &str, me: Option<&str>) -> (Vec<String>, SearchFilters, Vec<String>) {
+        let mut filters = SearchFilters::default();
+        let (terms, applied) = parse_query(query, &mut filters, me);
+        let applied = applied
+            .into_iter()
+            .map(|f| format!("{}:{}", f.operator, f.value))
+            .collect();
+        (terms, filters, applied)
+    }
+
+    #[test]
+    fn operators_become_filters() {
+        let (terms, filters, applied) =
+            parse("lunch from:me IN:general has:link plans", Some("alice"));
+        assert_eq!(terms, ["lunch", "plans"]);
+        assert_eq!(filters.from_user_id.as_deref(), Some("alice"));
+        assert_eq!(filters.conversation_id.as_deref(), Some("general"));
+        assert!(filters.has_link);
+        assert!(!filters.has_image);
+        assert_eq!(applied, ["from:me", "in:general", "has:link"]);
+    }
+
+    #[test]
+    fn unusable_operators_are_searched_as_text() {
+        let (terms, filters, applied) = parse(
+            "from:me has:video before:tomorrow https://example.com re:",
+            None,
+        );
+        assert_eq!(
+            terms,
+            [
+                "from:me",
+                "has:video",
+                "before:tomorrow",
+                "https://example.com",
+                "re:"
+            ]
+        );
+        assert!(filters.from_user_id.is_none() && filters.before.is_none());
+        assert!(applied.is_empty());
+    }
+
+    #[test]
+    fn dates_cover_whole_local_days() {
+        let (_, filters, _) = parse("before:2026-01-05 after:2026-01-05", None);
+        let day = NaiveDate::from_ymd_opt(2026, 1, 5).unwrap();
+        let start = day_start_ms(day).unwrap();
+        let next = day_start_ms(day.succ_opt().unwrap()).unwrap();
+        assert_eq!(filters.before, Some(start));
+        assert_eq!(filters.after, Some(next - 1));
+    }
+
+    #[test]
+    fn fts_query_quotes_words_and_prefixes_the_last() {
+        let terms = [
+            "say \"hi\"".to_string(),
+            "NEAR".to_string(),
+            "lun".to_string(),
+        ];
+        assert_eq!(fts_query(&terms), r#""say ""hi""" "NEAR" "lun"*"#);
+    }
+}
@@ -0,0 +1,96 @@
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+use unicode_segmentation::UnicodeSegmentation;
+
+use super::messages::{self, StoredMessage};
+use super::Database;
+use crate::unread::UnreadState;
+
+const SNAPSHOT_FILE: &str = "conversations.snapshot.json";
+/// Enough conversations to fill the list on first paint.
+const MAX_CONVERSATIONS: u32 = 100;
+/// Characters kept of each last message, enough for a preview line.
+const SNIPPET_CHARS: usize = 120;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SnapshotEntry {
+    pub conversation_id: String,
+    /// The newest message, its text cut to a preview.
+    pub last_message: StoredMessage,
+    pub unread: u32,
+}
+
+fn snapshot_path(app: &AppHandle) -> Result<PathBuf, String> {
+    let dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
+    Ok(dir.join(SNAPSHOT_FILE))
+}
+
+fn snippet(text: &str) -> String {
+    let mut graphemes = text.graphemes(true);
+    let snippet: String = graphemes.by_ref().take(SNIPPET_CHARS).collect();
+    if graphemes.next().is_some() {
+        format!("{}…", snippet)
+    } else {
+        snippet
+    }
+}
+
+/// Write the conversation list as it stands, newest first, for the next
+/// launch to show before the database is open. Called on exit.
+pub fn save(app: &AppHandle) {
+    let Some(db) = app.try_state::<Database>() else {
+        return;
+    };
+    let counts = app.state::<UnreadState>().counts();
+    let latest = db.with(|conn| {
+        // SQLite takes the bare columns from the row holding the MAX()
+        let mut stmt = conn.prepare(
+            "SELECT id, conversation_id, from_user_id, text, MAX(timestamp) FROM messages
+             GROUP BY conversation_id ORDER BY MAX(timestamp) DESC LIMIT ?1",
+        )?;
+        let latest = stmt
+            .query_map([MAX_CONVERSATIONS], messages::from_row)?
+            .collect::<rusqlite::Result<Vec<_>>>();
+        latest
+    });
+
+    let result = latest.and_then(|latest| {
+        let entries: Vec<SnapshotEntry> = latest
+            .into_iter()
+            .map(|mut message| {
+                message.text = snippet(&message.text);
+                SnapshotEntry {
+                    conversation_id: message.conversation_id.clone(),
+                    unread: counts.get(&message.conversation_id).copied().unwrap_or(0),
+                    last_message: message,
+                }
+            })
+            .collect();
+        let path = snapshot_path(app)?;
+        let json = serde_json::to_vec(&entries).map_err(|e| e.to_string())?;
+        let tmp = path.with_extension("tmp");
+        std::fs::write(&tmp, json).map_err(|e| e.to_string())?;
+        std::fs::rename(&tmp, &path).map_err(|e| e.to_string())?;
+        Ok(entries.len())
+    });
+
+    match result {
+        Ok(count) => log::debug!("Saved snapshot of {} conversations", count),
+        Err(e) => log::warn!("Failed to save conversation snapshot: {}", e),
+    }
+}
+
+/// The conversation list from the end of the last session. Doesn't touch
+/// the database, so the webview can paint the list right away and replace
+/// it once `list_stored_conversations` answers.
+#[tauri::command]
+pub fn get_conversation_snapshot(app: AppHandle) -> Result<Vec<SnapshotEntry>, String> {
+    match std::fs::read(snapshot_path(&app)?) {
+        Ok(bytes) => serde_json::from_slice(&bytes).map_err(|e| e.to_string()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(e.to_string()),
+    }
+}
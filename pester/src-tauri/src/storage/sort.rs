@@ -0,0 +1,239 @@
+use std::cmp::Reverse;
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use super::Database;
+use crate::unread::UnreadState;
+use crate::{clock, conversations, prefs};
+
+const SORT_KEY: &str = "conversation_sort";
+const DAY_MS: i64 = 24 * 60 * 60 * 1000;
+
+/// How conversation lists are ordered: the sidebar, the tray's recent
+/// chats and anything else listing conversations.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConversationSort {
+    /// Latest message first.
+    #[default]
+    Recency,
+    /// Conversations with unread messages first, then by recency.
+    UnreadFirst,
+    Alphabetical,
+    /// Pinned conversations first, then the ones used most, weighted
+    /// towards recent weeks.
+    PinnedThenFrecency,
+}
+
+#[derive(Default)]
+struct Stats {
+    last_timestamp: i64,
+    frecency: i64,
+}
+
+pub fn current(app: &AppHandle) -> ConversationSort {
+    prefs::load(app, SORT_KEY).unwrap_or_default()
+}
+
+/// Last message time and frecency per conversation as of `now`. Each
+/// message counts for less the older it is, and not at all after 90 days.
+fn stats(conn: &Connection, now: i64) -> rusqlite::Result<HashMap<String, Stats>> {
+    let mut stmt = conn.prepare(
+        "SELECT conversation_id, MAX(timestamp),
+                SUM(CASE
+                    WHEN timestamp >= ?1 - 4 * ?2 THEN 100
+                    WHEN timestamp >= ?1 - 14 * ?2 THEN 70
+                    WHEN timestamp >= ?1 - 31 * ?2 THEN 50
+                    WHEN timestamp >= ?1 - 90 * ?2 THEN 30
+                    ELSE 0
+                END)
+         FROM messages GROUP BY conversation_id",
+    )?;
+    let stats = stmt
+        .query_map(params![now, DAY_MS], |row| {
+            Ok((
+                row.get(0)?,
+                Stats {
+                    last_timestamp: row.get(1)?,
+                    frecency: row.get(2)?,
+                },
+            ))
+        })?
+        .collect();
+    stats
+}
+
+/// Put `items` in `mode`'s order. `unread` is only used for `UnreadFirst`
+/// and `pinned` for `PinnedThenFrecency`.
+fn order<T>(
+    mode: ConversationSort,
+    stats: &HashMap<String, Stats>,
+    unread: &HashSet<String>,
+    pinned: &BTreeSet<String>,
+    items: &mut [T],
+    id: impl Fn(&T) -> &str,
+) {
+    let none = Stats::default();
+    let stats_of = |item: &T| stats.get(id(item)).unwrap_or(&none);
+    match mode {
+        ConversationSort::Recency => {
+            items.sort_by_key(|item| Reverse(stats_of(item).last_timestamp));
+        }
+        ConversationSort::UnreadFirst => {
+            items.sort_by_key(|item| {
+                (
+                    !unread.contains(id(item)),
+                    Reverse(stats_of(item).last_timestamp),
+                )
+            });
+        }
+        ConversationSort::PinnedThenFrecency => {
+            items.sort_by_key(|item| {
+                let stats = stats_of(item);
+                (
+                    !pinned.contains(id(item)),
+                    Reverse(stats.frecency),
+                    Reverse(stats.last_timestamp),
+                )
+            });
+        }
+        ConversationSort::Alphabetical => {
+            items.sort_by_cached_key(|item| id(item).to_lowercase());
+        }
+    }
+}
+
+/// Put `items` in the user's chosen order, `id` giving each one's
+/// conversation. Orders that go by history put conversations without any
+/// last, keeping their order among themselves.
+pub fn sort<T>(
+    app: &AppHandle,
+    conn: &Connection,
+    items: &mut [T],
+    id: impl Fn(&T) -> &str,
+) -> Result<(), String> {
+    let stats = stats(conn, clock::now_millis() as i64).map_err(|e| e.to_string())?;
+    let mode = current(app);
+    let unread = match mode {
+        ConversationSort::UnreadFirst => app.state::<UnreadState>().counts().into_keys().collect(),
+        _ => HashSet::new(),
+    };
+    let pinned = match mode {
+        ConversationSort::PinnedThenFrecency => conversations::load(app).pinned,
+        _ => BTreeSet::new(),
+    };
+    order(mode, &stats, &unread, &pinned, items, id);
+    Ok(())
+}
+
+// ── Commands ────────────────────────────────────────────────────────────────
+
+#[tauri::command]
+pub fn get_conversation_sort(app: AppHandle) -> ConversationSort {
+    current(&app)
+}
+
+/// Change how conversation lists are ordered. Lists re-sort on
+/// `conversation-sort-changed`.
+#[tauri::command]
+pub fn set_conversation_sort(app: AppHandle, mode: ConversationSort) -> Result<(), String> {
+    prefs::save(&app, SORT_KEY, &mode)?;
+    log::debug!("Conversation sort: {:?}", mode);
+    let _ = app.emit("conversation-sort-changed", mode);
+    Ok(())
+}
+
+/// `conversations` in the chosen order, for lists the webview builds itself.
+#[tauri::command]
+pub fn sort_conversations(
+    app: AppHandle,
+    db: State<'_, Database>,
+    mut conversations: Vec<String>,
+) -> Result<Vec<String>, String> {
+    sort(&app, &db.lock(), &mut conversations, |c| c)?;
+    Ok(conversations)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: i64 = 1_000 * DAY_MS;
+
+    fn history() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch("CREATE TABLE messages (conversation_id TEXT, timestamp INTEGER)")
+            .unwrap();
+        // days ago for each message
+        let messages: &[(&str, &[i64])] = &[
+            ("daily", &[1, 2, 3, 5, 6, 8, 9, 10]),
+            ("latest", &[0]),
+            ("old", &[100, 120]),
+            ("Pinned", &[40]),
+        ];
+        for (conversation, days) in messages {
+            for days in *days {
+                conn.execute(
+                    "INSERT INTO messages VALUES (?1, ?2)",
+                    params![conversation, NOW - days * DAY_MS],
+                )
+                .unwrap();
+            }
+        }
+        conn
+    }
+
+    fn sorted(mode: ConversationSort, unread: &[&str], pinned: &[&str]) -> Vec<&'static str> {
+        let stats = stats(&history(), NOW).unwrap();
+        let unread = unread.iter().map(|c| c.to_string()).collect();
+        let pinned = pinned.iter().map(|c| c.to_string()).collect();
+        let mut items = vec!["new", "old", "Pinned", "daily", "latest", "empty"];
+        order(mode, &stats, &unread, &pinned, &mut items, |c| c);
+        items
+    }
+
+    #[test]
+    fn frecency_fades_with_age() {
+        let stats = stats(&history(), NOW).unwrap();
+        assert_eq!(stats["latest"].frecency, 100);
+        assert_eq!(stats["daily"].frecency, 3 * 100 + 5 * 70);
+        assert_eq!(stats["Pinned"].frecency, 30);
+        assert_eq!(stats["old"].frecency, 0);
+        assert_eq!(stats["old"].last_timestamp, NOW - 100 * DAY_MS);
+    }
+
+    #[test]
+    fn recency_keeps_conversations_without_history_last() {
+        assert_eq!(
+            sorted(ConversationSort::Recency, &[], &[]),
+            ["latest", "daily", "Pinned", "old", "new", "empty"]
+        );
+    }
+
+    #[test]
+    fn unread_comes_first() {
+        assert_eq!(
+            sorted(ConversationSort::UnreadFirst, &["old", "new"], &[]),
+            ["old", "new", "latest", "daily", "Pinned", "empty"]
+        );
+    }
+
+    #[test]
+    fn pinned_then_most_used() {
+        assert_eq!(
+            sorted(ConversationSort::PinnedThenFrecency, &[], &["Pinned"]),
+            ["Pinned", "daily", "latest", "old", "new", "empty"]
+        );
+    }
+
+    #[test]
+    fn alphabetical_ignores_case() {
+        assert_eq!(
+            sorted(ConversationSort::Alphabetical, &[], &[]),
+            ["daily", "empty", "latest", "new", "old", "Pinned"]
+        );
+    }
+}
@@ -0,0 +1,235 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use serde::Serialize;
+use tauri::window::{ProgressBarState, ProgressBarStatus};
+use tauri::{AppHandle, Emitter, Manager, State};
+
+/// Error message returned by tasks that stop because they were cancelled.
+pub const CANCELLED: &str = "cancelled";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskStatus {
+    Running,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+/// Snapshot of a background task, also used as the `task-progress` payload.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskInfo {
+    pub id: u64,
+    /// Feature that owns the task, e.g. "export" or "backup".
+    pub kind: String,
+    pub label: String,
+    pub status: TaskStatus,
+    pub done: u64,
+    /// `None` while the amount of work is not known yet.
+    pub total: Option<u64>,
+    pub error: Option<String>,
+}
+
+struct TaskEntry {
+    info: TaskInfo,
+    cancel: Arc<AtomicBool>,
+}
+
+/// Registry of long-running background work (exports, backups, imports,
+/// bulk downloads). Features spawn their work through here instead of
+/// inventing their own progress and cancellation plumbing.
+#[derive(Default)]
+pub struct TaskManager {
+    tasks: Mutex<HashMap<u64, TaskEntry>>,
+    next_id: AtomicU64,
+}
+
+/// Handle given to a running task for reporting progress and checking
+/// whether it should stop.
+#[derive(Clone)]
+pub struct TaskContext {
+    id: u64,
+    app: AppHandle,
+    cancel: Arc<AtomicBool>,
+}
+
+impl TaskContext {
+    pub fn app(&self) -> &AppHandle {
+        &self.app
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel.load(Ordering::Relaxed)
+    }
+
+    /// Convenience for `?`-style early returns at safe stopping points.
+    pub fn check_cancelled(&self) -> Result<(), String> {
+        if self.is_cancelled() {
+            Err(CANCELLED.to_string())
+        } else {
+            Ok(())
+        }
+    }
+
+    pub fn progress(&self, done: u64, total: Option<u64>) {
+        let manager = self.app.state::<TaskManager>();
+        manager.update(&self.app, self.id, |info| {
+            info.done = done;
+            info.total = total;
+        });
+    }
+}
+
+impl TaskManager {
+    /// Run `work` in the background as a tracked, cancellable task and
+    /// return its ID right away.
+    pub fn spawn<F, Fut>(&self, app: &AppHandle, kind: &str, label: String, work: F) -> u64
+    where
+        F: FnOnce(TaskContext) -> Fut + Send + 'static,
+        Fut: Future<Output = Result<(), String>> + Send + 'static,
+    {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+        let cancel = Arc::new(AtomicBool::new(false));
+        let info = TaskInfo {
+            id,
+            kind: kind.to_string(),
+            label,
+            status: TaskStatus::Running,
+            done: 0,
+            total: None,
+            error: None,
+        };
+
+        log::debug!("Starting {} task {}", kind, id);
+        let _ = app.emit("task-progress", &info);
+        self.tasks.lock().unwrap().insert(
+            id,
+            TaskEntry {
+                info,
+                cancel: cancel.clone(),
+            },
+        );
+        self.sync_taskbar(app);
+
+        let ctx = TaskContext {
+            id,
+            app: app.clone(),
+            cancel,
+        };
+        tauri::async_runtime::spawn(async move {
+            let app = ctx.app.clone();
+            let cancelled = ctx.cancel.clone();
+            let result = work(ctx).await;
+            app.state::<TaskManager>()
+                .finish(&app, id, result, cancelled.load(Ordering::Relaxed));
+        });
+
+        id
+    }
+
+    pub fn list(&self) -> Vec<TaskInfo> {
+        let tasks = self.tasks.lock().unwrap();
+        let mut list: Vec<TaskInfo> = tasks.values().map(|t| t.info.clone()).collect();
+        list.sort_by_key(|t| t.id);
+        list
+    }
+
+    pub fn cancel(&self, id: u64) -> bool {
+        match self.tasks.lock().unwrap().get(&id) {
+            Some(entry) => {
+                entry.cancel.store(true, Ordering::Relaxed);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn update(&self, app: &AppHandle, id: u64, apply: impl FnOnce(&mut TaskInfo)) {
+        let info = {
+            let mut tasks = self.tasks.lock().unwrap();
+            let Some(entry) = tasks.get_mut(&id) else {
+                return;
+            };
+            apply(&mut entry.info);
+            entry.info.clone()
+        };
+        let _ = app.emit("task-progress", &info);
+        self.sync_taskbar(app);
+    }
+
+    fn finish(&self, app: &AppHandle, id: u64, result: Result<(), String>, cancelled: bool) {
+        let Some(mut entry) = self.tasks.lock().unwrap().remove(&id) else {
+            return;
+        };
+
+        entry.info.status = match &result {
+            _ if cancelled => TaskStatus::Cancelled,
+            Ok(()) => TaskStatus::Completed,
+            Err(_) => TaskStatus::Failed,
+        };
+        if let Err(e) = result {
+            if !cancelled {
+                log::error!("Task {} ({}) failed: {}", id, entry.info.kind, e);
+                entry.info.error = Some(e);
+            }
+        }
+
+        log::debug!("Task {} finished: {:?}", id, entry.info.status);
+        let _ = app.emit("task-progress", &entry.info);
+        self.sync_taskbar(app);
+    }
+
+    /// Mirror overall progress on the taskbar button / dock icon so it stays
+    /// visible while the window is minimized. Tasks that don't know their
+    /// total yet make the bar indeterminate.
+    fn sync_taskbar(&self, app: &AppHandle) {
+        let Some(window) = app.get_webview_window("main") else {
+            return;
+        };
+
+        let state = {
+            let tasks = self.tasks.lock().unwrap();
+            if tasks.is_empty() {
+                ProgressBarState {
+                    status: Some(ProgressBarStatus::None),
+                    progress: None,
+                }
+            } else if tasks.values().any(|t| t.info.total.is_none()) {
+                ProgressBarState {
+                    status: Some(ProgressBarStatus::Indeterminate),
+                    progress: None,
+                }
+            } else {
+                let (done, total) = tasks.values().fold((0, 0), |(done, total), t| {
+                    (done + t.info.done, total + t.info.total.unwrap_or(0))
+                });
+                ProgressBarState {
+                    status: Some(ProgressBarStatus::Normal),
+                    progress: Some((done * 100).checked_div(total).unwrap_or(0).min(100)),
+                }
+            }
+        };
+
+        if let Err(e) = window.set_progress_bar(state) {
+            log::debug!("Failed to update taskbar progress: {}", e);
+        }
+    }
+}
+
+#[tauri::command]
+pub fn list_tasks(tasks: State<'_, TaskManager>) -> Vec<TaskInfo> {
+    tasks.list()
+}
+
+#[tauri::command]
+pub fn cancel_task(tasks: State<'_, TaskManager>, id: u64) -> Result<(), String> {
+    if tasks.cancel(id) {
+        Ok(())
+    } else {
+        Err(format!("No running task with id {}", id))
+    }
+}
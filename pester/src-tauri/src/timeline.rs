@@ -0,0 +1,115 @@
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, State};
+
+use crate::{clock, prefs};
+
+const TIMELINE_KEY: &str = "connection_timeline";
+
+/// How far back the timeline reaches.
+const RETENTION_MS: u64 = 48 * 60 * 60 * 1000;
+
+/// Hard cap so a reconnect loop can't grow the store without bound.
+const MAX_EVENTS: usize = 5000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConnectionEventKind {
+    Connecting,
+    Connected,
+    Registered,
+    Disconnected,
+    Kicked,
+    Failed,
+    /// A reconnect was scheduled; `backoff_ms` says how far out.
+    Backoff,
+    NetworkOnline,
+    NetworkOffline,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConnectionEvent {
+    pub timestamp: u64,
+    pub kind: ConnectionEventKind,
+    /// Close reason, error message or server URL, depending on the kind.
+    pub detail: Option<String>,
+    pub backoff_ms: Option<u64>,
+}
+
+/// Rolling 48-hour record of connection lifecycle events, kept so
+/// "it keeps disconnecting" reports come with something to look at.
+pub struct ConnectionTimeline {
+    events: Mutex<VecDeque<ConnectionEvent>>,
+}
+
+fn prune(events: &mut VecDeque<ConnectionEvent>, now: u64) {
+    let cutoff = now.saturating_sub(RETENTION_MS);
+    while events
+        .front()
+        .is_some_and(|e| e.timestamp < cutoff || events.len() > MAX_EVENTS)
+    {
+        events.pop_front();
+    }
+}
+
+impl ConnectionTimeline {
+    pub fn load(app: &AppHandle) -> Self {
+        let mut events: VecDeque<ConnectionEvent> =
+            prefs::load(app, TIMELINE_KEY).unwrap_or_default();
+        prune(&mut events, clock::now_millis());
+        Self {
+            events: Mutex::new(events),
+        }
+    }
+
+    pub fn record(
+        &self,
+        app: &AppHandle,
+        kind: ConnectionEventKind,
+        detail: Option<String>,
+        backoff_ms: Option<u64>,
+    ) {
+        let now = clock::now_millis();
+        let snapshot = {
+            let mut events = self.events.lock().unwrap();
+            events.push_back(ConnectionEvent {
+                timestamp: now,
+                kind,
+                detail,
+                backoff_ms,
+            });
+            prune(&mut events, now);
+            events.clone()
+        };
+        if let Err(e) = prefs::save(app, TIMELINE_KEY, &snapshot) {
+            log::warn!("Failed to persist connection timeline: {}", e);
+        }
+    }
+
+    pub fn events(&self) -> Vec<ConnectionEvent> {
+        let mut events = self.events.lock().unwrap();
+        prune(&mut events, clock::now_millis());
+        events.iter().cloned().collect()
+    }
+}
+
+/// For events only the webview sees, like the network going offline.
+#[tauri::command]
+pub fn record_connection_event(
+    app: AppHandle,
+    timeline: State<'_, ConnectionTimeline>,
+    kind: ConnectionEventKind,
+    detail: Option<String>,
+    backoff_ms: Option<u64>,
+) {
+    timeline.record(&app, kind, detail, backoff_ms);
+}
+
+/// Oldest first.
+#[tauri::command]
+pub fn get_connection_timeline(timeline: State<'_, ConnectionTimeline>) -> Vec<ConnectionEvent> {
+    timeline.events()
+}
@@ -0,0 +1,166 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use tauri::async_runtime::JoinHandle;
+use tauri::tray::{MouseButton, MouseButtonState, TrayIcon, TrayIconEvent};
+use tauri::{AppHandle, Manager};
+
+use crate::storage::Database;
+use crate::{actions, prefs};
+
+const SETTINGS_KEY: &str = "tray_click";
+/// How long a click waits to see whether it becomes a double click, when
+/// double clicks do something. Windows' default double-click time.
+const DOUBLE_CLICK_TIME: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TrayClickAction {
+    Nothing,
+    /// Show the window, or hide it if it's up front.
+    ToggleWindow,
+    /// Open the conversation with the newest message.
+    OpenLastChat,
+    /// Show the tray menu, as a right click does.
+    ShowMenu,
+}
+
+/// What left clicks on the tray icon do. Double clicks are only reported
+/// on Windows, and a tray icon can't show its menu on a double click.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct TrayClickSettings {
+    pub click: TrayClickAction,
+    pub double_click: TrayClickAction,
+}
+
+impl Default for TrayClickSettings {
+    fn default() -> Self {
+        Self {
+            click: TrayClickAction::ToggleWindow,
+            double_click: TrayClickAction::Nothing,
+        }
+    }
+}
+
+/// Clicks waiting out the double-click time.
+#[derive(Default)]
+pub struct TrayClicks {
+    pending: Mutex<Option<JoinHandle<()>>>,
+    /// The last double click, whose trailing click is ignored.
+    last_double: Mutex<Option<Instant>>,
+}
+
+fn settings(app: &AppHandle) -> TrayClickSettings {
+    prefs::load(app, SETTINGS_KEY).unwrap_or_default()
+}
+
+/// Let the OS open the menu on left click when that's the click action.
+fn apply(tray: &TrayIcon, settings: &TrayClickSettings) {
+    let show_menu = settings.click == TrayClickAction::ShowMenu;
+    if let Err(e) = tray.set_show_menu_on_left_click(show_menu) {
+        log::warn!("Failed to set tray left-click menu: {}", e);
+    }
+}
+
+fn run(app: &AppHandle, action: TrayClickAction) {
+    log::debug!("Tray click: {:?}", action);
+    match action {
+        TrayClickAction::Nothing | TrayClickAction::ShowMenu => {}
+        TrayClickAction::ToggleWindow => {
+            let Some(window) = app.get_webview_window("main") else {
+                return;
+            };
+            let up_front = window.is_visible().unwrap_or(false)
+                && !window.is_minimized().unwrap_or(false)
+                && window.is_focused().unwrap_or(false);
+            if up_front {
+                let _ = window.hide();
+            } else {
+                crate::show_main_window(app);
+            }
+        }
+        TrayClickAction::OpenLastChat => {
+            let last = app.state::<Database>().with(|conn| {
+                conn.query_row(
+                    "SELECT conversation_id FROM messages ORDER BY timestamp DESC LIMIT 1",
+                    [],
+                    |row| row.get::<_, String>(0),
+                )
+            });
+            match last {
+                Ok(conversation) => actions::open_chat(app, &conversation),
+                Err(_) => crate::show_main_window(app),
+            }
+        }
+    }
+}
+
+/// Handle a tray icon event according to the click settings.
+pub fn handle(tray: &TrayIcon, event: TrayIconEvent) {
+    let app = tray.app_handle();
+    crate::positioner::remember(app, &event);
+    let clicks = app.state::<TrayClicks>();
+    let settings = settings(app);
+    match event {
+        TrayIconEvent::Click {
+            button: MouseButton::Left,
+            button_state: MouseButtonState::Up,
+            ..
+        } => {
+            let after_double = clicks
+                .last_double
+                .lock()
+                .unwrap()
+                .is_some_and(|at| at.elapsed() < DOUBLE_CLICK_TIME);
+            if after_double {
+                return;
+            }
+            if !cfg!(target_os = "windows") || settings.double_click == TrayClickAction::Nothing {
+                run(app, settings.click);
+                return;
+            }
+            // Wait for a possible second click
+            let app = app.clone();
+            let pending = tauri::async_runtime::spawn(async move {
+                tokio::time::sleep(DOUBLE_CLICK_TIME).await;
+                app.state::<TrayClicks>().pending.lock().unwrap().take();
+                run(&app, settings.click);
+            });
+            if let Some(previous) = clicks.pending.lock().unwrap().replace(pending) {
+                previous.abort();
+            }
+        }
+        TrayIconEvent::DoubleClick {
+            button: MouseButton::Left,
+            ..
+        } => {
+            if let Some(pending) = clicks.pending.lock().unwrap().take() {
+                pending.abort();
+            }
+            *clicks.last_double.lock().unwrap() = Some(Instant::now());
+            run(app, settings.double_click);
+        }
+        _ => {}
+    }
+}
+
+/// Apply the saved settings to the tray icon.
+pub fn init(tray: &TrayIcon) {
+    apply(tray, &settings(tray.app_handle()));
+}
+
+#[tauri::command]
+pub fn get_tray_click_settings(app: AppHandle) -> TrayClickSettings {
+    settings(&app)
+}
+
+#[tauri::command]
+pub fn set_tray_click_settings(app: AppHandle, settings: TrayClickSettings) -> Result<(), String> {
+    prefs::save(&app, SETTINGS_KEY, &settings)?;
+    if let Some(tray) = app.tray_by_id("main-tray") {
+        apply(&tray, &settings);
+    }
+    Ok(())
+}
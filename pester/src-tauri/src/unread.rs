@@ -0,0 +1,92 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::a11y::{A11yBus, A11yEvent};
+use crate::conversations;
+use crate::notifications::MessageGroups;
+use crate::prefs;
+
+const UNREAD_KEY: &str = "unread_counts";
+
+/// Unread message counts per conversation, owned by the backend so badges
+/// and other surfaces don't depend on the webview being alive.
+pub struct UnreadState {
+    counts: Mutex<HashMap<String, u32>>,
+}
+
+impl UnreadState {
+    pub fn load(app: &AppHandle) -> Self {
+        Self {
+            counts: Mutex::new(prefs::load(app, UNREAD_KEY).unwrap_or_default()),
+        }
+    }
+
+    fn update(&self, app: &AppHandle, apply: impl FnOnce(&mut HashMap<String, u32>)) {
+        let snapshot = {
+            let mut counts = self.counts.lock().unwrap();
+            apply(&mut counts);
+            counts.retain(|_, n| *n > 0);
+            counts.clone()
+        };
+        if let Err(e) = prefs::save(app, UNREAD_KEY, &snapshot) {
+            log::warn!("Failed to persist unread counts: {}", e);
+        }
+        app.state::<A11yBus>().publish(A11yEvent::UnreadChanged {
+            total: snapshot.values().sum(),
+        });
+        crate::badge::set_tray_tooltip(app, &snapshot);
+        let _ = app.emit("unread-changed", &snapshot);
+    }
+}
+
+impl UnreadState {
+    pub fn counts(&self) -> HashMap<String, u32> {
+        self.counts.lock().unwrap().clone()
+    }
+
+    /// Count one more message, unless the conversation is muted.
+    pub fn increment(&self, app: &AppHandle, conversation: String) {
+        if conversations::is_muted(app, &conversation) {
+            return;
+        }
+        self.update(app, |counts| *counts.entry(conversation).or_default() += 1);
+    }
+
+    pub fn mark_unread(&self, app: &AppHandle, conversation: String) {
+        self.update(app, |counts| {
+            let count = counts.entry(conversation).or_default();
+            *count = (*count).max(1);
+        });
+    }
+
+    pub fn mark_read(&self, app: &AppHandle, conversation: &str) {
+        self.update(app, |counts| {
+            counts.remove(conversation);
+        });
+        app.state::<MessageGroups>().forget(conversation);
+    }
+}
+
+#[tauri::command]
+pub fn get_unread_counts(unread: State<'_, UnreadState>) -> HashMap<String, u32> {
+    unread.counts()
+}
+
+/// Count one more unread message in `conversation`.
+#[tauri::command]
+pub fn increment_unread(app: AppHandle, unread: State<'_, UnreadState>, conversation: String) {
+    unread.increment(&app, conversation);
+}
+
+/// Flag a conversation as unread again after it has been read.
+#[tauri::command]
+pub fn mark_unread(app: AppHandle, unread: State<'_, UnreadState>, conversation: String) {
+    unread.mark_unread(&app, conversation);
+}
+
+#[tauri::command]
+pub fn mark_read(app: AppHandle, unread: State<'_, UnreadState>, conversation: String) {
+    unread.mark_read(&app, &conversation);
+}
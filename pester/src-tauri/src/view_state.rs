@@ -0,0 +1,62 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+use tauri::AppHandle;
+
+use crate::{clock, prefs};
+
+const VIEW_STATE_KEY: &str = "view_states";
+
+/// Conversations remembered; the least recently saved are dropped first.
+const MAX_ENTRIES: usize = 200;
+
+/// Where the user was reading in a conversation.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ViewState {
+    /// First message visible at the top of the list.
+    pub anchor_message_id: String,
+    /// Pixels the anchor was scrolled past the top edge.
+    pub offset: f64,
+    pub saved_at: u64,
+}
+
+/// Conversation ID → view state.
+type ViewStates = HashMap<String, ViewState>;
+
+#[tauri::command]
+pub fn save_view_state(
+    app: AppHandle,
+    conversation: String,
+    anchor_message_id: String,
+    offset: f64,
+) -> Result<(), String> {
+    let mut states: ViewStates = prefs::load(&app, VIEW_STATE_KEY).unwrap_or_default();
+    states.insert(
+        conversation,
+        ViewState {
+            anchor_message_id,
+            offset,
+            saved_at: clock::now_millis(),
+        },
+    );
+
+    if states.len() > MAX_ENTRIES {
+        let mut by_age: Vec<(String, u64)> = states
+            .iter()
+            .map(|(k, v)| (k.clone(), v.saved_at))
+            .collect();
+        by_age.sort_by_key(|(_, saved_at)| *saved_at);
+        for (conversation, _) in by_age.into_iter().take(states.len() - MAX_ENTRIES) {
+            states.remove(&conversation);
+        }
+    }
+
+    prefs::save(&app, VIEW_STATE_KEY, &states)
+}
+
+#[tauri::command]
+pub fn get_view_state(app: AppHandle, conversation: String) -> Option<ViewState> {
+    let mut states: ViewStates = prefs::load(&app, VIEW_STATE_KEY).unwrap_or_default();
+    states.remove(&conversation)
+}
@@ -0,0 +1,113 @@
+use std::sync::Mutex;
+
+use semver::Version;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::{features::FeatureFlags, prefs};
+
+/// Release notes bundled with the app, newest first.
+const CHANGELOG: &str = include_str!("../changelog.json");
+/// The newest version whose notes have been announced.
+const SEEN_KEY: &str = "whats_new_seen";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Highlight {
+    pub title: String,
+    pub body: String,
+    /// Only shown while this feature flag is on, so staged features are
+    /// announced to the people who have them.
+    #[serde(default, skip_serializing)]
+    pub feature: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Release {
+    pub version: String,
+    pub highlights: Vec<Highlight>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WhatsNew {
+    pub current_version: String,
+    /// Every release up to the running one, newest first.
+    pub releases: Vec<Release>,
+}
+
+/// Releases not announced yet, held until the main window has loaded.
+#[derive(Default)]
+pub struct WhatsNewState(Mutex<Vec<Release>>);
+
+/// Releases from `after` (exclusive) up to the running version, with
+/// highlights for disabled features left out.
+fn releases(app: &AppHandle, after: Option<&Version>) -> Vec<Release> {
+    let changelog: Vec<Release> = match serde_json::from_str(CHANGELOG) {
+        Ok(changelog) => changelog,
+        Err(e) => {
+            log::warn!("Bundled changelog is malformed: {}", e);
+            return Vec::new();
+        }
+    };
+    let current = &app.package_info().version;
+    let flags = app.state::<FeatureFlags>();
+
+    changelog
+        .into_iter()
+        .filter_map(|mut release| {
+            let version = Version::parse(&release.version).ok()?;
+            if version > *current || after.is_some_and(|after| version <= *after) {
+                return None;
+            }
+            release.highlights.retain(|h| {
+                h.feature
+                    .as_deref()
+                    .is_none_or(|feature| flags.is_enabled(feature))
+            });
+            (!release.highlights.is_empty()).then_some(release)
+        })
+        .collect()
+}
+
+/// Work out what's new since the last run. Fresh installs have nothing to
+/// catch up on. Needs `FeatureFlags` managed.
+pub fn check(app: &AppHandle) {
+    let current = app.package_info().version.clone();
+    let seen = prefs::load::<_, String>(app, SEEN_KEY).and_then(|v| Version::parse(&v).ok());
+
+    if let Some(seen) = &seen {
+        if *seen >= current {
+            return;
+        }
+        let unseen = releases(app, Some(seen));
+        log::info!(
+            "Updated from {} to {}, {} releases to announce",
+            seen,
+            current,
+            unseen.len()
+        );
+        *app.state::<WhatsNewState>().0.lock().unwrap() = unseen;
+    }
+    if let Err(e) = prefs::save(app, SEEN_KEY, &current.to_string()) {
+        log::warn!("Failed to save the seen version: {}", e);
+    }
+}
+
+/// Emit `show-whats-new` with the unseen releases, once, after the main
+/// window has loaded and can listen for it.
+pub fn announce(app: &AppHandle) {
+    let unseen = std::mem::take(&mut *app.state::<WhatsNewState>().0.lock().unwrap());
+    if !unseen.is_empty() {
+        let _ = app.emit("show-whats-new", &unseen);
+    }
+}
+
+#[tauri::command]
+pub fn get_whats_new(app: AppHandle) -> WhatsNew {
+    WhatsNew {
+        current_version: app.package_info().version.to_string(),
+        releases: releases(&app, None),
+    }
+}
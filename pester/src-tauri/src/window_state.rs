@@ -0,0 +1,109 @@
+use serde::{Deserialize, Serialize};
+use tauri::{Manager, PhysicalPosition, PhysicalSize, Position, Size, WebviewWindow};
+
+use crate::prefs;
+
+const MAIN_GEOMETRY_KEY: &str = "window_geometry";
+
+/// Where a window was and how big, in physical pixels.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Geometry {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    #[serde(default)]
+    pub maximized: bool,
+}
+
+/// `window`'s geometry now. While maximized the bounds from `previous` are
+/// kept, so un-maximizing after a restart goes back to them. `None` while
+/// minimized, when the position is off screen.
+pub fn capture(window: &WebviewWindow, previous: Option<Geometry>) -> Option<Geometry> {
+    if window.is_minimized().unwrap_or(false) {
+        return None;
+    }
+    let position = window.outer_position().ok()?;
+    let size = window.inner_size().ok()?;
+    let current = Geometry {
+        x: position.x,
+        y: position.y,
+        width: size.width,
+        height: size.height,
+        maximized: false,
+    };
+    if window.is_maximized().unwrap_or(false) {
+        return Some(Geometry {
+            maximized: true,
+            ..previous.unwrap_or(current)
+        });
+    }
+    Some(current)
+}
+
+/// Whether the top of a window at `geometry` is on a connected monitor,
+/// so it can still be grabbed.
+fn on_screen(window: &WebviewWindow, geometry: &Geometry) -> bool {
+    let x = geometry.x + geometry.width as i32 / 2;
+    let y = geometry.y + 16;
+    window.available_monitors().is_ok_and(|monitors| {
+        monitors.iter().any(|monitor| {
+            let origin = monitor.position();
+            let size = monitor.size();
+            (origin.x..origin.x + size.width as i32).contains(&x)
+                && (origin.y..origin.y + size.height as i32).contains(&y)
+        })
+    })
+}
+
+/// Put `window` back where `geometry` says. The size is kept even when
+/// the monitor it was on is gone.
+pub fn apply(window: &WebviewWindow, geometry: &Geometry) -> Result<(), String> {
+    window
+        .set_size(Size::Physical(PhysicalSize {
+            width: geometry.width,
+            height: geometry.height,
+        }))
+        .map_err(|e| e.to_string())?;
+    if on_screen(window, geometry) {
+        window
+            .set_position(Position::Physical(PhysicalPosition {
+                x: geometry.x,
+                y: geometry.y,
+            }))
+            .map_err(|e| e.to_string())?;
+    }
+    if geometry.maximized {
+        window.maximize().map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// Restore the main window's saved geometry. Returns false when there is
+/// none, e.g. on first launch.
+pub fn restore_main(window: &WebviewWindow) -> bool {
+    let Some(geometry) = prefs::load::<_, Geometry>(window.app_handle(), MAIN_GEOMETRY_KEY) else {
+        return false;
+    };
+    if let Err(e) = apply(window, &geometry) {
+        log::warn!("Failed to restore window geometry: {}", e);
+        return false;
+    }
+    true
+}
+
+/// Remember the main window's geometry, on every move and resize. Not in
+/// compact mode, which keeps the full size to go back to.
+pub fn save_main(window: &WebviewWindow) {
+    let app = window.app_handle();
+    if crate::compact_mode::is_compact(app) {
+        return;
+    }
+    let previous = prefs::load(app, MAIN_GEOMETRY_KEY);
+    let Some(geometry) = capture(window, previous) else {
+        return;
+    };
+    if let Err(e) = prefs::save(app, MAIN_GEOMETRY_KEY, &geometry) {
+        log::warn!("Failed to save window geometry: {}", e);
+    }
+}
@@ -1,6 +1,7 @@
 import { useState, useCallback, useEffect, useRef } from "react";
 import "./App.css";
 import { usePubSub } from "@/lib/use-pubsub";
+import { applyMessageFont } from "@/lib/fonts";
 import { Titlebar } from "@/components/titlebar";
 import { ContactsList } from "@/components/contacts-list";
 import { MessageView } from "@/components/message-view";
@@ -8,12 +9,7 @@ import { SettingsPage } from "@/components/settings-page";
 import { Button } from "@/components/ui/button";
 import { Spinner } from "@/components/ui/spinner";
 import { Skeleton } from "@/components/ui/skeleton";
-import { Settings } from "lucide-react";
-import {
-  isPermissionGranted,
-  requestPermission,
-  sendNotification,
-} from "@tauri-apps/plugin-notification";
+import { Settings, Minimize2, Maximize2 } from "lucide-react";
 import { listen } from "@tauri-apps/api/event";
 import { invoke } from "@tauri-apps/api/core";
 import {
@@ -29,6 +25,14 @@ import {
   register as registerShortcut,
 } from "@tauri-apps/plugin-global-shortcut";
 import { getCurrentWindow } from "@tauri-apps/api/window";
+import type {
+  ConversationSort,
+  InAppNotification,
+  NotificationPermission,
+  NotificationReply,
+  Presence,
+  RecentChat,
+} from "@/lib/types";
 
 type Page = "contacts" | "chat" | "settings";
 
@@ -44,7 +48,9 @@ function App() {
     register,
     ensureConversation,
     sendMessage,
+    cancelPendingSend,
     sendTyping,
+    serverNow,
   } = usePubSub();
 
   const [page, setPage] = useState<Page>("contacts");
@@ -52,6 +58,9 @@ function App() {
   const [recentChats, setRecentChats] = useState<string[]>([]);
   const [identity, setIdentity] = useState<string | null>(null);
   const [loading, setLoading] = useState(true);
+  const [banner, setBanner] = useState<InAppNotification | null>(null);
+  const [presence, setPresence] = useState<Presence>("online");
+  const [unreadCounts, setUnreadCounts] = useState<Record<string, number>>({});
   const initializedRef = useRef(false);
 
   // ── Bootstrap: load identity + contacts + register ─────────────────────
@@ -73,7 +82,10 @@ function App() {
         setRecentChats(recent);
 
         // Update tray with recent chats
-        await invoke("update_tray_menu", { recentUsers: recent }).catch(() => {});
+        await invoke("update_tray_menu", {
+          recentChats: recent.map((id): RecentChat => ({ id })),
+          status: "online",
+        }).catch(() => {});
 
         // Register global shortcut if saved
         if (shortcut) {
@@ -115,14 +127,54 @@ function App() {
   useEffect(() => {
     if (!loading) {
       persistRecentChats(recentChats).catch(() => {});
-      invoke("update_tray_menu", { recentUsers: recentChats }).catch(() => {});
+      const chats = recentChats.map((id): RecentChat => ({ id, unread: unreadCounts[id] ?? 0 }));
+      invoke("update_tray_menu", { recentChats: chats, status: presence }).catch(() => {});
     }
-  }, [recentChats, loading]);
+  }, [recentChats, unreadCounts, presence, loading]);
+
+  // ── Compact mode: the window as a small floating bar ───────────────────
+  const [compact, setCompact] = useState(false);
+  useEffect(() => {
+    invoke<boolean>("get_compact_mode").then(setCompact).catch(() => {});
+    const unlisten = listen<boolean>("compact-mode-changed", (event) => setCompact(event.payload));
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, []);
+  const setCompactMode = (enabled: boolean) => {
+    invoke("set_compact_mode", { enabled }).catch(() => {});
+  };
+
+  // ── Contacts in the chosen sort order ──────────────────────────────────
+  const [sortedContacts, setSortedContacts] = useState<string[]>([]);
+  const [sortMode, setSortMode] = useState<ConversationSort | null>(null);
+  useEffect(() => {
+    const unlisten = listen<ConversationSort>("conversation-sort-changed", (event) => setSortMode(event.payload));
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, []);
+  useEffect(() => {
+    invoke<string[]>("sort_conversations", { conversations: contacts })
+      .then(setSortedContacts)
+      .catch(() => setSortedContacts(contacts));
+  }, [contacts, unreadCounts, sortMode]);
+
+  useEffect(() => {
+    const unlisten = listen<Presence>("status-changed", (event) => setPresence(event.payload));
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, []);
 
-  // ── Listen for tray menu actions ───────────────────────────────────────
+  // ── Message font (custom fonts + emoji fallback) ───────────────────────
   useEffect(() => {
-    const unlisten = listen<string>("tray-action", (event) => {
-      const action = event.payload;
+    applyMessageFont().catch(() => {});
+  }, []);
+
+  // ── Listen for tray menu actions and deep links ────────────────────────
+  useEffect(() => {
+    const handleAction = (action: string) => {
       if (action === "new_contact") {
         setPage("settings");
       } else if (action.startsWith("chat:")) {
@@ -134,27 +186,67 @@ function App() {
         });
         ensureConversation(contactId);
         setActiveFriendId(contactId);
+        invoke("mark_read", { conversation: contactId }).catch(() => {});
         setPage("chat");
       }
-    });
+    };
+
+    // Deep links are held by the backend until picked up, so one that
+    // launched the app isn't lost before this listener exists
+    const takeDeepLink = () => {
+      invoke<string | null>("take_pending_deep_link")
+        .then((action) => action && handleAction(action))
+        .catch(() => {});
+    };
+
+    const unlisten = listen<string>("tray-action", (event) => handleAction(event.payload));
+    const unlistenDeepLink = listen("deep-link", takeDeepLink);
+    takeDeepLink();
+
     return () => {
       unlisten.then((fn) => fn());
+      unlistenDeepLink.then((fn) => fn());
     };
   }, [ensureConversation, setActiveFriendId]);
 
   // ── Notification for incoming messages ──────────────────────────────────
+  useEffect(() => {
+    let timer: ReturnType<typeof setTimeout> | undefined;
+    const unlisten = listen<InAppNotification>("in-app-notification", (event) => {
+      setBanner(event.payload);
+      clearTimeout(timer);
+      timer = setTimeout(() => setBanner(null), 5000);
+    });
+    return () => {
+      clearTimeout(timer);
+      unlisten.then((fn) => fn());
+    };
+  }, []);
+
+  useEffect(() => {
+    const unlisten = listen<NotificationReply>("notification-reply", (event) => {
+      const { conversation, text } = event.payload;
+      sendMessage(conversation, text);
+      invoke("mark_read", { conversation }).catch(() => {});
+    });
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, [sendMessage]);
+
   useEffect(() => {
     if (!userId) return;
 
-    const notify = async (fromUser: string, text: string) => {
-      let granted = await isPermissionGranted();
-      if (!granted) {
-        const perm = await requestPermission();
-        granted = perm === "granted";
-      }
-      if (granted) {
-        sendNotification({ title: fromUser, body: text });
+    const notify = async (conversation: string, fromUser: string, text: string) => {
+      const permission = await invoke<NotificationPermission>("get_notification_permission").catch(
+        () => "unknown",
+      );
+      if (permission === "not_determined") {
+        await invoke("request_notification_permission").catch(() => {});
       }
+      // Routed through Rust so OS Focus / Do Not Disturb is respected; it
+      // falls back to an in-app banner when the OS blocks toasts
+      await invoke("notify_message", { conversation, title: fromUser, body: text }).catch(() => {});
     };
 
     for (const conv of conversations.values()) {
@@ -163,16 +255,46 @@ function App() {
         const last = msgs[msgs.length - 1];
         if (
           last.fromUserId !== userId &&
+          !last.catchUp &&
           conv.friendId !== activeFriendId &&
-          Date.now() - last.timestamp < 2000
+          serverNow() - last.timestamp < 2000
         ) {
-          notify(last.fromUserId, last.text);
+          notify(conv.friendId, last.fromUserId, last.text);
+          invoke("show_bubble", {
+            conversation: conv.friendId,
+            sender: last.fromUserId,
+            snippet: last.text,
+          }).catch(() => {});
+          invoke("increment_unread", { conversation: conv.friendId })
+            .then(() => {
+              // Stops by itself once the window is focused
+              if (!document.hasFocus()) return invoke("start_tray_flash");
+            })
+            .catch(() => {});
+          invoke("request_attention", { critical: false }).catch(() => {});
         }
       }
     }
     // eslint-disable-next-line react-hooks/exhaustive-deps
   }, [conversations, userId, activeFriendId]);
 
+  // ── Unread badge on the tray and Dock icons ─────────────────────────────
+  useEffect(() => {
+    const showTotal = (counts: Record<string, number>) => {
+      setUnreadCounts(counts);
+      const total = Object.values(counts).reduce((sum, n) => sum + n, 0);
+      invoke("set_unread_count", { count: total }).catch(() => {});
+      invoke("set_dock_badge", { text: total > 0 ? String(total) : null }).catch(() => {});
+      invoke("set_taskbar_overlay", { count: total }).catch(() => {});
+    };
+
+    invoke<Record<string, number>>("get_unread_counts").then(showTotal).catch(() => {});
+    const unlisten = listen<Record<string, number>>("unread-changed", (event) => showTotal(event.payload));
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, []);
+
   // ── Contact actions ─────────────────────────────────────────────────────
   const addContact = useCallback((id: string) => {
     setContacts((prev) => {
@@ -190,16 +312,53 @@ function App() {
     (contactId: string) => {
       ensureConversation(contactId);
       setActiveFriendId(contactId);
+      invoke("mark_read", { conversation: contactId }).catch(() => {});
       setRecentChats((prev) => addToRecent(prev, contactId));
       setPage("chat");
     },
     [ensureConversation, setActiveFriendId]
   );
 
+  if (compact) {
+    const unread = Object.values(unreadCounts).reduce((sum, n) => sum + n, 0);
+    return (
+      <div
+        data-tauri-drag-region
+        className="flex items-center gap-2 h-screen w-screen px-3 bg-card select-none overflow-hidden"
+      >
+        <span className="text-xs font-semibold tracking-wide uppercase text-muted-foreground pointer-events-none">
+          pester
+        </span>
+        <span className="text-xs text-muted-foreground flex-1 truncate pointer-events-none">
+          {unread > 0 ? `${unread} unread` : ""}
+        </span>
+        <Button
+          variant="ghost"
+          size="icon-xs"
+          onClick={() => setCompactMode(false)}
+          className="hover:bg-muted"
+          title="Expand"
+        >
+          <Maximize2 className="size-3" />
+        </Button>
+      </div>
+    );
+  }
+
   // ── Loading state with skeleton ───────────────────────────────────────
   if (loading || status === "connecting" || status === "connected") {
     return (
       <div className="flex flex-col h-screen w-screen overflow-hidden bg-background">
+      {banner && (
+        <button
+          type="button"
+          onClick={() => setBanner(null)}
+          className="absolute top-2 inset-x-2 z-50 rounded-md border bg-popover px-3 py-2 text-left text-xs shadow-md"
+        >
+          <div className="font-medium truncate">{banner.title}</div>
+          <div className="text-muted-foreground truncate">{banner.body}</div>
+        </button>
+      )}
         <Titlebar />
         <div className="flex flex-col flex-1 p-3 gap-2">
           <Skeleton className="h-8 w-full" />
@@ -241,6 +400,15 @@ function App() {
     <div className="flex flex-col h-screen w-screen overflow-hidden bg-background">
       {page !== "settings" && page !== "chat" && (
         <Titlebar>
+          <Button
+            variant="ghost"
+            size="icon-xs"
+            onClick={() => setCompactMode(true)}
+            className="hover:bg-muted"
+            title="Compact mode"
+          >
+            <Minimize2 className="size-3" />
+          </Button>
           <Button
             variant="ghost"
             size="icon-xs"
@@ -254,7 +422,7 @@ function App() {
 
       {page === "contacts" && (
         <ContactsList
-          contacts={contacts}
+          contacts={sortedContacts}
           onSelectContact={handleSelectContact}
         />
       )}
@@ -266,11 +434,17 @@ function App() {
           userId={userId ?? ""}
           typingUsers={typingUsers}
           onSendMessage={sendMessage}
+          onCancelSend={cancelPendingSend}
           onSendTyping={sendTyping}
           onBack={() => {
             setActiveFriendId(null);
             setPage("contacts");
           }}
+          onPopOut={() => {
+            invoke("open_chat_window", { conversationId: activeFriendId }).catch(() => {});
+            setActiveFriendId(null);
+            setPage("contacts");
+          }}
         />
       )}
 
@@ -0,0 +1,48 @@
+import { useEffect, useState } from "react";
+import { invoke } from "@tauri-apps/api/core";
+import { getCurrentWindow } from "@tauri-apps/api/window";
+import { MessageCircle, X } from "lucide-react";
+import { Button } from "@/components/ui/button";
+
+interface BubbleContent {
+  conversation: string;
+  sender: string;
+  snippet: string;
+}
+
+/** Heads-up mini window shown by the backend while the main window is hidden */
+export function Bubble() {
+  const [content, setContent] = useState<BubbleContent | null>(null);
+
+  useEffect(() => {
+    invoke<BubbleContent | null>("get_bubble").then(setContent).catch(() => {});
+    const unlisten = getCurrentWindow().listen<BubbleContent>("bubble-updated", (event) =>
+      setContent(event.payload),
+    );
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, []);
+
+  if (!content) return null;
+
+  return (
+    <div
+      data-tauri-drag-region
+      className="flex items-center gap-2 h-screen px-2 bg-card border rounded-lg select-none"
+    >
+      <MessageCircle className="size-4 shrink-0 text-primary pointer-events-none" />
+      <button
+        type="button"
+        onClick={() => invoke("open_bubble_chat")}
+        className="flex flex-col min-w-0 flex-1 text-left cursor-pointer"
+      >
+        <span className="text-xs font-medium truncate">{content.sender}</span>
+        <span className="text-xs text-muted-foreground truncate">{content.snippet}</span>
+      </button>
+      <Button variant="ghost" size="icon-xs" onClick={() => invoke("dismiss_bubble")}>
+        <X className="size-3" />
+      </Button>
+    </div>
+  );
+}
@@ -0,0 +1,45 @@
+import { useEffect } from "react";
+import { invoke } from "@tauri-apps/api/core";
+import { getCurrentWindow } from "@tauri-apps/api/window";
+import { usePubSub } from "@/lib/use-pubsub";
+import { MessageView } from "@/components/message-view";
+
+/** One conversation in a window of its own, opened with `open_chat_window` */
+export function ChatWindow({ conversation }: { conversation: string }) {
+  const { userId, conversations, typingUsers, ensureConversation, sendMessage, cancelPendingSend, sendTyping } =
+    usePubSub();
+
+  useEffect(() => {
+    ensureConversation(conversation);
+  }, [conversation, ensureConversation]);
+
+  // Reading it here counts as reading it anywhere
+  useEffect(() => {
+    const markRead = () => invoke("mark_read", { conversation }).catch(() => {});
+    markRead();
+    const unlisten = getCurrentWindow().onFocusChanged(({ payload: focused }) => {
+      if (focused) markRead();
+    });
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, [conversation]);
+
+  const close = () => getCurrentWindow().close();
+
+  return (
+    <div className="h-screen bg-background">
+      <MessageView
+        friendId={conversation}
+        messages={conversations.get(conversation)?.messages ?? []}
+        userId={userId ?? ""}
+        typingUsers={typingUsers}
+        onSendMessage={sendMessage}
+        onCancelSend={cancelPendingSend}
+        onSendTyping={sendTyping}
+        onBack={close}
+        onClose={close}
+      />
+    </div>
+  );
+}
@@ -16,26 +16,35 @@ import {
   ItemSeparator,
 } from "@/components/ui/item";
 import { cn } from "@/lib/utils";
-import { ArrowLeft, Send, Check, Copy, Minus, X } from "lucide-react";
+import { ArrowLeft, Send, Check, Copy, Minus, X, EyeOff, Timer, Undo2, ExternalLink, Pin, PinOff } from "lucide-react";
 import { writeText } from "@tauri-apps/plugin-clipboard-manager";
 import { getCurrentWindow } from "@tauri-apps/api/window";
-import type { ChatMessage } from "@/lib/types";
+import { invoke } from "@tauri-apps/api/core";
+import { MAX_MESSAGE_LENGTH, type ChatMessage } from "@/lib/types";
 
 const MessageSchema = v.pipe(
   v.string(),
   v.trim(),
   v.nonEmpty("Message cannot be empty"),
-  v.maxLength(300, "Message must be 300 characters or less"),
+  v.maxLength(MAX_MESSAGE_LENGTH, `Message must be ${MAX_MESSAGE_LENGTH} characters or less`),
 );
 
+/** Undo windows the timer button cycles through, in seconds */
+const SEND_DELAYS = [0, 5, 10, 15];
+
 interface MessageViewProps {
   friendId: string;
   messages: ChatMessage[];
   userId: string;
   typingUsers: Map<string, number>;
-  onSendMessage: (targetUserId: string, text: string) => void;
+  onSendMessage: (targetUserId: string, text: string, incognito?: boolean) => void;
+  onCancelSend: (id: number) => void;
   onSendTyping: (targetUserId: string) => void;
   onBack: () => void;
+  /** Offered as a button when set, to move the chat to its own window */
+  onPopOut?: () => void;
+  /** What the close button does; hides the window by default */
+  onClose?: () => void;
 }
 
 export function MessageView({
@@ -44,17 +53,107 @@ export function MessageView({
   userId,
   typingUsers,
   onSendMessage,
+  onCancelSend,
   onSendTyping,
   onBack,
+  onPopOut,
+  onClose,
 }: MessageViewProps) {
   const [text, setText] = useState("");
   const [sentConfirm, setSentConfirm] = useState<string | null>(null);
   const bottomRef = useRef<HTMLDivElement>(null);
   const typingThrottle = useRef<number>(0);
+  /** Position while recalling sent messages with Up/Down, -1 when not recalling */
+  const historyIndex = useRef(-1);
+  /** Incognito sends skip local send history */
+  const [incognito, setIncognito] = useState(false);
+  /** Seconds a sent message can still be undone in this chat */
+  const [sendDelay, setSendDelay] = useState(0);
+  /** Spellcheck language for this chat, detected or picked by the user */
+  const [chatLanguage, setChatLanguage] = useState<string | null>(null);
+
+  /** Whether this window floats over other apps */
+  const [pinned, setPinned] = useState(false);
+  const listRef = useRef<HTMLDivElement>(null);
+  const viewStateTimer = useRef<number>(0);
+
+  useEffect(() => {
+    const win = getCurrentWindow();
+    invoke<boolean>("is_always_on_top", { windowLabel: win.label })
+      .then(setPinned)
+      .catch(() => {});
+    const unlisten = win.listen<boolean>("always-on-top-changed", ({ payload }) => setPinned(payload));
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, []);
+
+  const togglePinned = () => {
+    invoke("set_always_on_top", { windowLabel: getCurrentWindow().label, pinned: !pinned })
+      .then(() => setPinned(!pinned))
+      .catch(() => {});
+  };
 
   useEffect(() => {
     bottomRef.current?.scrollIntoView({ behavior: "smooth" });
-  });
+  }, [messages.length]);
+
+  // Restore where the user was reading when a chat is reopened
+  useEffect(() => {
+    invoke<{ anchorMessageId: string; offset: number } | null>("get_view_state", {
+      conversation: friendId,
+    })
+      .then((state) => {
+        const list = listRef.current;
+        if (!state || !list) return;
+        const anchor = list.querySelector<HTMLElement>(
+          `[data-message-id="${CSS.escape(state.anchorMessageId)}"]`,
+        );
+        if (anchor) list.scrollTop = anchor.offsetTop + state.offset;
+      })
+      .catch(() => {});
+  }, [friendId]);
+
+  const handleScroll = () => {
+    window.clearTimeout(viewStateTimer.current);
+    viewStateTimer.current = window.setTimeout(() => {
+      const list = listRef.current;
+      if (!list) return;
+      const anchor = Array.from(
+        list.querySelectorAll<HTMLElement>("[data-message-id]"),
+      ).find((el) => el.offsetTop + el.offsetHeight > list.scrollTop);
+      if (!anchor) return;
+      invoke("save_view_state", {
+        conversation: friendId,
+        anchorMessageId: anchor.dataset.messageId,
+        offset: list.scrollTop - anchor.offsetTop,
+      }).catch(() => {});
+    }, 300);
+  };
+
+  // Re-detect the conversation language whenever a chat is opened
+  useEffect(() => {
+    const samples = messages.slice(-20).map((m) => m.text);
+    invoke<{ language: string | null }>("detect_conversation_language", {
+      conversation: friendId,
+      samples,
+    })
+      .then((result) => setChatLanguage(result.language))
+      .catch(() => setChatLanguage(null));
+  }, [friendId]);
+
+  useEffect(() => {
+    invoke<number>("get_send_delay", { conversation: friendId })
+      .then(setSendDelay)
+      .catch(() => setSendDelay(0));
+  }, [friendId]);
+
+  const cycleSendDelay = () => {
+    const next = SEND_DELAYS[(SEND_DELAYS.indexOf(sendDelay) + 1) % SEND_DELAYS.length];
+    invoke("set_send_delay", { conversation: friendId, seconds: next })
+      .then(() => setSendDelay(next))
+      .catch(() => {});
+  };
 
   const validationError = (() => {
     if (!text.trim()) return null;
@@ -63,13 +162,26 @@ export function MessageView({
     return result.issues[0]?.message ?? "Invalid message";
   })();
 
-  const handleSubmit = (e: React.FormEvent) => {
+  const handleSubmit = async (e: React.FormEvent) => {
     e.preventDefault();
     const result = v.safeParse(MessageSchema, text);
     if (!result.success) return;
 
-    onSendMessage(friendId, result.output);
+    // Warn before sending anything that looks like a secret or card number
+    const scan = await invoke<{
+      requiresConfirmation: boolean;
+      findings: { label: string; preview: string }[];
+    }>("scan_outgoing_message", { conversation: friendId, text: result.output });
+    if (scan.requiresConfirmation) {
+      const list = scan.findings.map((f) => `• ${f.label}: ${f.preview}`).join("\n");
+      if (!window.confirm(`This message looks like it contains:\n${list}\n\nSend anyway?`)) {
+        return;
+      }
+    }
+
+    onSendMessage(friendId, result.output, incognito);
     setText("");
+    historyIndex.current = -1;
 
     // Show sent confirmation
     const confirmId = `${Date.now()}`;
@@ -79,6 +191,7 @@ export function MessageView({
 
   const handleInput = (value: string) => {
     setText(value);
+    historyIndex.current = -1;
     const now = Date.now();
     if (now - typingThrottle.current > 1000) {
       typingThrottle.current = now;
@@ -86,6 +199,35 @@ export function MessageView({
     }
   };
 
+  // ── Up/Down recalls previously sent messages (IRC-style) ────────────────
+  const handleKeyDown = async (e: React.KeyboardEvent<HTMLInputElement>) => {
+    if (e.key === "ArrowUp" && (text === "" || historyIndex.current >= 0)) {
+      e.preventDefault();
+      const next = historyIndex.current + 1;
+      const recalled = await invoke<string | null>("get_send_history", {
+        conversation: friendId,
+        index: next,
+      }).catch(() => null);
+      if (recalled !== null) {
+        historyIndex.current = next;
+        setText(recalled);
+      }
+    } else if (e.key === "ArrowDown" && historyIndex.current >= 0) {
+      e.preventDefault();
+      const next = historyIndex.current - 1;
+      historyIndex.current = next;
+      if (next < 0) {
+        setText("");
+        return;
+      }
+      const recalled = await invoke<string | null>("get_send_history", {
+        conversation: friendId,
+        index: next,
+      }).catch(() => null);
+      setText(recalled ?? "");
+    }
+  };
+
   const handleCopyMessage = async (msg: ChatMessage) => {
     try {
       await writeText(msg.text);
@@ -107,17 +249,35 @@ export function MessageView({
           <span className="text-xs font-medium truncate">{friendId}</span>
         </div>
         <div className="flex items-center gap-0.5">
+          <Button
+            variant="ghost"
+            size="icon-xs"
+            onClick={togglePinned}
+            className={cn("hover:bg-muted", pinned && "text-primary")}
+            title={pinned ? "Stop keeping on top" : "Keep on top"}
+          >
+            {pinned ? <PinOff className="size-3" /> : <Pin className="size-3" />}
+          </Button>
+          {onPopOut && (
+            <Button variant="ghost" size="icon-xs" onClick={onPopOut} className="hover:bg-muted" title="Open in new window">
+              <ExternalLink className="size-3" />
+            </Button>
+          )}
           <Button variant="ghost" size="icon-xs" onClick={() => getCurrentWindow().minimize()} className="hover:bg-muted">
             <Minus className="size-3" />
           </Button>
-          <Button variant="ghost" size="icon-xs" onClick={() => getCurrentWindow().hide()} className="hover:bg-destructive hover:text-white">
+          <Button variant="ghost" size="icon-xs" onClick={onClose ?? (() => getCurrentWindow().hide())} className="hover:bg-destructive hover:text-white">
             <X className="size-3" />
           </Button>
         </div>
       </div>
 
       {/* Messages */}
-      <div className="flex-1 overflow-y-auto">
+      <div
+        ref={listRef}
+        onScroll={handleScroll}
+        className="relative flex-1 overflow-y-auto"
+      >
         {messages.length === 0 ? (
           <div className="flex items-center justify-center h-full">
             <p className="text-xs text-muted-foreground">
@@ -129,7 +289,7 @@ export function MessageView({
             {messages.map((msg, i) => {
               const isMe = msg.fromUserId === userId;
               return (
-                <div key={msg.id}>
+                <div key={msg.id} data-message-id={msg.id}>
                   {i > 0 && <ItemSeparator />}
                   <Item
                     size="sm"
@@ -148,16 +308,33 @@ export function MessageView({
                       >
                         {isMe ? "You" : msg.fromUserId}
                         <span className="text-[10px] text-muted-foreground font-normal ml-1">
-                          {new Date(msg.timestamp).toLocaleTimeString([], {
-                            hour: "2-digit",
-                            minute: "2-digit",
-                          })}
+                          {msg.pendingSendId !== undefined
+                            ? "Sending…"
+                            : msg.progress
+                              ? `Sending ${msg.progress.sent}/${msg.progress.total}…`
+                              : msg.queuedSendId !== undefined
+                                ? "Waiting to send…"
+                                : new Date(msg.timestamp).toLocaleTimeString([], {
+                                    hour: "2-digit",
+                                    minute: "2-digit",
+                                  })}
                         </span>
                       </ItemTitle>
-                      <ItemDescription className="text-xs line-clamp-none!">
+                      <ItemDescription className="text-xs line-clamp-none! font-(family-name:--font-message)">
                         {msg.text}
                       </ItemDescription>
                     </ItemContent>
+                    {msg.pendingSendId !== undefined && (
+                      <Button
+                        variant="ghost"
+                        size="xs"
+                        className="shrink-0 text-[10px]"
+                        onClick={() => onCancelSend(msg.pendingSendId!)}
+                      >
+                        <Undo2 className="size-3" />
+                        Undo
+                      </Button>
+                    )}
                     <Button
                       variant="ghost"
                       size="icon-xs"
@@ -203,11 +380,34 @@ export function MessageView({
             value={text}
             type="text"
             onChange={(e) => handleInput(e.target.value)}
-            maxLength={300}
+            onKeyDown={handleKeyDown}
+            lang={chatLanguage ?? undefined}
+            spellCheck
+            maxLength={MAX_MESSAGE_LENGTH}
             className="text-xs h-8"
             autoFocus
           />
           <InputGroupAddon align="inline-end">
+            <InputGroupButton
+              type="button"
+              size="icon-xs"
+              variant="ghost"
+              title={incognito ? "Incognito: not saved to history" : "Send incognito"}
+              onClick={() => setIncognito((on) => !on)}
+              className={cn(incognito && "text-primary")}
+            >
+              <EyeOff className="size-3.5" />
+            </InputGroupButton>
+            <InputGroupButton
+              type="button"
+              size="icon-xs"
+              variant="ghost"
+              title={sendDelay > 0 ? `Undo window: ${sendDelay}s` : "Add an undo window"}
+              onClick={cycleSendDelay}
+              className={cn(sendDelay > 0 && "text-primary")}
+            >
+              <Timer className="size-3.5" />
+            </InputGroupButton>
             <InputGroupButton
               type="submit"
               size="icon-xs"
@@ -224,9 +424,11 @@ export function MessageView({
         {text.trim().length > 0 && (
           <p className={cn(
             "text-[10px] mt-0.5 px-1",
-            text.trim().length > 280 ? "text-destructive" : "text-muted-foreground"
+            text.trim().length > MAX_MESSAGE_LENGTH - 20 ? "text-destructive" : "text-muted-foreground"
           )}>
-            {text.trim().length}/300
+            {text.trim().length > 300
+              ? `${text.trim().length} characters · sent in parts`
+              : `${text.trim().length}/300`}
           </p>
         )}
       </form>
@@ -13,8 +13,9 @@ import {
   ItemGroup,
   ItemSeparator,
 } from "@/components/ui/item";
-import { ArrowLeft, Plus, Trash2, Minus, X, Copy, Check, Keyboard } from "lucide-react";
+import { ArrowLeft, Plus, Trash2, Minus, X, Copy, Check, Keyboard, Server } from "lucide-react";
 import { getCurrentWindow } from "@tauri-apps/api/window";
+import { invoke } from "@tauri-apps/api/core";
 import { enable, disable, isEnabled } from "@tauri-apps/plugin-autostart";
 import { writeText } from "@tauri-apps/plugin-clipboard-manager";
 import {
@@ -23,6 +24,7 @@ import {
   isRegistered,
 } from "@tauri-apps/plugin-global-shortcut";
 import { loadShortcut, persistShortcut } from "@/lib/use-identity";
+import type { ServerConfig } from "@/lib/types";
 
 const SHORTCUT_PRESETS = [
   { label: "Ctrl+Shift+P", value: "CommandOrControl+Shift+P" },
@@ -52,11 +54,50 @@ export function SettingsPage({
   const [currentShortcut, setCurrentShortcut] = useState<string | null>(null);
   const [shortcutLoading, setShortcutLoading] = useState(false);
 
+  const [server, setServer] = useState<ServerConfig>({ kind: "default" });
+  const [serverAddress, setServerAddress] = useState("");
+  const [serverError, setServerError] = useState<string | null>(null);
+  /** Shown once discovery fails, or when picked */
+  const [manualServer, setManualServer] = useState(false);
+  const [websocketUrl, setWebsocketUrl] = useState("");
+  const [apiUrl, setApiUrl] = useState("");
+
   useEffect(() => {
     isEnabled().then(setAutostart).catch(() => {});
     loadShortcut().then(setCurrentShortcut).catch(() => {});
+    invoke<ServerConfig>("get_server").then(setServer).catch(() => {});
   }, []);
 
+  const handleDiscover = async (e: React.FormEvent) => {
+    e.preventDefault();
+    setServerError(null);
+    try {
+      await invoke("discover_server", { address: serverAddress });
+      setServer(await invoke<ServerConfig>("get_server"));
+      setServerAddress("");
+    } catch (err) {
+      setServerError(String(err));
+      setManualServer(true);
+    }
+  };
+
+  const handleManualServer = async (e: React.FormEvent) => {
+    e.preventDefault();
+    setServerError(null);
+    try {
+      await invoke("set_server_endpoints", { websocket: websocketUrl, api: apiUrl || null });
+      setServer(await invoke<ServerConfig>("get_server"));
+      setManualServer(false);
+    } catch (err) {
+      setServerError(String(err));
+    }
+  };
+
+  const handleResetServer = async () => {
+    await invoke("reset_server").catch(() => {});
+    setServer({ kind: "default" });
+  };
+
   const handleAdd = (e: React.FormEvent) => {
     e.preventDefault();
     const trimmed = newContact.trim();
@@ -298,6 +339,64 @@ export function SettingsPage({
 
         <Separator className="my-2" />
 
+        {/* Server */}
+        <div className="px-3 py-2">
+          <Label className="text-xs mb-2 flex items-center gap-1.5">
+            <Server className="size-3" />
+            Server
+          </Label>
+          <div className="flex items-center justify-between mt-1.5">
+            <span className="text-[10px] text-muted-foreground truncate">
+              {server.kind === "default" && "Default server"}
+              {server.kind === "discovered" && `Discovered from ${server.domain}`}
+              {server.kind === "manual" && server.endpoints.websocket}
+            </span>
+            {server.kind !== "default" && (
+              <Button variant="ghost" size="sm" className="text-[10px] h-6 px-2" onClick={handleResetServer}>
+                Use default
+              </Button>
+            )}
+          </div>
+          <form onSubmit={handleDiscover} className="flex gap-2 mt-1.5">
+            <Input
+              placeholder="you@example.com"
+              value={serverAddress}
+              onChange={(e) => setServerAddress(e.target.value)}
+              className="h-7 text-xs flex-1"
+            />
+            <Button type="submit" size="sm" variant="outline" className="text-[10px] h-7 px-2" disabled={!serverAddress.trim()}>
+              Discover
+            </Button>
+          </form>
+          {manualServer ? (
+            <form onSubmit={handleManualServer} className="flex flex-col gap-1.5 mt-2">
+              <Input
+                placeholder="wss://chat.example.com"
+                value={websocketUrl}
+                onChange={(e) => setWebsocketUrl(e.target.value)}
+                className="h-7 text-xs"
+              />
+              <Input
+                placeholder="https://chat.example.com (optional)"
+                value={apiUrl}
+                onChange={(e) => setApiUrl(e.target.value)}
+                className="h-7 text-xs"
+              />
+              <Button type="submit" size="sm" variant="outline" className="text-[10px] h-6" disabled={!websocketUrl.trim()}>
+                Use these endpoints
+              </Button>
+            </form>
+          ) : (
+            <button type="button" className="text-[10px] text-muted-foreground underline mt-1.5" onClick={() => setManualServer(true)}>
+              Enter endpoints manually
+            </button>
+          )}
+          {serverError && <p className="text-[10px] text-destructive mt-1">{serverError}</p>}
+          <p className="text-[10px] text-muted-foreground mt-1">Takes effect on the next connection.</p>
+        </div>
+
+        <Separator className="my-2" />
+
         {/* Autostart toggle */}
         <div className="px-3 py-2">
           <Item size="sm" variant="default">
@@ -0,0 +1,31 @@
+import { convertFileSrc, invoke } from "@tauri-apps/api/core";
+
+interface CustomFont {
+  family: string;
+  file: string;
+  path: string;
+}
+
+interface MessageFont {
+  family: string | null;
+  stack: string;
+}
+
+/** Load imported fonts and point `--font-message` at the chosen stack */
+export async function applyMessageFont() {
+  const [custom, font] = await Promise.all([
+    invoke<CustomFont[]>("list_custom_fonts"),
+    invoke<MessageFont>("get_message_font"),
+  ]);
+
+  for (const f of custom) {
+    try {
+      const face = new FontFace(f.family, `url("${convertFileSrc(f.path)}")`);
+      document.fonts.add(await face.load());
+    } catch {
+      // unreadable font file, fall through to the next one
+    }
+  }
+
+  document.documentElement.style.setProperty("--font-message", font.stack);
+}
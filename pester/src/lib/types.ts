@@ -5,6 +5,14 @@ export interface ChatMessage {
   fromUserId: string;
   text: string;
   timestamp: number;
+  /** Outbox ID while the message can still be undone */
+  pendingSendId?: number;
+  /** Outbox ID while the message waits in the send queue */
+  queuedSendId?: number;
+  /** Held by the server while offline; the backend counts and summarizes these */
+  catchUp?: boolean;
+  /** Parts written so far while a long message goes out */
+  progress?: { sent: number; total: number };
 }
 
 export interface Conversation {
@@ -12,18 +20,198 @@ export interface Conversation {
   messages: ChatMessage[];
 }
 
+/** A conversation as it stood when Pester last quit */
+export interface SnapshotEntry {
+  conversationId: string;
+  /** Text cut to a preview */
+  lastMessage: ChatMessage & { conversationId: string };
+  unread: number;
+}
+
 // ── Server → Client events ──────────────────────────────────────────────────
 
 export type ServerMessage =
   | { type: "registered"; userId: string; timestamp: number }
   | { type: "kicked"; message: string }
-  | { type: "message"; fromUserId: string; text: string; timestamp: number }
+  | { type: "message"; id: string; fromUserId: string; text: string; timestamp: number; catchUp?: boolean }
   | { type: "typing"; fromUserId: string; timestamp: number }
   | { type: "error"; message: string };
 
+// ── Outbox ──────────────────────────────────────────────────────────────────
+
+/** Longest message the backend accepts; past 300 it's sent in parts */
+export const MAX_MESSAGE_LENGTH = 65536;
+
+export interface PendingSend {
+  id: number;
+  /** ID the message is stored in history under */
+  messageId: string;
+  conversation: string;
+  text: string;
+  sendAt: number;
+  /** Server time the message is stamped with */
+  timestamp: number;
+  /** Couldn't go out yet; retried from the send queue */
+  queued: boolean;
+  /** Kept out of history and off the disk */
+  incognito: boolean;
+}
+
+export interface SendFinalized {
+  id: number;
+  conversation: string;
+  outcome: "sent" | "cancelled" | "queued" | "failed";
+  error: string | null;
+}
+
+/** Typed into a message notification's reply field */
+export interface NotificationReply {
+  conversation: string;
+  text: string;
+}
+
+export interface SendProgress {
+  id: number;
+  conversation: string;
+  timestamp: number;
+  sent: number;
+  total: number;
+}
+
+export interface MessageSent {
+  id: number;
+  conversation: string;
+  timestamp: number;
+}
+
+export interface MessageFailed {
+  id: number;
+  conversation: string;
+  error: string;
+  attempts: number;
+  /** false once the message has been dropped from the queue */
+  willRetry: boolean;
+}
+
+export interface ReleaseHighlight {
+  title: string;
+  body: string;
+}
+
+export interface Release {
+  version: string;
+  highlights: ReleaseHighlight[];
+}
+
+/** From `get_whats_new`; `show-whats-new` carries only the unseen releases */
+export interface WhatsNew {
+  currentVersion: string;
+  releases: Release[];
+}
+
+/** Daily quiet hours; times are "HH:MM:SS" local, days like "Mon" */
+export interface DndSchedule {
+  enabled: boolean;
+  start: string;
+  end: string;
+  /** Days quiet hours start on, every day when empty */
+  days: string[];
+}
+
+export type SoundEvent = "message" | "reminder" | "send_failed";
+
+/** Sound IDs are a built-in name, a path to an audio file, or "none" */
+export interface SoundSettings {
+  enabled: boolean;
+  /** From 0 to 1 */
+  volume: number;
+  events: Partial<Record<SoundEvent, string>>;
+  /** Message sounds per contact */
+  contacts: Record<string, string>;
+}
+
+export type TrayClickAction = "nothing" | "toggle_window" | "open_last_chat" | "show_menu";
+
+/** Left clicks on the tray icon; double clicks are Windows only */
+export interface TrayClickSettings {
+  click: TrayClickAction;
+  doubleClick: TrayClickAction;
+}
+
+/** A recent chat in the tray menu */
+export interface RecentChat {
+  id: string;
+  /** Shown instead of the ID when set */
+  name?: string;
+  unread?: number;
+}
+
+export type Presence = "online" | "away" | "do_not_disturb" | "invisible";
+
+/** How conversation lists are ordered, set with `set_conversation_sort` */
+export type ConversationSort = "recency" | "unread_first" | "alphabetical" | "pinned_then_frecency";
+
+export type NotificationPermission = "granted" | "denied" | "not_determined" | "unknown";
+
+/** A notification the OS blocks, shown as a banner instead */
+export interface InAppNotification {
+  title: string;
+  body: string;
+}
+
+export interface DndState {
+  active: boolean;
+  reason: "schedule" | "snooze" | null;
+  /** When notifications resume, while active */
+  until: number | null;
+  schedule: DndSchedule;
+  snoozedUntil: number | null;
+}
+
+export interface BackupStatus {
+  enabled: boolean;
+  folder: string | null;
+  lastBackupAt: number | null;
+  /** Whether this device has a backup key */
+  hasKey: boolean;
+  /** Task of a backup or restore in progress */
+  runningTask: number | null;
+}
+
 // ── Client → Server events ──────────────────────────────────────────────────
 
 export type ClientMessage =
   | { type: "register"; userId: string }
   | { type: "message"; targetUserId: string; text: string }
   | { type: "typing"; targetUserId: string };
+
+/** Where a server listens, from `/.well-known/pester` or entered by hand */
+export interface ServerEndpoints {
+  websocket: string;
+  api: string | null;
+}
+
+/** Result of `get_server` */
+export type ServerConfig =
+  | { kind: "default" }
+  | { kind: "discovered"; domain: string }
+  | { kind: "manual"; endpoints: ServerEndpoints };
+
+/** One record from `read_logs`, newest first */
+export interface LogEntry {
+  level: "ERROR" | "WARN" | "INFO" | "DEBUG" | "TRACE";
+  module: string;
+  timestamp: string;
+  message: string;
+}
+
+export interface LogCursor {
+  file: string;
+  offset: number;
+}
+
+export interface LogPage {
+  entries: LogEntry[];
+  /** Pass back to `read_logs` for the next, older page */
+  nextCursor: LogCursor | null;
+}
@@ -1,21 +1,51 @@
 import { useCallback, useEffect, useRef, useState } from "react";
 import * as v from "valibot";
-import type { Conversation, ChatMessage, ServerMessage } from "./types";
-import TauriWebSocket from "@tauri-apps/plugin-websocket";
+import type {
+  Conversation,
+  ChatMessage,
+  MessageFailed,
+  MessageSent,
+  PendingSend,
+  SendFinalized,
+  SendProgress,
+  ServerMessage,
+  SnapshotEntry,
+} from "./types";
+import { MAX_MESSAGE_LENGTH } from "./types";
+import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
 
-const WS_URL = "ws://localhost:4000";
+/** Add a sent message to local send history (or just count it, if incognito) */
+function recordSent(conversation: string, text: string, incognito: boolean) {
+  if (incognito) {
+    invoke("record_incognito_message", { conversation }).catch(() => {});
+  } else {
+    invoke("record_sent_message", { conversation, text }).catch(() => {});
+  }
+}
+
+/** Append to the backend's connection timeline (support diagnostics) */
+function recordConnectionEvent(kind: string) {
+  invoke("record_connection_event", { kind }).catch(() => {});
+}
 
 const MessageTextSchema = v.pipe(
   v.string(),
   v.trim(),
   v.nonEmpty("Message cannot be empty"),
-  v.maxLength(300, "Message must be 300 characters or less"),
+  v.maxLength(MAX_MESSAGE_LENGTH, `Message must be ${MAX_MESSAGE_LENGTH} characters or less`),
 );
 
 export type ConnectionStatus = "disconnected" | "connecting" | "connected" | "registered";
 
+/** Payload of the backend's `connection-state` event */
+interface ConnectionState {
+  status: ConnectionStatus;
+  userId: string | null;
+  error: string | null;
+}
+
 export function usePubSub() {
-  const wsRef = useRef<TauriWebSocket | null>(null);
   const [status, setStatus] = useState<ConnectionStatus>("disconnected");
   const [userId, setUserId] = useState<string | null>(null);
   const [conversations, setConversations] = useState<Map<string, Conversation>>(new Map());
@@ -24,21 +54,41 @@ export function usePubSub() {
   const [error, setError] = useState<string | null>(null);
 
   const typingTimersRef = useRef<Map<string, ReturnType<typeof setTimeout>>>(new Map());
-  const userIdRef = useRef<string | null>(null);
+  /** server - local clock offset, measured by the backend during register */
+  const clockOffsetRef = useRef(0);
+  /** Messages inside their undo window, by outbox ID */
+  const pendingSendsRef = useRef<Map<number, { text: string; incognito: boolean }>>(new Map());
+  /** Messages waiting in the backend's send queue, by outbox ID */
+  const queuedSendsRef = useRef<Map<number, { text: string; incognito: boolean }>>(new Map());
 
-  // Keep ref in sync for use inside WS listener closure
+  // Network changes often explain a burst of disconnects
   useEffect(() => {
-    userIdRef.current = userId;
-  }, [userId]);
-
-  // ── Send helper ───────────────────────────────────────────────────────────
-  const send = useCallback((msg: object) => {
-    const ws = wsRef.current;
-    if (ws) {
-      ws.send(JSON.stringify(msg));
-    }
+    const online = () => recordConnectionEvent("network_online");
+    const offline = () => recordConnectionEvent("network_offline");
+    window.addEventListener("online", online);
+    window.addEventListener("offline", offline);
+    return () => {
+      window.removeEventListener("online", online);
+      window.removeEventListener("offline", offline);
+    };
+  }, []);
+
+  // Start from the last measured offset until the next handshake refines it
+  const refreshClockOffset = useCallback(() => {
+    invoke<{ offsetMs: number }>("get_clock_skew")
+      .then((skew) => {
+        clockOffsetRef.current = skew.offsetMs;
+      })
+      .catch(() => {});
   }, []);
 
+  useEffect(() => {
+    refreshClockOffset();
+  }, [refreshClockOffset]);
+
+  // ── Current time on the server's clock ──────────────────────────────────
+  const serverNow = useCallback(() => Date.now() + clockOffsetRef.current, []);
+
   // ── Ensure a conversation exists for a given friendId ─────────────────────
   const ensureConversation = useCallback((friendId: string) => {
     setConversations((prev) => {
@@ -49,26 +99,73 @@ export function usePubSub() {
     });
   }, []);
 
+  // ── Load stored history ──────────────────────────────────────────────────
+  const loadHistory = useCallback(async () => {
+    const stored = await invoke<{ conversationId: string }[]>("list_stored_conversations").catch(() => []);
+    const loaded = await Promise.all(
+      stored.map(({ conversationId }) =>
+        invoke<(ChatMessage & { conversationId: string })[]>("get_messages", { conversationId })
+          .then((messages) => [conversationId, messages] as const)
+          .catch(() => [conversationId, []] as const),
+      ),
+    );
+    setConversations((prev) => {
+      const next = new Map(prev);
+      for (const [friendId, history] of loaded) {
+        // Keep anything that arrived while history was loading
+        const live = next.get(friendId)?.messages ?? [];
+        const known = new Set(history.map((m) => m.id));
+        const messages: ChatMessage[] = [
+          ...history.map(({ id, fromUserId, text, timestamp }) => ({ id, fromUserId, text, timestamp })),
+          ...live.filter((m) => !known.has(m.id)),
+        ];
+        next.set(friendId, { friendId, messages });
+      }
+      return next;
+    });
+  }, []);
+
+  // Paint last session's list right away; loadHistory replaces it
+  useEffect(() => {
+    invoke<SnapshotEntry[]>("get_conversation_snapshot")
+      .then((snapshot) =>
+        setConversations((prev) => {
+          const next = new Map(prev);
+          for (const { conversationId, lastMessage } of snapshot) {
+            if (next.has(conversationId)) continue;
+            const { id, fromUserId, text, timestamp } = lastMessage;
+            next.set(conversationId, {
+              friendId: conversationId,
+              messages: [{ id, fromUserId, text, timestamp }],
+            });
+          }
+          return next;
+        }),
+      )
+      .catch(() => {});
+  }, []);
+
   // ── Message handler ──────────────────────────────────────────────────────
   const handleMessage = useCallback((msg: ServerMessage) => {
     switch (msg.type) {
       case "registered":
+        // The backend measured the clock offset during the handshake
         setUserId(msg.userId);
-        setStatus("registered");
+        refreshClockOffset();
+        loadHistory();
         break;
 
       case "kicked":
-        setError(msg.message);
-        setStatus("disconnected");
         setUserId(null);
         break;
 
       case "message": {
         const chatMsg: ChatMessage = {
-          id: `${msg.fromUserId}-${msg.timestamp}`,
+          id: msg.id,
           fromUserId: msg.fromUserId,
           text: msg.text,
           timestamp: msg.timestamp,
+          catchUp: msg.catchUp,
         };
         setConversations((prev) => {
           const next = new Map(prev);
@@ -120,100 +217,210 @@ export function usePubSub() {
         setError(msg.message);
         break;
     }
-  }, []);
+  }, [refreshClockOffset, loadHistory]);
+
+  // ── Backend connection events ────────────────────────────────────────────
+  // The socket lives in the backend, so it survives page reloads: pick up
+  // its current state on mount, then follow along.
+  useEffect(() => {
+    const applyState = (state: ConnectionState) => {
+      setStatus(state.status);
+      if (state.error) setError(state.error);
+    };
+
+    invoke<ConnectionState>("get_connection_state")
+      .then((state) => {
+        applyState(state);
+        if (state.status === "registered") {
+          setUserId(state.userId);
+          loadHistory();
+        }
+      })
+      .catch(() => {});
+
+    const unlistenState = listen<ConnectionState>("connection-state", (event) => {
+      applyState(event.payload);
+    });
+    const unlistenMessage = listen<ServerMessage>("server-message", (event) => {
+      handleMessage(event.payload);
+    });
+    return () => {
+      unlistenState.then((f) => f());
+      unlistenMessage.then((f) => f());
+    };
+  }, [handleMessage, loadHistory]);
 
   // ── Register (connect + subscribe) ───────────────────────────────────────
   const register = useCallback(async (id: string) => {
-    if (wsRef.current) {
-      await wsRef.current.disconnect();
-    }
-
-    setStatus("connecting");
     setError(null);
-
     try {
-      const ws = await TauriWebSocket.connect(WS_URL);
-      wsRef.current = ws;
-      setStatus("connected");
-
-      ws.addListener((rawMsg) => {
-        if (typeof rawMsg === "object" && rawMsg !== null) {
-          const envelope = rawMsg as { type?: string; data?: string };
-          if (envelope.type === "Text" && envelope.data) {
-            try {
-              const parsed: ServerMessage = JSON.parse(envelope.data);
-              handleMessage(parsed);
-            } catch {
-              // ignore parse errors
-            }
-          } else if (envelope.type === "Close") {
-            setStatus("disconnected");
-            wsRef.current = null;
-            // Auto-reconnect after a short delay
-            setTimeout(() => {
-              if (userIdRef.current) {
-                register(userIdRef.current);
-              }
-            }, 3000);
-          }
-        }
-      });
-
-      await ws.send(JSON.stringify({ type: "register", userId: id }));
-    } catch {
-      setError("Connection failed. Is the server running?");
-      setStatus("disconnected");
+      await invoke("connect", { userId: id });
+    } catch (e) {
+      setError(String(e));
     }
-  }, [handleMessage]);
+  }, []);
 
   // ── Actions ──────────────────────────────────────────────────────────────
   const sendMessage = useCallback(
-    (targetUserId: string, text: string) => {
+    (targetUserId: string, text: string, incognito = false) => {
       if (!userId) return;
       const result = v.safeParse(MessageTextSchema, text);
       if (!result.success) return;
       const validText = result.output;
 
-      send({ type: "message", targetUserId, text: validText });
+      invoke<PendingSend>("send_message", { targetUserId, text: validText, incognito })
+        .then((pending) => {
+          // With a send delay the message waits in the outbox until
+          // `send-finalized`; without one it has already gone out, or is
+          // queued until `message-sent`
+          const delayed = pending.sendAt > Date.now();
+          if (delayed) {
+            pendingSendsRef.current.set(pending.id, { text: validText, incognito });
+          } else if (pending.queued) {
+            queuedSendsRef.current.set(pending.id, { text: validText, incognito });
+          } else {
+            recordSent(targetUserId, validText, incognito);
+          }
+
+          // Append to local conversation, stamped by the backend in server time
+          // so it orders correctly against incoming messages and matches history
+          const chatMsg: ChatMessage = {
+            id: pending.messageId,
+            fromUserId: userId,
+            text: validText,
+            timestamp: pending.timestamp,
+            pendingSendId: delayed ? pending.id : undefined,
+            queuedSendId: pending.queued ? pending.id : undefined,
+          };
+          setConversations((prev) => {
+            const next = new Map(prev);
+            const existing = next.get(targetUserId);
+            if (existing) {
+              next.set(targetUserId, {
+                ...existing,
+                messages: [...existing.messages, chatMsg],
+              });
+            } else {
+              next.set(targetUserId, {
+                friendId: targetUserId,
+                messages: [chatMsg],
+              });
+            }
+            return next;
+          });
+        })
+        .catch((e) => setError(String(e)));
+    },
+    [userId]
+  );
+
+  const cancelPendingSend = useCallback((id: number) => {
+    invoke("cancel_pending_send", { id }).catch(() => {});
+  }, []);
+
+  // ── Undo window closed: keep, drop or flag the pending message ───────────
+  useEffect(() => {
+    const unlisten = listen<SendFinalized>("send-finalized", (event) => {
+      const { id, conversation, outcome, error } = event.payload;
+      const pending = pendingSendsRef.current.get(id);
+      if (!pending) return;
+      pendingSendsRef.current.delete(id);
+
+      if (outcome === "sent") {
+        recordSent(conversation, pending.text, pending.incognito);
+      } else if (outcome === "queued") {
+        queuedSendsRef.current.set(id, pending);
+      } else if (outcome === "failed") {
+        setError(error ?? "Message could not be sent");
+      }
 
-      // Append to local conversation
-      const chatMsg: ChatMessage = {
-        id: `${userId}-${Date.now()}`,
-        fromUserId: userId,
-        text: validText,
-        timestamp: Date.now(),
-      };
       setConversations((prev) => {
+        const existing = prev.get(conversation);
+        if (!existing) return prev;
+        const messages =
+          outcome === "sent" || outcome === "queued"
+            ? existing.messages.map((m) =>
+                m.pendingSendId === id
+                  ? { ...m, pendingSendId: undefined, queuedSendId: outcome === "queued" ? id : undefined }
+                  : m,
+              )
+            : existing.messages.filter((m) => m.pendingSendId !== id);
         const next = new Map(prev);
-        const existing = next.get(targetUserId);
-        if (existing) {
-          next.set(targetUserId, {
-            ...existing,
-            messages: [...existing.messages, chatMsg],
-          });
-        } else {
-          next.set(targetUserId, {
-            friendId: targetUserId,
-            messages: [chatMsg],
-          });
-        }
+        next.set(conversation, { ...existing, messages });
         return next;
       });
-    },
-    [send, userId]
-  );
+    });
+    return () => {
+      unlisten.then((f) => f());
+    };
+  }, []);
+
+  // ── Send queue: a queued message went out or was given up on ────────────
+  useEffect(() => {
+    const settle = (conversation: string, id: number, sent: boolean) => {
+      setConversations((prev) => {
+        const existing = prev.get(conversation);
+        if (!existing) return prev;
+        const messages = sent
+          ? existing.messages.map((m) => (m.queuedSendId === id ? { ...m, queuedSendId: undefined } : m))
+          : existing.messages.filter((m) => m.queuedSendId !== id);
+        const next = new Map(prev);
+        next.set(conversation, { ...existing, messages });
+        return next;
+      });
+    };
+
+    const unlistenSent = listen<MessageSent>("message-sent", (event) => {
+      const { id, conversation } = event.payload;
+      const queued = queuedSendsRef.current.get(id);
+      if (!queued) return;
+      queuedSendsRef.current.delete(id);
+      recordSent(conversation, queued.text, queued.incognito);
+      settle(conversation, id, true);
+    });
+    const unlistenFailed = listen<MessageFailed>("message-failed", (event) => {
+      const { id, conversation, error, willRetry } = event.payload;
+      if (willRetry || !queuedSendsRef.current.delete(id)) return;
+      setError(error || "Message could not be sent");
+      settle(conversation, id, false);
+    });
+    return () => {
+      unlistenSent.then((f) => f());
+      unlistenFailed.then((f) => f());
+    };
+  }, []);
+
+  // ── Long messages: show how many parts have gone out ────────────────────
+  useEffect(() => {
+    const unlisten = listen<SendProgress>("send-progress", (event) => {
+      const { conversation, timestamp, sent, total } = event.payload;
+      setConversations((prev) => {
+        const existing = prev.get(conversation);
+        if (!existing) return prev;
+        const messages = existing.messages.map((m) =>
+          m.timestamp === timestamp && m.fromUserId !== conversation
+            ? { ...m, progress: sent < total ? { sent, total } : undefined }
+            : m,
+        );
+        const next = new Map(prev);
+        next.set(conversation, { ...existing, messages });
+        return next;
+      });
+    });
+    return () => {
+      unlisten.then((f) => f());
+    };
+  }, []);
 
   const sendTyping = useCallback(
     (targetUserId: string) => {
-      send({ type: "typing", targetUserId });
+      invoke("send_typing", { targetUserId }).catch(() => {});
     },
-    [send]
+    []
   );
 
   const disconnect = useCallback(async () => {
-    if (wsRef.current) {
-      await wsRef.current.disconnect();
-    }
+    await invoke("disconnect").catch(() => {});
     setUserId(null);
     setConversations(new Map());
     setActiveFriendId(null);
@@ -221,9 +428,9 @@ export function usePubSub() {
   }, []);
 
   // ── Cleanup on unmount ───────────────────────────────────────────────────
+  // The connection itself stays up; only `disconnect` closes it.
   useEffect(() => {
     return () => {
-      wsRef.current?.disconnect();
       for (const timer of typingTimersRef.current.values()) {
         clearTimeout(timer);
       }
@@ -242,7 +449,9 @@ export function usePubSub() {
     register,
     ensureConversation,
     sendMessage,
+    cancelPendingSend,
     sendTyping,
     disconnect,
+    serverNow,
   };
 }
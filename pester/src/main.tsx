@@ -1,6 +1,8 @@
 import React from "react";
 import ReactDOM from "react-dom/client";
 import App from "./App";
+import { Bubble } from "@/components/bubble";
+import { ChatWindow } from "@/components/chat-window";
 import { ThemeProvider } from "@/components/theme-provider";
 
 // Attach Tauri logs to console in dev mode
@@ -15,7 +17,13 @@ if (import.meta.env.DEV) {
 ReactDOM.createRoot(document.getElementById("root") as HTMLElement).render(
   <React.StrictMode>
     <ThemeProvider defaultTheme="dark" storageKey="pester-ui-theme">
-      <App />
+      {window.location.hash === "#bubble" ? (
+        <Bubble />
+      ) : window.location.hash.startsWith("#chat/") ? (
+        <ChatWindow conversation={decodeURIComponent(window.location.hash.slice("#chat/".length))} />
+      ) : (
+        <App />
+      )}
     </ThemeProvider>
   </React.StrictMode>,
 );